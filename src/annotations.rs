@@ -0,0 +1,105 @@
+//! Turns the diagnostics of rustc and the errors of cargo-run-wasm into GitHub Actions workflow commands, for `--annotations github`,
+//! so that a failing wasm build shows up on the lines of the pull request diff that caused it.
+//!
+//! See <https://docs.github.com/en/actions/using-workflows/workflow-commands-for-github-actions#setting-an-error-message>.
+
+use serde_json::Value;
+
+/// Whether running in GitHub Actions, where annotations are printed unless disabled.
+pub(crate) fn detect() -> bool {
+    matches!(std::env::var("GITHUB_ACTIONS").as_deref(), Ok("true"))
+}
+
+/// The workflow command annotating a rustc diagnostic, the `message` of a cargo `compiler-message`,
+/// or None for the notes and summaries that dont need one of their own.
+pub(crate) fn compiler_message(message: &Value) -> Option<String> {
+    let command = match message["level"].as_str()? {
+        "error" | "error: internal compiler error" => "error",
+        "warning" => "warning",
+        _ => return None,
+    };
+    let text = message["message"].as_str()?;
+    let spans = message["spans"]
+        .as_array()
+        .map(|x| x.as_slice())
+        .unwrap_or_default();
+    // Already covered by the diagnostics they summarize.
+    if spans.is_empty()
+        && (text.starts_with("aborting due to")
+            || text.ends_with("warning emitted")
+            || text.ends_with("warnings emitted"))
+    {
+        return None;
+    }
+    let mut properties = vec![];
+    if let Some(span) = spans
+        .iter()
+        .find(|x| x["is_primary"].as_bool() == Some(true))
+    {
+        if let Some(file) = span["file_name"].as_str() {
+            properties.push(format!("file={}", escape_property(file)));
+            for (name, key) in [
+                ("line", "line_start"),
+                ("endLine", "line_end"),
+                ("col", "column_start"),
+                ("endColumn", "column_end"),
+            ] {
+                if let Some(value) = span[key].as_u64() {
+                    properties.push(format!("{}={}", name, value));
+                }
+            }
+        }
+    }
+    properties.push(format!("title={}", escape_property(text)));
+    // The rendered diagnostic includes the notes and suggestions, which the title alone would leave out.
+    let body = message["rendered"]
+        .as_str()
+        .map(strip_ansi)
+        .unwrap_or_else(|| text.to_string());
+    Some(format!(
+        "::{} {}::{}",
+        command,
+        properties.join(","),
+        escape_data(body.trim_end())
+    ))
+}
+
+/// The workflow command annotating an error of cargo-run-wasm itself, e.g. wasm-bindgen failing.
+pub(crate) fn error(message: &str) -> String {
+    format!(
+        "::error title={}::{}",
+        escape_property("cargo-run-wasm"),
+        escape_data(&strip_ansi(message))
+    )
+}
+
+fn escape_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(property: &str) -> String {
+    escape_data(property)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+/// Removes the color escape codes of a diagnostic rendered for a terminal.
+fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // A control sequence ends at its first letter, e.g. the `m` of `\x1b[1;31m`.
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
@@ -0,0 +1,257 @@
+//! Packs the `--dist` output into a zip or tarball for `--archive`, with the index.html at the root of the archive as hosts such as itch.io expect.
+//!
+//! Only the small subset of each format needed for a folder of files is written, rather than depending on a crate for it.
+//! Every entry gets the same fixed modification time, so that building the same files gives the same archive.
+
+use crate::Error;
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl Format {
+    /// Picks the format from the extension of `path`.
+    pub fn from_path(path: &Path) -> Result<Format, String> {
+        let name = path
+            .file_name()
+            .map(|x| x.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        let format = if name.ends_with(".zip") {
+            Format::Zip
+        } else if name.ends_with(".tar") {
+            Format::Tar
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Format::TarGz
+        } else {
+            return Err(format!(
+                "Invalid --archive {:?}, expected a path ending in .zip, .tar.gz, .tgz or .tar",
+                path.display()
+            ));
+        };
+        if format == Format::TarGz && cfg!(not(feature = "flate2")) {
+            return Err(
+                "Writing a .tar.gz archive requires the flate2 feature, which the server feature enables, use .zip or .tar instead"
+                    .to_string(),
+            );
+        }
+        Ok(format)
+    }
+}
+
+/// Writes every file in `dir` to the archive at `path`, leaving out the precompressed `.br` and `.gz` copies,
+/// since hosts that take an archive serve the files themselves.
+pub(crate) fn write(dir: &Path, path: &Path) -> Result<(), Error> {
+    let format = Format::from_path(path).map_err(Error::InvalidArgs)?;
+    let mut files = vec![];
+    find_files(dir, &mut files)?;
+    files.sort();
+    let mut entries = vec![];
+    for file in files {
+        let name = file
+            .strip_prefix(dir)
+            .unwrap()
+            .components()
+            .map(|x| x.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .join("/");
+        let contents = std::fs::read(&file)
+            .map_err(Error::io(format!("Failed to read {}", file.display())))?;
+        entries.push((name, contents));
+    }
+    let archive = match format {
+        Format::Zip => zip(&entries),
+        Format::Tar => tar(&entries),
+        Format::TarGz => tar(&entries).and_then(|tar| gzip(&tar)),
+    }
+    .map_err(|err| {
+        Error::io(format!("Failed to write {}", path.display()))(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            err,
+        ))
+    })?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(Error::io(format!("Failed to create {}", parent.display())))?;
+    }
+    std::fs::write(path, archive).map_err(Error::io(format!("Failed to write {}", path.display())))
+}
+
+fn find_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let entries =
+        std::fs::read_dir(dir).map_err(Error::io(format!("Failed to read {}", dir.display())))?;
+    for entry in entries {
+        let path = entry
+            .map_err(Error::io(format!("Failed to read {}", dir.display())))?
+            .path();
+        if path.is_dir() {
+            find_files(&path, files)?;
+        } else if !matches!(
+            path.extension().and_then(|x| x.to_str()),
+            Some("br") | Some("gz")
+        ) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// A zip without zip64 extensions, so each file and the whole archive must be under 4GiB.
+fn zip(entries: &[(String, Vec<u8>)]) -> Result<Vec<u8>, String> {
+    // 1980-01-01 00:00, the earliest time a zip can store.
+    const DOS_TIME: u16 = 0;
+    const DOS_DATE: u16 = (1 << 5) | 1;
+    // The names are UTF-8.
+    const FLAGS: u16 = 1 << 11;
+    let too_large = || "it would be larger than the 4GiB a zip can hold".to_string();
+    let u32_len = |len: usize| u32::try_from(len).map_err(|_| too_large());
+
+    let mut archive = vec![];
+    let mut central_directory = vec![];
+    for (name, contents) in entries {
+        let (method, data) = deflate(contents);
+        let offset = u32_len(archive.len())?;
+        let mut header = vec![];
+        header.extend_from_slice(&FLAGS.to_le_bytes());
+        header.extend_from_slice(&method.to_le_bytes());
+        header.extend_from_slice(&DOS_TIME.to_le_bytes());
+        header.extend_from_slice(&DOS_DATE.to_le_bytes());
+        header.extend_from_slice(&crc32(contents).to_le_bytes());
+        header.extend_from_slice(&u32_len(data.len())?.to_le_bytes());
+        header.extend_from_slice(&u32_len(contents.len())?.to_le_bytes());
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        // No extra field.
+        header.extend_from_slice(&0u16.to_le_bytes());
+
+        archive.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        // The version needed to extract, 2.0 supports deflate.
+        archive.extend_from_slice(&20u16.to_le_bytes());
+        archive.extend_from_slice(&header);
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(&data);
+
+        central_directory.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        // Made by and needed to extract.
+        central_directory.extend_from_slice(&20u16.to_le_bytes());
+        central_directory.extend_from_slice(&20u16.to_le_bytes());
+        central_directory.extend_from_slice(&header);
+        // No comment, on disk 0, no internal or external attributes.
+        central_directory.extend_from_slice(&[0; 10]);
+        central_directory.extend_from_slice(&offset.to_le_bytes());
+        central_directory.extend_from_slice(name.as_bytes());
+    }
+    let count = u16::try_from(entries.len()).map_err(|_| "it would hold over 65535 files")?;
+    let central_directory_offset = u32_len(archive.len())?;
+    let central_directory_size = u32_len(central_directory.len())?;
+    archive.extend_from_slice(&central_directory);
+    archive.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    // This disk and the disk the central directory starts on.
+    archive.extend_from_slice(&[0; 4]);
+    archive.extend_from_slice(&count.to_le_bytes());
+    archive.extend_from_slice(&count.to_le_bytes());
+    archive.extend_from_slice(&central_directory_size.to_le_bytes());
+    archive.extend_from_slice(&central_directory_offset.to_le_bytes());
+    // No comment.
+    archive.extend_from_slice(&0u16.to_le_bytes());
+    Ok(archive)
+}
+
+/// Returns the zip compression method and the data compressed with it.
+#[cfg(feature = "flate2")]
+fn deflate(contents: &[u8]) -> (u16, Vec<u8>) {
+    use std::io::Write;
+
+    let mut encoder = flate2::write::DeflateEncoder::new(vec![], flate2::Compression::best());
+    // Writing to a Vec cant fail.
+    encoder.write_all(contents).unwrap();
+    let deflated = encoder.finish().unwrap();
+    if deflated.len() < contents.len() {
+        (8, deflated)
+    } else {
+        (0, contents.to_vec())
+    }
+}
+
+/// Without flate2 the files are stored uncompressed.
+#[cfg(not(feature = "flate2"))]
+fn deflate(contents: &[u8]) -> (u16, Vec<u8>) {
+    (0, contents.to_vec())
+}
+
+#[cfg(feature = "flate2")]
+fn gzip(tar: &[u8]) -> Result<Vec<u8>, String> {
+    use std::io::Write;
+
+    let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::best());
+    encoder.write_all(tar).map_err(|err| err.to_string())?;
+    encoder.finish().map_err(|err| err.to_string())
+}
+
+/// Unreachable, since [`Format::from_path`] rejects .tar.gz without flate2.
+#[cfg(not(feature = "flate2"))]
+fn gzip(_tar: &[u8]) -> Result<Vec<u8>, String> {
+    Err("gzip requires the flate2 feature".to_string())
+}
+
+/// A ustar tarball of regular files.
+fn tar(entries: &[(String, Vec<u8>)]) -> Result<Vec<u8>, String> {
+    let mut archive = vec![];
+    for (name, contents) in entries {
+        let mut header = [0u8; 512];
+        // Names too long for the name field are split into a prefix and name at a `/`.
+        let (prefix, name) = if name.len() <= 100 {
+            ("", name.as_str())
+        } else {
+            name.char_indices()
+                .filter(|(i, c)| *c == '/' && *i <= 155 && name.len() - i - 1 <= 100)
+                .map(|(i, _)| (&name[..i], &name[i + 1..]))
+                .next()
+                .ok_or_else(|| format!("the path {} is too long for a tarball", name))?
+        };
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..108].copy_from_slice(b"0000644\0");
+        header[108..116].copy_from_slice(b"0000000\0");
+        header[116..124].copy_from_slice(b"0000000\0");
+        let size = format!("{:011o}\0", contents.len());
+        if size.len() != 12 {
+            return Err(format!("{} is too large for a tarball", name));
+        }
+        header[124..136].copy_from_slice(size.as_bytes());
+        header[136..148].copy_from_slice(b"00000000000\0");
+        // The checksum is calculated with its own field filled with spaces.
+        header[148..156].copy_from_slice(b"        ");
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+        let checksum: u32 = header.iter().map(|x| *x as u32).sum();
+        header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+
+        archive.extend_from_slice(&header);
+        archive.extend_from_slice(contents);
+        archive.resize(archive.len() + (512 - contents.len() % 512) % 512, 0);
+    }
+    // Two empty blocks mark the end of the archive.
+    archive.resize(archive.len() + 1024, 0);
+    Ok(archive)
+}
+
+/// The CRC-32 zip stores for each file.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
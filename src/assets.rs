@@ -0,0 +1,90 @@
+//! Copying of static asset directories into the output directory.
+
+use crate::server::events::Events;
+use crate::server::reload::Reloader;
+use crate::Verbosity;
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Recursively copy the contents of `source` into `dest`, overwriting any existing files.
+pub(crate) fn copy_dir(source: &Path, dest: &Path) -> io::Result<()> {
+    fs::create_dir_all(dest)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let source = entry.path();
+        let dest = dest.join(entry.file_name());
+        // Use Path::is_dir instead of the entry file type so that symlinked directories are followed.
+        if source.is_dir() {
+            copy_dir(&source, &dest)?;
+        } else {
+            fs::copy(&source, &dest)?;
+        }
+    }
+    Ok(())
+}
+
+/// Spawn a thread that polls the assets directories for changes,
+/// copying them into each of `dests` again and reloading the page whenever anything changes.
+///
+/// Polling is used rather than OS file watching APIs to avoid pulling in a large dependency.
+#[cfg_attr(not(feature = "server"), allow(dead_code))]
+pub(crate) fn watch(
+    dirs: Vec<PathBuf>,
+    dests: Vec<PathBuf>,
+    reloader: Arc<Reloader>,
+    events: Arc<Events>,
+    verbosity: Verbosity,
+) {
+    thread::spawn(move || {
+        let mut last_snapshot = snapshot(&dirs);
+        loop {
+            thread::sleep(Duration::from_millis(500));
+            let snapshot = snapshot(&dirs);
+            if snapshot != last_snapshot {
+                last_snapshot = snapshot;
+                for (dir, dest) in dirs.iter().flat_map(|x| dests.iter().map(move |y| (x, y))) {
+                    if let Err(err) = copy_dir(dir, dest) {
+                        tracing::warn!(dir = %dir.display(), error = %err, "failed to copy assets");
+                        println!("Failed to copy assets from {}: {}", dir.display(), err);
+                    }
+                }
+                tracing::info!("assets changed, reloading");
+                if verbosity >= Verbosity::Normal {
+                    println!("Assets changed, reloading");
+                }
+                reloader.reload();
+                events.emit("reload", json!({ "reason": "assets changed" }));
+            }
+        }
+    });
+}
+
+/// The modification time and size of every file in `dirs`.
+pub(crate) fn snapshot(dirs: &[PathBuf]) -> HashMap<PathBuf, (Option<SystemTime>, u64)> {
+    let mut snapshot = HashMap::new();
+    for dir in dirs {
+        snapshot_dir(dir, &mut snapshot);
+    }
+    snapshot
+}
+
+fn snapshot_dir(dir: &Path, snapshot: &mut HashMap<PathBuf, (Option<SystemTime>, u64)>) {
+    // Errors are ignored since files can disappear while we are looking at them,
+    // any real problem will be reported when copying.
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                snapshot_dir(&path, snapshot);
+            } else if let Ok(metadata) = path.metadata() {
+                snapshot.insert(path, (metadata.modified().ok(), metadata.len()));
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Opening the served page in a browser.
+
+use std::process::Command;
+
+/// Open `url` in `browser`, or the users default browser if None.
+///
+/// `browser` can be the name of a browser, e.g. `firefox`, or a path to a browser executable.
+/// `browser_args` are passed to the browser before the url and are ignored when using the default browser.
+pub(crate) fn open(url: &str, browser: Option<&str>, browser_args: &[String]) {
+    let mut command = match browser {
+        Some(browser) => browser_command(browser, url, browser_args),
+        None => {
+            if !browser_args.is_empty() {
+                println!("Ignoring browser args since no browser was specified");
+            }
+            default_browser_command(url)
+        }
+    };
+    // Dont wait on the browser since it may run until the user closes it.
+    if let Err(err) = command.spawn() {
+        println!("Failed to open a browser: {}", err);
+    }
+}
+
+fn default_browser_command(url: &str) -> Command {
+    if cfg!(target_os = "windows") {
+        // The empty argument is the window title, otherwise start would treat a quoted url as the title.
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", "", url]);
+        command
+    } else if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        command.arg(url);
+        command
+    } else {
+        let mut command = Command::new("xdg-open");
+        command.arg(url);
+        command
+    }
+}
+
+fn browser_command(browser: &str, url: &str, browser_args: &[String]) -> Command {
+    let is_path = browser.contains('/') || browser.contains('\\');
+    if cfg!(target_os = "windows") && !is_path {
+        // start can find browsers by name even when they are not in the PATH, e.g. `firefox`, `chrome` or `msedge`.
+        let mut command = Command::new("cmd");
+        command
+            .args(["/C", "start", "", browser])
+            .args(browser_args)
+            .arg(url);
+        command
+    } else if cfg!(target_os = "macos") && !is_path {
+        // Browsers on macos are rarely in the PATH, so launch the application by name e.g. `Firefox` or `Google Chrome`.
+        let mut command = Command::new("open");
+        command.args(["-a", browser, url]);
+        if !browser_args.is_empty() {
+            command.arg("--args").args(browser_args);
+        }
+        command
+    } else {
+        let mut command = Command::new(browser);
+        command.args(browser_args).arg(url);
+        command
+    }
+}
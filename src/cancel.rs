@@ -0,0 +1,126 @@
+//! Cancelling a build or dev server from another thread, for GUI wrappers and test harnesses that tear them down.
+
+#[cfg(feature = "tokio")]
+use std::future::Future;
+#[cfg(feature = "tokio")]
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "tokio")]
+use std::task::{Context, Poll};
+
+type Callback = Box<dyn FnOnce() + Send>;
+
+/// Cancels the builds and dev server of the [`crate::RunWasm`] it is given to via [`crate::RunWasm::cancel_token`].
+///
+/// Clones share the same state, so keep a clone to call [`CancelToken::cancel`] on from another thread:
+/// ```no_run
+/// let token = cargo_run_wasm::CancelToken::new();
+/// let cancel = token.clone();
+/// std::thread::spawn(move || {
+///     std::thread::sleep(std::time::Duration::from_secs(60));
+///     cancel.cancel();
+/// });
+/// let result = cargo_run_wasm::RunWasm::new().cancel_token(token).try_run();
+/// ```
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    next_id: AtomicUsize,
+    callbacks: Mutex<Vec<(usize, Callback)>>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    /// Kills cargo if it is running and stops the dev server, a build in progress then fails with [`crate::Error::Cancelled`].
+    ///
+    /// Steps that run in process, such as wasm-bindgen, are not interrupted, instead the build stops once they finish.
+    /// Cancelling again has no effect.
+    pub fn cancel(&self) {
+        if self.inner.cancelled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let callbacks = std::mem::take(&mut *self.inner.callbacks.lock().unwrap());
+        for (_, callback) in callbacks {
+            callback();
+        }
+    }
+
+    /// Whether [`CancelToken::cancel`] has been called, long running [`crate::BuildStep`]s can check this to stop early.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Calls `callback` when cancelled, or right away if already cancelled, until the returned registration is dropped.
+    pub(crate) fn on_cancel<F: FnOnce() + Send + 'static>(&self, callback: F) -> Registration {
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut callbacks = self.inner.callbacks.lock().unwrap();
+        if self.is_cancelled() {
+            drop(callbacks);
+            callback();
+        } else {
+            callbacks.push((id, Box::new(callback)));
+        }
+        Registration {
+            token: self.clone(),
+            id,
+        }
+    }
+}
+
+/// Unregisters a callback given to [`CancelToken::on_cancel`] when dropped.
+pub(crate) struct Registration {
+    token: CancelToken,
+    id: usize,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut callbacks = self.token.inner.callbacks.lock().unwrap();
+        callbacks.retain(|(id, _)| *id != self.id);
+    }
+}
+
+/// Awaits `future`, or returns None as soon as `token` is cancelled, dropping `future`.
+#[cfg(feature = "tokio")]
+pub(crate) async fn or_cancelled<F: Future>(token: &CancelToken, future: F) -> Option<F::Output> {
+    let (cancel, cancelled) = tokio::sync::oneshot::channel();
+    let _registration = token.on_cancel(move || {
+        cancel.send(()).ok();
+    });
+    OrCancelled {
+        future: Box::pin(future),
+        cancelled,
+    }
+    .await
+}
+
+#[cfg(feature = "tokio")]
+struct OrCancelled<F> {
+    future: Pin<Box<F>>,
+    cancelled: tokio::sync::oneshot::Receiver<()>,
+}
+
+#[cfg(feature = "tokio")]
+impl<F: Future> Future for OrCancelled<F> {
+    type Output = Option<F::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if let Poll::Ready(output) = this.future.as_mut().poll(cx) {
+            return Poll::Ready(Some(output));
+        }
+        match Pin::new(&mut this.cancelled).poll(cx) {
+            Poll::Ready(Ok(())) => Poll::Ready(None),
+            _ => Poll::Pending,
+        }
+    }
+}
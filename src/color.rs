@@ -0,0 +1,75 @@
+//! Colors for terminal output, following the same conventions as cargo.
+//!
+//! Whether colors are used is decided once at startup, since output is printed from many threads.
+
+use crate::ColorChoice;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub(crate) const RED: &str = "\x1b[31m";
+#[cfg_attr(not(feature = "server"), allow(dead_code))]
+pub(crate) const GREEN: &str = "\x1b[32m";
+pub(crate) const YELLOW: &str = "\x1b[33m";
+#[cfg_attr(not(feature = "server"), allow(dead_code))]
+pub(crate) const CYAN: &str = "\x1b[36m";
+pub(crate) const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// Decides whether to use colors.
+///
+/// With [`ColorChoice::Auto`] `CARGO_TERM_COLOR` is respected like cargo does,
+/// otherwise colors are used when stdout is a terminal, unless `NO_COLOR` is set or `TERM` is `dumb`.
+pub(crate) fn init(choice: ColorChoice) {
+    let choice = match (choice, std::env::var("CARGO_TERM_COLOR").as_deref()) {
+        (ColorChoice::Auto, Ok("always")) => ColorChoice::Always,
+        (ColorChoice::Auto, Ok("never")) => ColorChoice::Never,
+        (choice, _) => choice,
+    };
+    let enabled = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            let no_color = matches!(std::env::var_os("NO_COLOR"), Some(x) if !x.is_empty());
+            let dumb = matches!(std::env::var("TERM").as_deref(), Ok("dumb"));
+            !no_color && !dumb && stdout_is_terminal()
+        }
+    };
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub(crate) fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Wraps `text` in the escape codes for `style`, e.g. [`GREEN`], when colors are enabled.
+pub(crate) fn paint(style: &str, text: &str) -> String {
+    if enabled() && !style.is_empty() {
+        format!("{}{}{}", style, text, RESET)
+    } else {
+        text.to_string()
+    }
+}
+
+/// The style for an http status code, e.g. [`RED`] for server errors.
+#[cfg_attr(not(feature = "server"), allow(dead_code))]
+pub(crate) fn status_style(status: u16) -> &'static str {
+    match status {
+        200..=299 => GREEN,
+        300..=399 => CYAN,
+        400..=499 => YELLOW,
+        _ => RED,
+    }
+}
+
+#[cfg(unix)]
+fn stdout_is_terminal() -> bool {
+    // Safe because isatty only inspects the file descriptor.
+    unsafe { libc::isatty(libc::STDOUT_FILENO) == 1 }
+}
+
+#[cfg(not(unix))]
+fn stdout_is_terminal() -> bool {
+    // Without a way to query the console on our MSRV, only assume support in terminals known to handle escape codes.
+    std::env::var_os("WT_SESSION").is_some() || std::env::var_os("TERM").is_some()
+}
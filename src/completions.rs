@@ -0,0 +1,157 @@
+//! Shell completion scripts for `cargo run-wasm`, printed by the hidden `--completions <SHELL>` option.
+//!
+//! The scripts complete options from the `--help` list, and package and example names by asking
+//! `cargo run-wasm --completion-names` for them, so that they stay up to date as the workspace changes.
+
+use crate::help;
+
+/// The hidden option that prints a package or example name per line, for the completion scripts.
+pub(crate) const NAMES_OPTION: &str = "--completion-names";
+
+pub(crate) fn script(shell: &str) -> Result<String, String> {
+    match shell {
+        "bash" => Ok(bash()),
+        "zsh" => Ok(zsh()),
+        "fish" => Ok(fish()),
+        "powershell" => Ok(powershell()),
+        _ => Err(format!(
+            "Invalid --completions shell {:?}, expected 'bash', 'zsh', 'fish' or 'powershell'",
+            shell
+        )),
+    }
+}
+
+/// Every short and long option name separated by spaces.
+fn option_words() -> String {
+    let mut words = vec![];
+    for (short, long, _) in help::option_names() {
+        words.extend(short);
+        words.push(long);
+    }
+    words.join(" ")
+}
+
+/// The long names of the options that take a value, separated by `|` for use in a shell case pattern.
+fn value_option_pattern() -> String {
+    help::option_names()
+        .filter(|(_, _, value)| *value)
+        .map(|(_, long, _)| long)
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+fn bash() -> String {
+    format!(
+        r#"# bash completion for `cargo run-wasm`, falls back to cargo's own completion for other subcommands.
+_cargo_run_wasm() {{
+    if [[ "${{COMP_WORDS[1]}}" != "run-wasm" ]]; then
+        if declare -F _cargo >/dev/null; then
+            _cargo "$@"
+        fi
+        return
+    fi
+    local cur="${{COMP_WORDS[COMP_CWORD]}}"
+    local prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    case "$prev" in
+        {values})
+            COMPREPLY=( $(compgen -f -- "$cur") )
+            return
+            ;;
+    esac
+    if [[ "$cur" == -* ]]; then
+        COMPREPLY=( $(compgen -W "{options}" -- "$cur") )
+    else
+        COMPREPLY=( $(compgen -W "$(cargo run-wasm {names} 2>/dev/null)" -- "$cur") )
+    fi
+}}
+complete -o default -F _cargo_run_wasm cargo
+"#,
+        values = value_option_pattern(),
+        options = option_words(),
+        names = NAMES_OPTION,
+    )
+}
+
+fn zsh() -> String {
+    format!(
+        r#"#compdef cargo
+# zsh completion for `cargo run-wasm`, falls back to cargo's own completion for other subcommands.
+_cargo_run_wasm() {{
+    if [[ "${{words[2]}}" != "run-wasm" ]]; then
+        (( $+functions[_cargo] )) && _cargo "$@"
+        return
+    fi
+    case "${{words[CURRENT-1]}}" in
+        {values})
+            _files
+            return
+            ;;
+    esac
+    if [[ "$PREFIX" == -* ]]; then
+        compadd -- {options}
+    else
+        compadd -- ${{(f)"$(cargo run-wasm {names} 2>/dev/null)"}}
+    fi
+}}
+compdef _cargo_run_wasm cargo
+"#,
+        values = value_option_pattern(),
+        options = option_words(),
+        names = NAMES_OPTION,
+    )
+}
+
+fn fish() -> String {
+    let mut script = String::from(
+        "# fish completion for `cargo run-wasm`, added alongside cargo's own completion.\n",
+    );
+    script.push_str(&format!(
+        "complete -c cargo -n '__fish_seen_subcommand_from run-wasm' -f -a '(cargo run-wasm {} 2>/dev/null)'\n",
+        NAMES_OPTION
+    ));
+    for ((short, long, value), (_, description)) in help::option_names().zip(help::OPTIONS) {
+        script.push_str("complete -c cargo -n '__fish_seen_subcommand_from run-wasm'");
+        if let Some(short) = short {
+            script.push_str(&format!(" -s {}", short.trim_start_matches('-')));
+        }
+        script.push_str(&format!(" -l {}", long.trim_start_matches('-')));
+        if value {
+            script.push_str(" -r");
+        }
+        script.push_str(&format!(" -d '{}'\n", fish_escape(description[0])));
+    }
+    script
+}
+
+fn powershell() -> String {
+    let options = help::option_names()
+        .flat_map(|(short, long, _)| short.into_iter().chain(Some(long)))
+        .map(|x| format!("'{}'", x))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        r#"# PowerShell completion for `cargo run-wasm`.
+Register-ArgumentCompleter -Native -CommandName cargo -ScriptBlock {{
+    param($wordToComplete, $commandAst, $cursorPosition)
+    $elements = $commandAst.CommandElements
+    if ($elements.Count -lt 2 -or $elements[1].Value -ne 'run-wasm') {{
+        return
+    }}
+    if ($wordToComplete.StartsWith('-')) {{
+        $candidates = @({options})
+    }} else {{
+        $candidates = @(cargo run-wasm {names} 2>$null)
+    }}
+    $candidates | Where-Object {{ $_ -like "$wordToComplete*" }} | ForEach-Object {{
+        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+    }}
+}}
+"#,
+        options = options,
+        names = NAMES_OPTION,
+    )
+}
+
+fn fish_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\'', "\\'")
+}
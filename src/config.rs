@@ -0,0 +1,408 @@
+//! Defaults read from `run-wasm.toml` in the workspace root, so that the standard invocation for a project can be kept in the repo.
+//!
+//! Command line args take priority over the config file, which in turn takes priority over what the runner configures via [`crate::RunWasm`].
+//!
+//! Each package can also carry its own settings in a `[package.metadata.run-wasm]` table, which take priority over the config file.
+//!
+//! `CARGO_RUN_WASM_*` environment variables sit between the command line args and the config file,
+//! so that CI and container images can set behavior for every project they run without changing the invocation.
+
+use crate::server::ExtraHeader;
+use crate::{gallery, suggest};
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+pub(crate) const CONFIG_FILE: &str = "run-wasm.toml";
+
+/// The environment variables read by [`load_env`] and the config file keys they correspond to.
+pub(crate) const ENV_VARS: &[(&str, &str)] = &[
+    ("CARGO_RUN_WASM_FEATURES", "features"),
+    ("CARGO_RUN_WASM_RELEASE", "release"),
+    ("CARGO_RUN_WASM_HOST", "host"),
+    ("CARGO_RUN_WASM_PORT", "port"),
+    ("CARGO_RUN_WASM_PUBLIC_HOST", "public-host"),
+    ("CARGO_RUN_WASM_OPEN", "open"),
+    ("CARGO_RUN_WASM_BROWSER", "browser"),
+    ("CARGO_RUN_WASM_NO_QR", "no-qr"),
+    ("CARGO_RUN_WASM_NOTIFY", "notify"),
+];
+
+#[derive(Default)]
+pub(crate) struct Config {
+    /// The package or example to run when no NAME is given, and whether it is an example.
+    pub target: Option<(String, bool)>,
+    pub features: Option<String>,
+    pub release: Option<bool>,
+    pub host: Option<String>,
+    pub port: Option<String>,
+    pub public_host: Option<String>,
+    /// The contents of the css file.
+    pub css: Option<String>,
+    pub headers: Vec<ExtraHeader>,
+    pub assets_dirs: Vec<PathBuf>,
+    pub open: Option<bool>,
+    pub browser: Option<String>,
+    pub no_qr: Option<bool>,
+    pub notify: Option<bool>,
+}
+
+impl Config {
+    /// Combines two layers of config, where values set in `self` take priority over those in `lower`.
+    pub fn over(self, lower: Config) -> Config {
+        let mut headers = lower.headers;
+        headers.extend(self.headers);
+        let mut assets_dirs = lower.assets_dirs;
+        assets_dirs.extend(self.assets_dirs);
+        Config {
+            target: self.target.or(lower.target),
+            features: self.features.or(lower.features),
+            release: self.release.or(lower.release),
+            host: self.host.or(lower.host),
+            port: self.port.or(lower.port),
+            public_host: self.public_host.or(lower.public_host),
+            css: self.css.or(lower.css),
+            headers,
+            assets_dirs,
+            open: self.open.or(lower.open),
+            browser: self.browser.or(lower.browser),
+            no_qr: self.no_qr.or(lower.no_qr),
+            notify: self.notify.or(lower.notify),
+        }
+    }
+}
+
+/// Reads the `CARGO_RUN_WASM_*` environment variables.
+pub(crate) fn load_env() -> Result<Config, String> {
+    let mut config = Config::default();
+    for (var, key) in ENV_VARS {
+        let value = match std::env::var(var) {
+            Ok(value) => value,
+            Err(std::env::VarError::NotPresent) => continue,
+            Err(std::env::VarError::NotUnicode(_)) => {
+                return Err(format!("{} is not valid unicode", var))
+            }
+        };
+        let boolean = || match value.to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" => Ok(true),
+            "0" | "false" | "no" | "" => Ok(false),
+            _ => Err(format!(
+                "Invalid {} {:?}, expected true or false",
+                var, value
+            )),
+        };
+        match *key {
+            "features" => config.features = Some(value.clone()),
+            "release" => config.release = Some(boolean()?),
+            "host" => config.host = Some(value.clone()),
+            "port" => config.port = Some(value.clone()),
+            "public-host" => config.public_host = Some(value.clone()),
+            "open" => config.open = Some(boolean()?),
+            "browser" => config.browser = Some(value.clone()),
+            "no-qr" => config.no_qr = Some(boolean()?),
+            "notify" => config.notify = Some(boolean()?),
+            _ => unreachable!(),
+        }
+    }
+    Ok(config)
+}
+
+/// Settings from the `[package.metadata.run-wasm]` table of the package being run, or of the package containing the example being run.
+#[derive(Default)]
+pub(crate) struct PackageConfig {
+    /// Features that must always be enabled, in addition to any passed via `--features`.
+    pub features: Vec<String>,
+    /// The contents of the css file.
+    pub css: Option<String>,
+    /// The id of a `<canvas>` element to add to the page.
+    pub canvas_id: Option<String>,
+    pub assets_dirs: Vec<PathBuf>,
+    /// The manifest to pass to cargo when the package is in a nested workspace, see [`crate::workspace_packages`].
+    pub workspace_manifest: Option<PathBuf>,
+    /// The target to build instead of the whole package, when the runner is in the same package, see [`own_target`].
+    pub target: Option<PackageTarget>,
+    /// The package as described by `cargo metadata`.
+    pub metadata: serde_json::Value,
+}
+
+/// A single target of a package for cargo to build.
+pub(crate) enum PackageTarget {
+    Bin(String),
+    Lib,
+}
+
+/// Reads the `[package.metadata.run-wasm]` table for `name`, paths in it are relative to the directory of the package.
+pub(crate) fn load_package(
+    cargo: &str,
+    project_root: &Path,
+    name: &str,
+    example: bool,
+) -> Result<PackageConfig, String> {
+    let packages = crate::workspace_packages(cargo, project_root)?;
+    let found = packages.iter().find(|package| {
+        let package = &package.metadata;
+        if example {
+            package["targets"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .any(|target| {
+                    target["name"] == name
+                        && target["kind"]
+                            .as_array()
+                            .into_iter()
+                            .flatten()
+                            .any(|x| x == "example")
+                })
+        } else {
+            package["name"] == name
+        }
+    });
+    let (package, mut config) = match found {
+        Some(found) => (
+            &found.metadata,
+            PackageConfig {
+                workspace_manifest: found.workspace_manifest.clone(),
+                metadata: found.metadata.clone(),
+                ..PackageConfig::default()
+            },
+        ),
+        None => return Err(unknown_target(cargo, project_root, name, example)),
+    };
+    if !example {
+        if let Some((manifest, runner)) = crate::runner_target() {
+            if package["manifest_path"].as_str().map(Path::new) == Some(&manifest) {
+                config.target = Some(own_target(package, name, &runner)?);
+            }
+        }
+    }
+    let package_dir = package["manifest_path"]
+        .as_str()
+        .and_then(|x| Path::new(x).parent())
+        .unwrap_or(project_root);
+    let table = match package["metadata"]["run-wasm"].as_object() {
+        Some(table) => table,
+        None => return Ok(config),
+    };
+
+    let invalid = |key: &str, expected: &str| {
+        format!(
+            "Invalid {:?} in [package.metadata.run-wasm] of {}, expected {}",
+            key, name, expected
+        )
+    };
+    let strings = |key: &str, value: &serde_json::Value| match value {
+        serde_json::Value::String(value) => Ok(vec![value.clone()]),
+        serde_json::Value::Array(values) => values
+            .iter()
+            .map(|x| x.as_str().map(|x| x.to_string()))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| invalid(key, "a string or array of strings")),
+        _ => Err(invalid(key, "a string or array of strings")),
+    };
+    for (key, value) in table {
+        match key.as_str() {
+            "features" => config.features = strings(key, value)?,
+            "css" => {
+                let css_path = match value.as_str() {
+                    Some(css_path) => package_dir.join(css_path),
+                    None => return Err(invalid(key, "a string")),
+                };
+                let css = std::fs::read_to_string(&css_path).map_err(|err| {
+                    format!(
+                        "Failed to read the css file {} configured for {}: {}",
+                        css_path.display(),
+                        name,
+                        err
+                    )
+                })?;
+                config.css = Some(css);
+            }
+            "canvas" => match value.as_str() {
+                Some(id) => config.canvas_id = Some(id.to_string()),
+                None => return Err(invalid(key, "a string")),
+            },
+            "assets" => {
+                for dir in strings(key, value)? {
+                    config.assets_dirs.push(package_dir.join(dir));
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "Unknown key {:?} in [package.metadata.run-wasm] of {}",
+                    key, name
+                ))
+            }
+        }
+    }
+    Ok(config)
+}
+
+/// Picks the target to run from the package the runner is in, e.g. a standalone crate with the runner as one of its examples.
+///
+/// Building the whole package would build the runner for wasm too, which fails as cargo-run-wasm does not support wasm.
+/// So a binary named after the package is built, or else its only other binary, or else its cdylib.
+fn own_target(
+    package: &serde_json::Value,
+    name: &str,
+    runner: &str,
+) -> Result<PackageTarget, String> {
+    let mut bins = vec![];
+    let mut cdylib = false;
+    for target in package["targets"].as_array().into_iter().flatten() {
+        let kinds = target["kind"].as_array();
+        let is_kind = |kind: &str| kinds.into_iter().flatten().any(|x| x == kind);
+        match target["name"].as_str() {
+            Some(bin) if is_kind("bin") && bin != runner => bins.push(bin),
+            _ => cdylib |= is_kind("cdylib"),
+        }
+    }
+    if bins.contains(&name) {
+        Ok(PackageTarget::Bin(name.to_string()))
+    } else if bins.len() == 1 {
+        Ok(PackageTarget::Bin(bins[0].to_string()))
+    } else if cdylib {
+        Ok(PackageTarget::Lib)
+    } else {
+        Err(format!(
+            "The runner is in {}, which has no binary named {} or cdylib to run instead",
+            name, name
+        ))
+    }
+}
+
+/// Reads the config file in `project_root`, returning the default config if there is none.
+pub(crate) fn load(project_root: &Path) -> Result<Config, String> {
+    let path = project_root.join(CONFIG_FILE);
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(err) => return Err(format!("Failed to read {}: {}", path.display(), err)),
+    };
+    let table = match contents.parse::<Value>() {
+        Ok(Value::Table(table)) => table,
+        Ok(_) => return Err(format!("{} must be a table", CONFIG_FILE)),
+        Err(err) => return Err(format!("Failed to parse {}: {}", CONFIG_FILE, err)),
+    };
+
+    let mut config = Config::default();
+    for (key, value) in &table {
+        match key.as_str() {
+            "package" => config.target = Some((string(key, value)?, false)),
+            "example" => config.target = Some((string(key, value)?, true)),
+            "features" => {
+                config.features = Some(match value {
+                    Value::Array(features) => features
+                        .iter()
+                        .map(|x| string(key, x))
+                        .collect::<Result<Vec<_>, _>>()?
+                        .join(","),
+                    value => string(key, value)?,
+                })
+            }
+            "release" => config.release = Some(boolean(key, value)?),
+            "host" => config.host = Some(string(key, value)?),
+            "public-host" => config.public_host = Some(string(key, value)?),
+            "port" => match value.as_integer() {
+                Some(port) => config.port = Some(port.to_string()),
+                None => return Err(invalid(key, "a number")),
+            },
+            "css" => {
+                let css_path = project_root.join(string(key, value)?);
+                let css = std::fs::read_to_string(&css_path).map_err(|err| {
+                    format!(
+                        "Failed to read the css file {} configured in {}: {}",
+                        css_path.display(),
+                        CONFIG_FILE,
+                        err
+                    )
+                })?;
+                config.css = Some(css);
+            }
+            "headers" => match value {
+                Value::Table(headers) => {
+                    for (name, value) in headers {
+                        let header = format!("{}: {}", name, string(name, value)?);
+                        config.headers.push(ExtraHeader::parse(&header)?);
+                    }
+                }
+                _ => return Err(invalid(key, "a table of header names to values")),
+            },
+            "assets" => match value {
+                Value::Array(dirs) => {
+                    for dir in dirs {
+                        config.assets_dirs.push(PathBuf::from(string(key, dir)?));
+                    }
+                }
+                value => config.assets_dirs.push(PathBuf::from(string(key, value)?)),
+            },
+            "open" => config.open = Some(boolean(key, value)?),
+            "browser" => config.browser = Some(string(key, value)?),
+            "no-qr" => config.no_qr = Some(boolean(key, value)?),
+            "notify" => config.notify = Some(boolean(key, value)?),
+            _ => return Err(format!("Unknown key {:?} in {}", key, CONFIG_FILE)),
+        }
+    }
+    if table.contains_key("package") && table.contains_key("example") {
+        return Err(format!(
+            "{} can set either package or example, not both",
+            CONFIG_FILE
+        ));
+    }
+    Ok(config)
+}
+
+/// Explains that `name` is not in the workspace, suggesting similarly named packages and examples.
+///
+/// Cargo would only list every package, which is not helpful in large workspaces and does not mention examples.
+fn unknown_target(cargo: &str, project_root: &Path, name: &str, example: bool) -> String {
+    let kind = if example { "example" } else { "package" };
+    let entries = match gallery::workspace_entries(cargo, project_root) {
+        Ok(entries) => entries,
+        Err(_) => return format!("No {} named `{}` in the workspace", kind, name),
+    };
+    // The name may be right and only --example wrong.
+    if let Some(entry) = entries.iter().find(|x| x.name == name) {
+        return if entry.example {
+            format!(
+                "No package named `{}` in the workspace, but there is an example, use `--example {}`",
+                name, name
+            )
+        } else {
+            format!(
+                "No example named `{}` in the workspace, but there is a package, remove `--example`",
+                name
+            )
+        };
+    }
+    let suggestions = suggest::similar(
+        name,
+        entries
+            .iter()
+            .filter(|x| x.example == example)
+            .map(|x| x.name.as_str()),
+    );
+    format!(
+        "No {} named `{}` in the workspace{}",
+        kind,
+        name,
+        suggest::did_you_mean(&suggestions)
+    )
+}
+
+fn string(key: &str, value: &Value) -> Result<String, String> {
+    match value.as_str() {
+        Some(value) => Ok(value.to_string()),
+        None => Err(invalid(key, "a string")),
+    }
+}
+
+fn boolean(key: &str, value: &Value) -> Result<bool, String> {
+    value.as_bool().ok_or_else(|| invalid(key, "true or false"))
+}
+
+fn invalid(key: &str, expected: &str) -> String {
+    format!(
+        "Invalid {:?} in {}, expected {}",
+        key, CONFIG_FILE, expected
+    )
+}
@@ -0,0 +1,171 @@
+//! Writes configs for static hosts next to the `--dist` output, so that it is served with the same headers as the dev server.
+//!
+//! Rather than translating the header globs of the dev server into the path syntax of each host,
+//! the headers the dev server would send are worked out for every file and given to the host per file.
+
+use crate::server::{self, ExtraHeader};
+use crate::{DeployConfig, Error};
+use serde_json::json;
+use std::path::Path;
+
+/// The files written here, which are left out of the configs themselves.
+const CONFIG_FILES: &[&str] = &["nginx.conf", "_headers", "vercel.json"];
+
+/// The dev server options the configs are made to match.
+pub(crate) struct Hosting {
+    pub headers: Vec<ExtraHeader>,
+    pub mime_types: Vec<(String, String)>,
+    pub cache_control: String,
+    pub immutable: bool,
+}
+
+/// The url path of a served file and the headers to send with it.
+struct File {
+    path: String,
+    headers: Vec<(String, String)>,
+}
+
+/// Writes each of `configs` to `dir`.
+pub(crate) fn write_configs(
+    dir: &Path,
+    configs: &[DeployConfig],
+    hosting: &Hosting,
+) -> Result<(), Error> {
+    let mut files = vec![];
+    find_files(dir, "/", hosting, &mut files)?;
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    for config in configs {
+        let (file_name, contents) = match config {
+            DeployConfig::Nginx => ("nginx.conf", nginx(&files)),
+            DeployConfig::Netlify => ("_headers", netlify(&files)),
+            DeployConfig::Vercel => ("vercel.json", vercel(&files)),
+        };
+        let path = dir.join(file_name);
+        std::fs::write(&path, contents)
+            .map_err(Error::io(format!("Failed to write {}", path.display())))?;
+    }
+    Ok(())
+}
+
+fn find_files(
+    dir: &Path,
+    url_path: &str,
+    hosting: &Hosting,
+    files: &mut Vec<File>,
+) -> Result<(), Error> {
+    let entries =
+        std::fs::read_dir(dir).map_err(Error::io(format!("Failed to read {}", dir.display())))?;
+    for entry in entries {
+        let entry = entry.map_err(Error::io(format!("Failed to read {}", dir.display())))?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let path = format!("{}{}", url_path, file_name);
+        if entry.path().is_dir() {
+            find_files(&entry.path(), &format!("{}/", path), hosting, files)?;
+            continue;
+        }
+        // Precompressed copies are served in place of the file they belong to rather than requested themselves.
+        let precompressed = file_name.ends_with(".br") || file_name.ends_with(".gz");
+        if precompressed || url_path == "/" && CONFIG_FILES.contains(&file_name.as_str()) {
+            continue;
+        }
+        let extension = Path::new(&file_name)
+            .extension()
+            .and_then(|x| x.to_str())
+            .unwrap_or_default();
+        let mut headers = server::file_headers(
+            &path,
+            &hosting.headers,
+            &hosting.mime_types,
+            &hosting.cache_control,
+            hosting.immutable,
+        );
+        // Hosts already know the usual content types, only the wasm one is commonly missing, along with any set on the dev server.
+        let custom_type = extension == "wasm"
+            || hosting
+                .mime_types
+                .iter()
+                .any(|(x, _)| x.eq_ignore_ascii_case(extension));
+        if !custom_type {
+            headers.retain(|(name, _)| !name.eq_ignore_ascii_case("Content-Type"));
+        }
+        // Hosts that match headers on the requested path see the directory rather than its index.html.
+        if file_name == "index.html" {
+            files.push(File {
+                path: url_path.to_string(),
+                headers: headers.clone(),
+            });
+        }
+        files.push(File { path, headers });
+    }
+    Ok(())
+}
+
+/// A snippet for the server block, since nginx applies a location only to its exact path the directory entries are left out.
+fn nginx(files: &[File]) -> String {
+    let quote = |x: &str| format!("\"{}\"", x.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut config = String::from(
+        "# Serves these files with the same headers as the cargo-run-wasm dev server.\n\
+         # Include it in the server block whose root is this directory.\n\
+         \n\
+         gzip_static on;\n\
+         # Also serve the precompressed .br files, which requires the ngx_brotli module.\n\
+         # brotli_static on;\n",
+    );
+    for file in files.iter().filter(|x| !x.path.ends_with('/')) {
+        config.push_str(&format!("\nlocation = {} {{\n", quote(&file.path)));
+        for (name, value) in &file.headers {
+            if name.eq_ignore_ascii_case("Content-Type") {
+                // An empty types block makes nginx use the default type regardless of the extension.
+                config.push_str(&format!(
+                    "    types {{ }}\n    default_type {};\n",
+                    quote(value)
+                ));
+            } else {
+                config.push_str(&format!("    add_header {} {};\n", name, quote(value)));
+            }
+        }
+        config.push_str("}\n");
+    }
+    config
+}
+
+/// Netlify compresses responses itself, so only the headers are needed.
+fn netlify(files: &[File]) -> String {
+    let mut config = String::new();
+    for file in files {
+        config.push_str(&file.path);
+        config.push('\n');
+        for (name, value) in &file.headers {
+            config.push_str(&format!("  {}: {}\n", name, value));
+        }
+    }
+    config
+}
+
+/// Vercel compresses responses itself, so only the headers are needed.
+fn vercel(files: &[File]) -> String {
+    let headers: Vec<_> = files
+        .iter()
+        .map(|file| {
+            let headers: Vec<_> = file
+                .headers
+                .iter()
+                .map(|(name, value)| json!({ "key": name, "value": value }))
+                .collect();
+            json!({ "source": escape_path_pattern(&file.path), "headers": headers })
+        })
+        .collect();
+    serde_json::to_string_pretty(&json!({ "headers": headers })).unwrap() + "\n"
+}
+
+/// Vercel matches paths with path-to-regexp patterns, in which these characters have a meaning unless escaped.
+fn escape_path_pattern(path: &str) -> String {
+    let mut escaped = String::new();
+    for c in path.chars() {
+        if "()[]{}:*?+\\".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,130 @@
+//! Turns the generated files into a folder ready to upload to a static host such as GitHub Pages or S3, for `--dist`.
+//!
+//! The js and wasm are renamed to include a hash of their contents, so that hosts and CDNs can cache them forever,
+//! and compressed copies are written alongside for hosts that serve precompressed files.
+//! Every url in the generated files is relative, so the folder can be served from any path, not just the root of a site.
+
+use crate::Error;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+#[cfg(all(feature = "brotli", feature = "flate2"))]
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Files smaller than this are not worth compressing.
+#[cfg(all(feature = "brotli", feature = "flate2"))]
+const MIN_COMPRESS_SIZE: u64 = 1024;
+
+/// Extensions of the files that compress well, which are the kinds of files hosts compress themselves.
+#[cfg(all(feature = "brotli", feature = "flate2"))]
+const COMPRESSIBLE: &[&str] = &[
+    "html", "js", "mjs", "wasm", "css", "json", "svg", "txt", "xml", "gltf", "map",
+];
+
+/// Renames the js and wasm of `name` in `dir` to include their hash, and points the index.html and js at the new names.
+///
+/// Returns the new paths of the wasm and js.
+pub(crate) fn hash_names(dir: &Path, name: &str) -> Result<(PathBuf, PathBuf), Error> {
+    let wasm_name = format!("{}_bg.wasm", name);
+    let js_name = format!("{}.js", name);
+    let read = |path: &Path| {
+        std::fs::read(path).map_err(Error::io(format!("Failed to read {}", path.display())))
+    };
+
+    let wasm = read(&dir.join(&wasm_name))?;
+    let hashed_wasm_name = format!("{}_bg-{}.wasm", name, hash(&wasm));
+    // The wasm must be renamed first, since the js refers to it and so its hash depends on the wasm name.
+    let js =
+        String::from_utf8_lossy(&read(&dir.join(&js_name))?).replace(&wasm_name, &hashed_wasm_name);
+    let hashed_js_name = format!("{}-{}.js", name, hash(js.as_bytes()));
+
+    let index_path = dir.join("index.html");
+    let index = String::from_utf8_lossy(&read(&index_path)?)
+        .replace(&format!("./{}", js_name), &format!("./{}", hashed_js_name))
+        .replace(
+            &format!("./{}", wasm_name),
+            &format!("./{}", hashed_wasm_name),
+        );
+
+    let write = |path: &Path, contents: &[u8]| {
+        std::fs::write(path, contents)
+            .map_err(Error::io(format!("Failed to write {}", path.display())))
+    };
+    let remove = |path: &Path| {
+        std::fs::remove_file(path)
+            .map_err(Error::io(format!("Failed to delete {}", path.display())))
+    };
+    write(&dir.join(&hashed_wasm_name), &wasm)?;
+    remove(&dir.join(&wasm_name))?;
+    write(&dir.join(&hashed_js_name), js.as_bytes())?;
+    remove(&dir.join(&js_name))?;
+    write(&index_path, index.as_bytes())?;
+    Ok((dir.join(hashed_wasm_name), dir.join(hashed_js_name)))
+}
+
+/// Writes a `.br` and `.gz` copy next to every file in `dir` that is worth compressing.
+///
+/// Requires the `brotli` and `flate2` features, which the `server` feature enables, otherwise this does nothing.
+#[cfg(all(feature = "brotli", feature = "flate2"))]
+pub(crate) fn precompress(dir: &Path) -> Result<(), Error> {
+    let entries =
+        std::fs::read_dir(dir).map_err(Error::io(format!("Failed to read {}", dir.display())))?;
+    for entry in entries {
+        let path = entry
+            .map_err(Error::io(format!("Failed to read {}", dir.display())))?
+            .path();
+        if path.is_dir() {
+            precompress(&path)?;
+            continue;
+        }
+        let compressible = matches!(
+            path.extension().and_then(|x| x.to_str()),
+            Some(extension) if COMPRESSIBLE.contains(&extension)
+        );
+        let size = std::fs::metadata(&path)
+            .map(|x| x.len())
+            .unwrap_or_default();
+        if !compressible || size < MIN_COMPRESS_SIZE {
+            continue;
+        }
+        let contents = std::fs::read(&path)
+            .map_err(Error::io(format!("Failed to read {}", path.display())))?;
+        // Unlike the dev server, which compresses on every request, this only happens once so the best levels are worth it.
+        let mut brotli = vec![];
+        {
+            let mut writer = brotli::CompressorWriter::new(&mut brotli, 4096, 11, 22);
+            writer
+                .write_all(&contents)
+                .map_err(Error::io(format!("Failed to compress {}", path.display())))?;
+        }
+        let mut gzip = flate2::write::GzEncoder::new(vec![], flate2::Compression::best());
+        gzip.write_all(&contents)
+            .map_err(Error::io(format!("Failed to compress {}", path.display())))?;
+        let gzip = gzip
+            .finish()
+            .map_err(Error::io(format!("Failed to compress {}", path.display())))?;
+        for (extension, compressed) in [("br", brotli), ("gz", gzip)] {
+            let mut compressed_path = path.clone().into_os_string();
+            compressed_path.push(".");
+            compressed_path.push(extension);
+            let compressed_path = PathBuf::from(compressed_path);
+            std::fs::write(&compressed_path, compressed).map_err(Error::io(format!(
+                "Failed to write {}",
+                compressed_path.display()
+            )))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(all(feature = "brotli", feature = "flate2")))]
+pub(crate) fn precompress(_dir: &Path) -> Result<(), Error> {
+    Ok(())
+}
+
+/// A hash of `contents` to put in a file name, long enough to be recognized as one by the dev server and CDNs.
+pub(crate) fn hash(contents: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
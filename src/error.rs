@@ -0,0 +1,120 @@
+//! The error returned when cargo-run-wasm fails to build or serve the wasm.
+
+use std::fmt;
+use std::io;
+
+/// Why [`crate::RunWasm::try_run`] failed.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The command line args or environment were invalid, the message explains how.
+    InvalidArgs(String),
+    /// Cargo failed to build the wasm. Cargo will have already printed why.
+    BuildFailed {
+        /// The exit code of cargo, None if it was killed by a signal.
+        exit_code: Option<i32>,
+    },
+    /// wasm-bindgen failed to process the wasm built by cargo,
+    /// or would have since the package does not depend on wasm-bindgen, which is checked before building.
+    Bindgen(String),
+    /// The dev server could not listen on the requested port because it is already in use.
+    PortInUse(String),
+    /// A hook registered with [`crate::RunWasm::before_build`] or [`crate::RunWasm::after_build`] returned an error.
+    Hook(Box<dyn std::error::Error + Send + Sync>),
+    /// A [`crate::BuildStep`] returned an error.
+    Step {
+        /// The [`crate::BuildStep::name`] of the step.
+        step: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// The build was stopped by [`crate::CancelToken::cancel`].
+    Cancelled,
+    /// The page failed the headless browser test run by [`crate::RunWasm::test_headless`], the message explains how.
+    TestFailed(String),
+    /// The headless browser test could not be run, e.g. because no WebDriver was found.
+    WebDriver(String),
+    /// The output of [`crate::RunWasm::check_dist`] differs from the manifest it was checked against, the message lists the files that differ.
+    DistMismatch(String),
+    /// The site served by [`crate::RunWasm::check_links`] links to files that are not found, the message lists them.
+    BrokenLinks(String),
+    /// An IO operation such as writing the generated files or running cargo failed.
+    Io {
+        /// What was being done, e.g. `Failed to write index.html`.
+        context: String,
+        source: io::Error,
+    },
+}
+
+impl Error {
+    /// The exit code [`crate::RunWasm::run`] exits the process with for this error, so that scripts and CI can tell failures apart:
+    ///
+    /// | Error | Exit code |
+    /// |-------|-----------|
+    /// | [`Error::Io`] | 1 |
+    /// | [`Error::InvalidArgs`] | 2 |
+    /// | [`Error::BuildFailed`] | 3 |
+    /// | [`Error::Bindgen`] | 4 |
+    /// | [`Error::PortInUse`] | 5 |
+    /// | [`Error::Hook`] | 6 |
+    /// | [`Error::Step`] | 7 |
+    /// | [`Error::Cancelled`] | 8 |
+    /// | [`Error::TestFailed`] | 9 |
+    /// | [`Error::WebDriver`] | 10 |
+    /// | [`Error::DistMismatch`] | 11 |
+    /// | [`Error::BrokenLinks`] | 12 |
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Io { .. } => 1,
+            Error::InvalidArgs(_) => 2,
+            Error::BuildFailed { .. } => 3,
+            Error::Bindgen(_) => 4,
+            Error::PortInUse(_) => 5,
+            Error::Hook(_) => 6,
+            Error::Step { .. } => 7,
+            Error::Cancelled => 8,
+            Error::TestFailed(_) => 9,
+            Error::WebDriver(_) => 10,
+            Error::DistMismatch(_) => 11,
+            Error::BrokenLinks(_) => 12,
+        }
+    }
+
+    /// For use with `map_err`, wraps an io error with a description of what was being done.
+    pub(crate) fn io(context: impl Into<String>) -> impl FnOnce(io::Error) -> Error {
+        let context = context.into();
+        move |source| Error::Io { context, source }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidArgs(message) => write!(f, "{}", message),
+            Error::BuildFailed {
+                exit_code: Some(exit_code),
+            } => write!(f, "cargo failed to build the wasm, exit code {}", exit_code),
+            Error::BuildFailed { exit_code: None } => write!(f, "cargo failed to build the wasm"),
+            Error::Bindgen(message) => write!(f, "wasm-bindgen failed: {}", message),
+            Error::PortInUse(message) => write!(f, "{}", message),
+            Error::Hook(err) => write!(f, "A build hook failed: {}", err),
+            Error::Step { step, source } => write!(f, "The {} build step failed: {}", step, source),
+            Error::Cancelled => write!(f, "The build was cancelled"),
+            Error::TestFailed(message) => write!(f, "The headless test failed: {}", message),
+            Error::WebDriver(message) => write!(f, "{}", message),
+            Error::DistMismatch(message) => write!(f, "{}", message),
+            Error::BrokenLinks(message) => write!(f, "{}", message),
+            Error::Io { context, source } => write!(f, "{}: {}", context, source),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Hook(err) => Some(err.as_ref()),
+            Error::Step { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Skips wasm-bindgen, rendering the index.html and copying the assets when nothing they depend on changed since the last build,
+//! so that running the same command again goes straight to serving.
+//!
+//! Cargo only writes the wasm again when it had to rebuild it, so the size and modification time of the wasm stand in for its own freshness checks.
+//! The fingerprint also covers the options that affect the generated files, the assets and the generated files themselves,
+//! which makes editing or deleting the output redo it too.
+
+use crate::steps::{BuildContext, OutputFile};
+use crate::{assets, dist, Error};
+use std::path::{Path, PathBuf};
+
+/// Where the fingerprint of the last build into `dir` is kept, in the cargo target directory `target_dir` the wasm is built in.
+pub(crate) fn path(target_dir: &Path, dir: &Path) -> PathBuf {
+    target_dir
+        .join("run-wasm-fingerprints")
+        .join(dist::hash(dir.to_string_lossy().as_bytes()))
+}
+
+/// The fingerprint of everything the steps after cargo read and write, with `artifacts` being the files they generate.
+pub(crate) fn compute(build: &BuildContext, artifacts: &[PathBuf]) -> String {
+    let mut inputs = vec![
+        format!("wasm-bindgen {}", wasm_bindgen_shared::version()),
+        format!("{:?} {:?}", build.cargo_args, build.cargo_env),
+        format!(
+            "{:?} {:?} {:?} {} {}",
+            build.css, build.html, build.canvas_id, build.streaming, build.bindgen
+        ),
+        file_state(&build.wasm_path),
+    ];
+    for dir in &build.assets_dirs {
+        let mut files: Vec<_> = assets::snapshot(&[build.info.project_root.join(dir)])
+            .into_iter()
+            .collect();
+        files.sort();
+        inputs.push(format!("{:?}", files));
+    }
+    for (path, file) in &build.output_files {
+        inputs.push(match file {
+            OutputFile::Contents(contents) => {
+                format!("{} {}", path.display(), dist::hash(contents))
+            }
+            OutputFile::Copy(source) => file_state(&build.info.project_root.join(source)),
+        });
+    }
+    inputs.extend(artifacts.iter().map(|x| file_state(x)));
+    dist::hash(inputs.join("\n").as_bytes())
+}
+
+/// Whether the fingerprint at `path` is `fingerprint`, false when there is none yet.
+pub(crate) fn matches(path: &Path, fingerprint: &str) -> bool {
+    matches!(std::fs::read_to_string(path), Ok(x) if x == fingerprint)
+}
+
+pub(crate) fn write(path: &Path, fingerprint: &str) -> Result<(), Error> {
+    std::fs::create_dir_all(path.parent().unwrap())
+        .and_then(|()| std::fs::write(path, fingerprint))
+        .map_err(Error::io(format!(
+            "Failed to write the fingerprint of the build to {}",
+            path.display()
+        )))
+}
+
+/// The path, size and modification time of the file at `path`, or that it doesnt exist.
+fn file_state(path: &Path) -> String {
+    match std::fs::metadata(path) {
+        Ok(metadata) => format!(
+            "{} {} {:?}",
+            path.display(),
+            metadata.len(),
+            metadata.modified().ok()
+        ),
+        Err(_) => format!("{} missing", path.display()),
+    }
+}
@@ -0,0 +1,164 @@
+//! Generates a landing page linking to every package and example in the workspace, for when they are all being served.
+
+use crate::server::escape_html;
+use crate::Error;
+use std::path::Path;
+
+pub(crate) struct Entry {
+    pub name: String,
+    pub example: bool,
+    pub description: String,
+}
+
+/// Write an index.html listing every runnable package and example in the workspace to `output_dir`.
+///
+/// Entries that have been built into `output_dir` link to their page, the rest show how to build them.
+#[cfg_attr(not(feature = "server"), allow(dead_code))]
+pub(crate) fn write(cargo: &str, project_root: &Path, output_dir: &Path) -> Result<(), Error> {
+    let entries = workspace_entries(cargo, project_root).map_err(Error::InvalidArgs)?;
+    let mut html_entries = String::new();
+    for entry in &entries {
+        let name = escape_html(&entry.name);
+        let kind = if entry.example { "example" } else { "package" };
+        let status = if output_dir.join(&entry.name).join("index.html").is_file() {
+            format!(
+                "<a href=\"./{}/\">{}</a> <span class=\"status\">{}</span>",
+                name, name, kind
+            )
+        } else {
+            let flag = if entry.example { "--example " } else { "" };
+            format!(
+                "{} <span class=\"status\">{}, not built yet, run <code>cargo run-wasm {}{}</code></span>",
+                name, kind, flag, name
+            )
+        };
+        html_entries.push_str(&format!(
+            "    <li>\n      {}\n      <div class=\"description\">{}</div>\n    </li>\n",
+            status,
+            escape_html(&entry.description)
+        ));
+    }
+    if entries.is_empty() {
+        html_entries.push_str("    <li>No packages or examples found in the workspace</li>\n");
+    }
+
+    let title = match project_root.file_name() {
+        Some(name) => escape_html(&name.to_string_lossy()),
+        None => "cargo run-wasm".to_string(),
+    };
+    let html = include_str!("gallery.template.html")
+        .replace("{{title}}", &title)
+        .replace("{{entries}}", html_entries.trim_end());
+    std::fs::write(output_dir.join("index.html"), html)
+        .map_err(Error::io("Failed to write the gallery page"))
+}
+
+/// Lists the workspace packages that produce a binary or cdylib and all examples, excluding the binary or example of the run-wasm runner itself.
+///
+/// Packages of nested workspaces in `workspace.exclude` are included, see [`crate::workspace_packages`].
+pub(crate) fn workspace_entries(cargo: &str, project_root: &Path) -> Result<Vec<Entry>, String> {
+    let packages = crate::workspace_packages(cargo, project_root)?;
+
+    let runner = crate::runner_target();
+    let mut entries = vec![];
+    for package in packages.iter().map(|x| &x.metadata) {
+        let manifest = package["manifest_path"].as_str().map(Path::new);
+        // The runner may share its package with what it runs, as in a standalone crate, so only skip the runner itself.
+        let runner_name = match &runner {
+            Some((runner_manifest, name)) if manifest == Some(runner_manifest) => {
+                Some(name.as_str())
+            }
+            _ => None,
+        };
+        let description = package["description"].as_str().unwrap_or_default();
+        let mut runnable = false;
+        for target in package["targets"].as_array().into_iter().flatten() {
+            if runner_name.is_some() && target["name"].as_str() == runner_name {
+                continue;
+            }
+            let kinds: Vec<&str> = target["kind"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|x| x.as_str())
+                .collect();
+            if kinds.contains(&"example") {
+                let src_path = target["src_path"].as_str().map(Path::new);
+                entries.push(Entry {
+                    name: target["name"].as_str().unwrap_or_default().to_string(),
+                    example: true,
+                    description: src_path.and_then(doc_summary).unwrap_or_default(),
+                });
+            } else if kinds.contains(&"bin") || kinds.contains(&"cdylib") {
+                runnable = true;
+            }
+        }
+        if runnable {
+            entries.push(Entry {
+                name: package["name"].as_str().unwrap_or_default().to_string(),
+                example: false,
+                description: description.to_string(),
+            });
+        }
+    }
+    entries.sort_by(|a, b| (a.example, &a.name).cmp(&(b.example, &b.name)));
+    Ok(entries)
+}
+
+/// Print every entry with its description for `--list`, or as a JSON object per line when `json` is set.
+pub(crate) fn print_list(entries: &[Entry], json: bool) {
+    if json {
+        for entry in entries {
+            println!(
+                "{}",
+                serde_json::json!({
+                    "reason": "runnable",
+                    "name": entry.name,
+                    "kind": if entry.example { "example" } else { "package" },
+                    "description": entry.description,
+                })
+            );
+        }
+        return;
+    }
+    if entries.is_empty() {
+        println!("No packages or examples found in the workspace");
+        return;
+    }
+    let width = entries.iter().map(|x| x.name.len()).max().unwrap_or(0);
+    for (example, heading) in [
+        (false, "Packages:"),
+        (true, "Examples (run with --example NAME):"),
+    ] {
+        let mut entries = entries.iter().filter(|x| x.example == example).peekable();
+        if entries.peek().is_none() {
+            continue;
+        }
+        println!("{}", heading);
+        for entry in entries {
+            if entry.description.is_empty() {
+                println!("  {}", entry.name);
+            } else {
+                println!(
+                    "  {:width$}  {}",
+                    entry.name,
+                    entry.description,
+                    width = width
+                );
+            }
+        }
+    }
+}
+
+/// Examples have no description in Cargo.toml, so use the first line of their `//!` doc comment instead.
+fn doc_summary(src_path: &Path) -> Option<String> {
+    let source = std::fs::read_to_string(src_path).ok()?;
+    source
+        .lines()
+        .map(|line| line.trim())
+        .take_while(|line| line.starts_with("//!") || line.is_empty())
+        .filter_map(|line| line.strip_prefix("//!"))
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty())
+        .map(|line| line.to_string())
+}
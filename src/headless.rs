@@ -0,0 +1,350 @@
+//! Loads the served page in a headless browser via WebDriver and reports whether it passed, so that browser tests can run in CI.
+//!
+//! The driver, e.g. chromedriver or geckodriver, has to be installed separately, as with wasm-bindgen-test-runner.
+//! The dev server injects a script into the page that records console output, uncaught errors and when the wasm is loaded,
+//! which is read back by polling the page until it passes, fails or the timeout is reached.
+
+use crate::{color, Error};
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// The drivers tried in order when none is given, from the environment variable wasm-bindgen-test-runner also reads or else the PATH.
+const DRIVERS: &[(&str, &str)] = &[
+    ("CHROMEDRIVER", "chromedriver"),
+    ("GECKODRIVER", "geckodriver"),
+    ("MSEDGEDRIVER", "msedgedriver"),
+];
+
+/// Without an assertion, the page passes once it has run this long after the wasm was loaded without an uncaught error.
+const SETTLE: Duration = Duration::from_secs(1);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Run in the page on every poll, returning the state recorded by the injected script and the result of the assertion.
+const POLL_SCRIPT: &str = r#"
+  const test = window.__runWasmTest;
+  if (!test) return null;
+  let assertion = null;
+  if (arguments[0] !== null) {
+    try { assertion = Boolean((0, eval)(arguments[0])); } catch (e) { assertion = String(e); }
+  }
+  return { loaded: test.loaded, errors: test.errors, output: test.output.splice(0), assertion };
+"#;
+
+pub(crate) struct HeadlessTest {
+    /// A JS expression that passes the test once it is true.
+    pub assertion: Option<String>,
+    /// The path to a driver executable or the http url of a running driver.
+    pub webdriver: Option<String>,
+    pub timeout: Duration,
+}
+
+/// Loads `url` in a headless browser, failing with [`Error::TestFailed`] unless it passes `test`.
+///
+/// The page fails on an uncaught error, including a panic, or when the wasm-bindgen-test summary it logs reports failures.
+/// It passes once the assertion is true, or without one once the wasm was loaded and the page ran for a moment without errors.
+pub(crate) fn run(url: &str, name: &str, test: &HeadlessTest) -> Result<(), Error> {
+    let driver = Driver::start(test.webdriver.as_deref())?;
+    println!(
+        "{} `{}` in a headless browser via {}",
+        color::paint(color::GREEN, "Testing"),
+        name,
+        driver.name
+    );
+    let session = driver.new_session()?;
+    let result = driver.test(&session, url, test);
+    // Closes the browser, the driver itself is killed when dropped.
+    driver
+        .request("DELETE", &format!("/session/{}", session), None)
+        .ok();
+    result?;
+    println!("{} `{}`", color::paint(color::GREEN, "Passed"), name);
+    Ok(())
+}
+
+struct Driver {
+    /// How the driver was found, for messages.
+    name: String,
+    /// The `host:port` the driver listens on.
+    host: String,
+    /// The path the WebDriver endpoints are under, e.g. `/wd/hub` for selenium.
+    base_path: String,
+    /// None when connecting to a driver that was already running.
+    child: Option<Child>,
+}
+
+impl Driver {
+    /// Connects to `webdriver` when it is an http url, otherwise starts it or the first of [`DRIVERS`] that is installed.
+    fn start(webdriver: Option<&str>) -> Result<Driver, Error> {
+        if let Some(url) = webdriver.and_then(|x| x.strip_prefix("http://")) {
+            let (host, base_path) = match url.split_once('/') {
+                Some((host, path)) => (host, format!("/{}", path.trim_end_matches('/'))),
+                None => (url, String::new()),
+            };
+            let driver = Driver {
+                name: webdriver.unwrap().to_string(),
+                host: host.to_string(),
+                base_path,
+                child: None,
+            };
+            driver.wait_until_ready()?;
+            return Ok(driver);
+        }
+        let programs: Vec<String> = match webdriver {
+            Some(program) => vec![program.to_string()],
+            None => DRIVERS
+                .iter()
+                .map(|(var, program)| std::env::var(var).unwrap_or_else(|_| program.to_string()))
+                .collect(),
+        };
+        for program in programs {
+            let port = free_port()?;
+            // chromedriver, geckodriver and msedgedriver all take the port in this form.
+            let child = Command::new(&program)
+                .arg(format!("--port={}", port))
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn();
+            match child {
+                Ok(child) => {
+                    let driver = Driver {
+                        name: program,
+                        host: format!("127.0.0.1:{}", port),
+                        base_path: String::new(),
+                        child: Some(child),
+                    };
+                    driver.wait_until_ready()?;
+                    return Ok(driver);
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound && webdriver.is_none() => {}
+                Err(err) => {
+                    return Err(Error::WebDriver(format!(
+                        "Failed to run the WebDriver {}: {}",
+                        program, err
+                    )))
+                }
+            }
+        }
+        Err(Error::WebDriver(
+            "No WebDriver found, install chromedriver or geckodriver, or give the path to one via --webdriver".to_string(),
+        ))
+    }
+
+    fn wait_until_ready(&self) -> Result<(), Error> {
+        let start = Instant::now();
+        loop {
+            let err = match self.request("GET", "/status", None) {
+                Ok(status) if status["ready"] != false => return Ok(()),
+                Ok(_) => "it is not ready".to_string(),
+                Err(err) => err.to_string(),
+            };
+            if start.elapsed() > Duration::from_secs(10) {
+                return Err(Error::WebDriver(format!(
+                    "The WebDriver {} did not become ready: {}",
+                    self.name, err
+                )));
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Starts a headless browser, returning the id of its session.
+    fn new_session(&self) -> Result<String, Error> {
+        // Each driver ignores the options of the others, so there is no need to know which browser the driver is for.
+        // The chromium args match those wasm-bindgen-test-runner uses, which are needed to run in containers.
+        let chromium_args = ["--headless", "--disable-dev-shm-usage", "--no-sandbox"];
+        let capabilities = json!({
+            "capabilities": {
+                "alwaysMatch": {
+                    "goog:chromeOptions": { "args": chromium_args },
+                    "ms:edgeOptions": { "args": chromium_args },
+                    "moz:firefoxOptions": { "args": ["-headless"] },
+                }
+            }
+        });
+        let session = self.request("POST", "/session", Some(&capabilities))?;
+        session["sessionId"]
+            .as_str()
+            .map(|x| x.to_string())
+            .ok_or_else(|| {
+                Error::WebDriver(format!(
+                    "The WebDriver {} did not return a session id",
+                    self.name
+                ))
+            })
+    }
+
+    fn test(&self, session: &str, url: &str, test: &HeadlessTest) -> Result<(), Error> {
+        self.request(
+            "POST",
+            &format!("/session/{}/url", session),
+            Some(&json!({ "url": url })),
+        )?;
+        let start = Instant::now();
+        let mut loaded_at = None;
+        let mut assertion_error = None;
+        loop {
+            let state = self.request(
+                "POST",
+                &format!("/session/{}/execute/sync", session),
+                Some(&json!({ "script": POLL_SCRIPT, "args": [test.assertion] })),
+            )?;
+            let mut summary = None;
+            for output in state["output"].as_array().into_iter().flatten() {
+                let level = output["level"].as_str().unwrap_or("log");
+                let text = output["text"].as_str().unwrap_or_default();
+                for line in text.lines() {
+                    println!("[console.{}] {}", level, line);
+                }
+                // The summary wasm-bindgen-test prints once every test has run.
+                if text.contains("test result: FAILED") {
+                    summary = Some(false);
+                } else if text.contains("test result: ok") {
+                    summary = Some(true);
+                }
+            }
+            if let Some(error) = state["errors"].as_array().and_then(|x| x.first()) {
+                return Err(Error::TestFailed(format!(
+                    "Uncaught error in the page: {}",
+                    error.as_str().unwrap_or_default()
+                )));
+            }
+            match summary {
+                Some(true) => return Ok(()),
+                Some(false) => {
+                    return Err(Error::TestFailed(
+                        "wasm-bindgen-test reported failures".to_string(),
+                    ))
+                }
+                None => {}
+            }
+            match &state["assertion"] {
+                Value::Bool(true) => return Ok(()),
+                Value::String(error) => assertion_error = Some(error.clone()),
+                _ => {}
+            }
+            if test.assertion.is_none()
+                && state["loaded"] == true
+                && loaded_at.get_or_insert_with(Instant::now).elapsed() >= SETTLE
+            {
+                return Ok(());
+            }
+            if start.elapsed() >= test.timeout {
+                let waiting_for = match &test.assertion {
+                    Some(assertion) => format!("{:?} to be true", assertion),
+                    None => "the wasm to be loaded".to_string(),
+                };
+                let mut message = format!(
+                    "Timed out after {}s waiting for {}",
+                    test.timeout.as_secs(),
+                    waiting_for
+                );
+                if let Some(error) = assertion_error {
+                    message.push_str(&format!(", the assertion last threw {}", error));
+                }
+                return Err(Error::TestFailed(message));
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Makes a WebDriver request, returning the `value` of the response.
+    ///
+    /// Drivers listen on localhost and only need a minimal HTTP/1.1 client, so one is written here rather than depending on one.
+    fn request(&self, method: &str, path: &str, body: Option<&Value>) -> Result<Value, Error> {
+        let error = |err: std::io::Error| {
+            Error::WebDriver(format!(
+                "Failed to connect to the WebDriver {}: {}",
+                self.name, err
+            ))
+        };
+        let mut stream = TcpStream::connect(&self.host).map_err(error)?;
+        // Starting a browser can take a while, but a driver that stops responding should not hang CI forever.
+        stream
+            .set_read_timeout(Some(Duration::from_secs(120)))
+            .map_err(error)?;
+        let body = body.map(|x| x.to_string()).unwrap_or_default();
+        write!(
+            stream,
+            "{} {}{} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method,
+            self.base_path,
+            path,
+            self.host,
+            body.len(),
+            body
+        )
+        .map_err(error)?;
+        let mut response = vec![];
+        stream.read_to_end(&mut response).map_err(error)?;
+
+        let invalid = || {
+            Error::WebDriver(format!(
+                "Invalid response from the WebDriver {} to {} {}",
+                self.name, method, path
+            ))
+        };
+        let split = response
+            .windows(4)
+            .position(|x| x == b"\r\n\r\n")
+            .ok_or_else(invalid)?;
+        let head = String::from_utf8_lossy(&response[..split]).to_ascii_lowercase();
+        let mut body = response[split + 4..].to_vec();
+        if head.contains("transfer-encoding: chunked") {
+            body = dechunk(&body).ok_or_else(invalid)?;
+        }
+        let status: u16 = head
+            .split_whitespace()
+            .nth(1)
+            .and_then(|x| x.parse().ok())
+            .ok_or_else(invalid)?;
+        let json: Value = serde_json::from_slice(&body).map_err(|_| invalid())?;
+        let value = json["value"].clone();
+        if status >= 400 {
+            return Err(Error::WebDriver(format!(
+                "The WebDriver {} failed to {} {}: {}",
+                self.name,
+                method,
+                path,
+                value["message"].as_str().unwrap_or_default()
+            )));
+        }
+        Ok(value)
+    }
+}
+
+impl Drop for Driver {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.child {
+            child.kill().ok();
+            child.wait().ok();
+        }
+    }
+}
+
+/// A port for the driver to listen on, there is a small chance another process takes it first but drivers cant report the one they picked.
+fn free_port() -> Result<u16, Error> {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .map_err(Error::io("Failed to find a free port for the WebDriver"))
+}
+
+/// Decodes a `Transfer-Encoding: chunked` body.
+pub(crate) fn dechunk(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = vec![];
+    loop {
+        let line_end = body.windows(2).position(|x| x == b"\r\n")?;
+        let size = std::str::from_utf8(&body[..line_end]).ok()?;
+        let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Some(decoded);
+        }
+        decoded.extend_from_slice(body.get(..size)?);
+        body = body.get(size + 2..)?;
+    }
+}
@@ -0,0 +1,140 @@
+//! The `--help` text, generated from a single list of the options and environment variables so that it is always complete.
+
+use crate::config::ENV_VARS;
+
+/// Every option in the order they are shown in `--help`, with the lines of their description.
+///
+/// The usage is e.g. `-v, --verbose` for a flag or `--port <PORT>` for an option with a value, ending in `...` when it can be repeated.
+pub(crate) const OPTIONS: &[(&str, &[&str])] = &[
+    ("--release", &["Build in release mode, with optimizations"]),
+    ("--profiles <PROFILES>", &["Build each of the comma separated PROFILES, 'debug' and 'release', into target/wasm-profiles/NAME, or --out-dir,", "and serve them under /debug/ and /release/ with a page at / comparing them side by side"]),
+    ("-v, --verbose", &["Log every request made to the dev server"]),
+    ("-q, --quiet", &["Only print errors, this is also passed on to cargo"]),
+    ("-h, --help", &["Print this help, then exit"]),
+    (
+        "--color <WHEN>",
+        &["Color the output 'auto' (default), 'always' or 'never'. NO_COLOR and CARGO_TERM_COLOR are respected"],
+    ),
+    ("-V, --version", &["Print the version of cargo-run-wasm and of the wasm-bindgen it runs, then exit"]),
+    ("--list", &["List the packages and examples in the workspace that can be run, then exit. Combine with --message-format json for a JSON object per line"]),
+    ("--project-root <DIR>", &["The workspace root to build in, instead of the workspace containing the runner or current directory"]),
+    ("--example", &["Build and run the example NAME instead of a package NAME"]),
+    ("--features <FEATURES>...", &["Comma separated list of features to activate"]),
+    ("--notify", &["Show a desktop notification when the build succeeds or fails"]),
+    ("--message-format <FMT>", &["Print 'human' readable output (default) or a 'json' object per line for tools to consume"]),
+    ("--annotations <FORMAT>", &["Also print rustc errors and warnings and other errors as annotations for 'github' Actions, or 'none'.", "By default 'github' when GITHUB_ACTIONS is true, unless --message-format json is used"]),
+    ("--build-only", &["Only build the WASM artifacts, do not run the dev server"]),
+    ("--dist", &["Write a release build ready to upload to a static host to target/wasm-dist/NAME, or --out-dir, instead of serving it.", "The js and wasm get a content hash in their name and precompressed .br and .gz copies of the files are written"]),
+    ("--trunk-dist", &["Write the --dist output laid out like `trunk build --release` does, to dist next to the package, or --out-dir,", "using the dist and public_url of an existing Trunk.toml. Implies --dist"]),
+    ("--gallery-dist", &["Build every package and example like --dist into target/wasm-gallery, or --out-dir, with a page linking them, instead of NAME"]),
+    ("--deploy-config <HOST>...", &["Also write a config for HOST, 'nginx', 'netlify' or 'vercel', that serves the --dist output with the same headers as the dev server.", "Implies --dist"]),
+    ("--archive <PATH>", &["Also pack the --dist output into a .zip, .tar.gz or .tar at PATH with the index.html at its root, e.g. for itch.io. Implies --dist"]),
+    ("--publish-itch", &["Build for uploading to itch.io as an HTML game: --dist packed into target/wasm-dist/NAME.zip, or --archive, with an index.html", "that works in its iframe, and NAME.itch.txt next to it listing the project settings that replace the dev server headers"]),
+    ("--dist-manifest <PATH>", &["Also write the hash of every file in the --dist output to PATH, in the format of sha1sum. Implies --dist"]),
+    ("--check-dist <PATH>", &["Fail if the --dist output differs from the manifest at PATH written by --dist-manifest, e.g. to catch a stale committed build in CI.", "Implies --dist"]),
+    ("--check-links", &["Serve the --dist or --gallery-dist output once built and request every page, script, stylesheet and asset it links to,", "failing if any is not found. Implies --dist unless --gallery-dist is given"]),
+    ("--pkg <TARGET>", &["Write a release build laid out like the pkg directory of wasm-pack to target/wasm-pkg/NAME, or --out-dir, instead of serving it.", "TARGET is 'web' or 'bundler', a package.json and TypeScript declarations are included for publishing to npm"]),
+    ("--out-dir <DIR>", &["Write the generated files to DIR instead of target/wasm-examples/NAME, e.g. to deploy the output of --build-only"]),
+    ("--listen <unix:PATH>", &["Makes the dev server listen on a unix domain socket at PATH instead of on a TCP port"]),
+    ("--host <HOST>", &["Makes the dev server listen on host (default 'localhost'), IPv6 addresses such as '::1' are supported"]),
+    ("--port <PORT>", &["Makes the dev server listen on port (default '8000'), use '0' to pick any free port"]),
+    ("--auth <USER:PASSWORD>", &["Require the browser to log in to the dev server with USER and PASSWORD"]),
+    ("--public-host <HOST>", &["Show and open urls with HOST instead of the address listened on, e.g. when --host 0.0.0.0 is used in a container"]),
+    ("--strict-port", &["Fail if the port is in use, instead of using the next free port"]),
+    ("--tunnel <BACKEND>", &["Share the dev server on a public https url via BACKEND, one of 'cloudflared', 'ngrok', 'localhost.run'", "or a command containing '{port}' or '{url}'. The tunneling tool must be installed separately."]),
+    ("--mdns", &["Advertise the dev server on the LAN as NAME.local, requires listening on all interfaces"]),
+    ("--no-qr", &["Dont print a QR code of the LAN url when listening on all interfaces"]),
+    ("--header <HEADER>...", &["Add a header to every dev server response, in the form 'Name: value'"]),
+    ("--allow-feature <FEATURE>...", &["Allow the browser feature FEATURE via a Permissions-Policy header, even in cross origin iframes,", "e.g. 'gamepad', 'clipboard-read', 'clipboard-write', 'xr-spatial-tracking' or 'fullscreen'"]),
+    ("--deny-feature <FEATURE>...", &["Deny the browser feature FEATURE via a Permissions-Policy header"]),
+    ("--cors <ORIGIN>...", &["Allow cross origin requests to the dev server from ORIGIN, use '*' to allow any origin"]),
+    ("--proxy <PREFIX=URL>...", &["Forward dev server requests for paths under PREFIX to URL, e.g. '/api=http://localhost:3000'"]),
+    ("--assets <DIR>...", &["Copy the contents of DIR, relative to the workspace root, alongside the generated files.", "While the dev server is running, changes to DIR are copied and the page is reloaded."]),
+    ("--serve-all", &["Serve every previously built package and example under /NAME/, instead of only NAME"]),
+    ("--gallery", &["Serve a page listing every package and example in the workspace at /, implies --serve-all"]),
+    ("--mount <PREFIX=DIR>...", &["Serve the contents of DIR, relative to the workspace root, at PREFIX without copying them, e.g. '/data=testdata'"]),
+    ("--mime <EXT=TYPE>...", &["Serve files with the extension EXT with the Content-Type TYPE, e.g. 'ktx2=image/ktx2'"]),
+    ("--list-dirs", &["Show an index of the files in directories without an index.html, such as those served by --mount"]),
+    ("--spa", &["Serve index.html for unknown paths without an extension, for client side routing"]),
+    ("--no-bindgen", &["Serve the wasm as cargo built it as NAME.wasm, for apps that load it with js of their own from --assets,", "instead of running wasm-bindgen, which fails early when NAME does not depend on it"]),
+    ("--no-streaming", &["Download the whole wasm file before compiling it, for when a proxy breaks streaming compilation"]),
+    ("--no-compression", &["Disable gzip/brotli compression of dev server responses"]),
+    ("--throttle <PRESET>", &["Simulate a slow network by delaying and rate limiting responses, PRESET is one of 'slow-3g',", "'3g', '4g' or 'KBITS,MS' e.g. '1000,200' for 1000 kbit/s with 200ms latency"]),
+    ("--cache-control <VALUE>", &["The Cache-Control header sent with dev server responses (default 'no-cache')"]),
+    ("--no-immutable", &["Dont tell the browser to cache files with a content hash in their name forever"]),
+    ("--report <PATH>", &["Write how long each phase of the build took and the sizes of the generated files to PATH as JSON"]),
+    ("--history", &["Append the --report of every build to target/wasm-history.jsonl, along with the time and git commit it was built from"]),
+    ("--stats", &["Print the latest builds of NAME, or of everything, recorded by --history and how the wasm size and build time changed, then exit"]),
+    ("--clean", &["Delete the generated files of NAME, or when no NAME is given everything cargo-run-wasm has built, then exit"]),
+    ("--clean-on-exit", &["Delete the generated files when the dev server is stopped with ctrl-c"]),
+    ("--access-log <PATH>", &["Append a line for every request made to the dev server to the file at PATH"]),
+    ("--access-log-format <FORMAT>", &["The format of the access log, 'common' (default) for the common log format or 'json' for JSON lines"]),
+    ("--dashboard", &["Show a screen summarizing the build, connections and recent requests that is updated in place"]),
+    ("--console", &["Print the pages console output and uncaught errors, including panics, in the terminal"]),
+    ("--profile-startup", &["Print how long the page took to fetch and instantiate the wasm and draw its first frame, as marked with performance.mark"]),
+    ("--coverage", &["Build with -Cinstrument-coverage and write the coverage of each page load to target/wasm-coverage/<NAME>", "Requires nightly and an app that exports run_wasm_coverage via minicov"]),
+    ("--test-headless", &["Instead of serving until stopped, load the page in a headless browser via WebDriver and exit with an error unless it passes.", "It fails on an uncaught error or panic, and passes once the wasm loads and runs for a second, or once --test-assert is true"]),
+    ("--ci-smoke", &["Check that the page boots for a CI gate: --test-headless on any free port, ignoring --test-assert"]),
+    ("--test-assert <JS>", &["A JS expression that passes --test-headless once it is true, e.g. 'document.title === \"done\"'"]),
+    ("--webdriver <DRIVER>", &["The WebDriver for --test-headless, a path to chromedriver, geckodriver or msedgedriver or the url of a running driver.", "By default CHROMEDRIVER, GECKODRIVER or MSEDGEDRIVER or else the first of them in the PATH"]),
+    ("--test-timeout <SECS>", &["How long --test-headless waits for the page to pass (default '60')"]),
+    ("--open", &["Open the served page in the default browser once the dev server is running"]),
+    ("--browser <BROWSER>", &["Open the served page in BROWSER instead, a browser name or path to its executable. Implies --open"]),
+    ("--browser-arg <ARG>...", &["Pass ARG to the browser opened by --browser, e.g. '--enable-unsafe-webgpu'"]),
+];
+
+/// What a runner changed about the help, so that it describes how the runner is actually invoked.
+#[derive(Clone, Default)]
+pub(crate) struct Custom {
+    pub program_name: Option<String>,
+    pub usage: Option<String>,
+    /// The usage and description of the options the runner added, shown after ours.
+    pub options: Vec<(String, String)>,
+}
+
+pub(crate) fn help(custom: &Custom) -> String {
+    let program_name = custom.program_name.as_deref().unwrap_or("cargo run-wasm");
+    let usage = match &custom.usage {
+        Some(usage) => usage.clone(),
+        None => format!("{} [OPTIONS] NAME", program_name),
+    };
+    let mut help = format!("{}\n\nUSAGE:\n  {}\n\nOPTIONS:\n", program_name, usage);
+    for (usage, description) in OPTIONS {
+        for (i, line) in description.iter().enumerate() {
+            let usage = if i == 0 { usage } else { "" };
+            help.push_str(&format!("  {:<28} {}\n", usage, line));
+        }
+    }
+    for (usage, description) in &custom.options {
+        help.push_str(&format!("  {:<28} {}\n", usage, description));
+    }
+    help.push_str(
+        "
+NAME:
+  Name of the package (crate) within the workspace to run.
+  Can be omitted when set by `package` or `example` in run-wasm.toml.
+  Otherwise when omitted in a terminal, it is picked from a list of the packages and examples.
+
+ENVIRONMENT:
+",
+    );
+    for (var, key) in ENV_VARS {
+        help.push_str(&format!("  {:<28} Sets --{}\n", var, key));
+    }
+    help.push_str("  The command line takes priority over the environment, which takes priority over run-wasm.toml.\n");
+    help
+}
+
+/// The short name, long name and whether it takes a value of each option, e.g. `(Some("-v"), "--verbose", false)`.
+pub(crate) fn option_names() -> impl Iterator<Item = (Option<&'static str>, &'static str, bool)> {
+    OPTIONS.iter().map(|(usage, _)| {
+        let (short, long) = match usage.split_once(", ") {
+            Some((short, long)) => (Some(short), long),
+            None => (None, *usage),
+        };
+        match long.split_once(' ') {
+            Some((long, _)) => (short, long, true),
+            None => (short, long, false),
+        }
+    })
+}
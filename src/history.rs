@@ -0,0 +1,237 @@
+//! Keeps a line of JSON per build in `target/wasm-history.jsonl`, for `--history`, and summarizes it for `--stats`,
+//! so that how the wasm size and build time change over the lifetime of a branch can be followed without any service to send them to.
+//!
+//! Each line is the `--report` of the build along with when it finished and the git commit and branch it was built from.
+
+use crate::report::{format_size, BuildReport};
+use crate::{color, Error};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Relative to the workspace root, `cargo clean` deletes it along with everything else in `target`.
+pub(crate) const PATH: &str = "target/wasm-history.jsonl";
+
+/// How many of the latest builds of each package `--stats` lists, the trends are over all of them.
+const LISTED_BUILDS: usize = 10;
+
+/// A build as read back from the history.
+struct Entry {
+    time: u64,
+    commit: Option<String>,
+    duration_secs: f64,
+    wasm_size: Option<u64>,
+    js_size: Option<u64>,
+}
+
+/// Appends the report of a finished build to the history of the workspace at `project_root`.
+pub(crate) fn append(project_root: &Path, report: &BuildReport) -> Result<(), Error> {
+    let mut entry = report.to_value();
+    entry["time"] = json!(now());
+    entry["commit"] = json!(git(project_root, &["rev-parse", "--short", "HEAD"]));
+    // `HEAD` itself when detached, which isnt a branch.
+    entry["branch"] =
+        json!(git(project_root, &["rev-parse", "--abbrev-ref", "HEAD"]).filter(|x| x != "HEAD"));
+
+    let path = project_root.join(PATH);
+    let write = || -> std::io::Result<()> {
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        // In one call so that the lines of builds running at the same time dont interleave.
+        file.write_all(format!("{}\n", entry).as_bytes())
+    };
+    write().map_err(Error::io(format!(
+        "Failed to append the build to {}",
+        path.display()
+    )))
+}
+
+/// Prints the latest builds of `name`, or of every package and example, from the history of the workspace at `project_root`,
+/// along with how their wasm size and build time changed since the first recorded build.
+pub(crate) fn print_stats(project_root: &Path, name: Option<&str>) -> Result<(), Error> {
+    let path = project_root.join(PATH);
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => {
+            return Err(Error::Io {
+                context: format!("Failed to read {}", path.display()),
+                source: err,
+            })
+        }
+    };
+
+    // By name and profile, oldest first.
+    let mut builds: BTreeMap<(String, String), Vec<Entry>> = BTreeMap::new();
+    // A line cut short by a build that was killed while writing it is skipped rather than losing the rest.
+    for line in contents.lines() {
+        let value: Value = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        let (entry_name, profile) = match (value["name"].as_str(), value["profile"].as_str()) {
+            (Some(entry_name), Some(profile)) => (entry_name, profile),
+            _ => continue,
+        };
+        if matches!(name, Some(name) if name != entry_name) {
+            continue;
+        }
+        builds
+            .entry((entry_name.to_string(), profile.to_string()))
+            .or_default()
+            .push(Entry {
+                time: value["time"].as_u64().unwrap_or(0),
+                commit: value["commit"].as_str().map(|x| x.to_string()),
+                duration_secs: value["duration_secs"].as_f64().unwrap_or(0.0),
+                wasm_size: artifact_size(&value, ".wasm"),
+                js_size: artifact_size(&value, ".js"),
+            });
+    }
+    if builds.is_empty() {
+        println!(
+            "No builds of {} have been recorded in {} yet, build with --history to record them",
+            name.unwrap_or("anything"),
+            path.display()
+        );
+        return Ok(());
+    }
+
+    let now = now();
+    for (i, ((name, profile), entries)) in builds.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!(
+            "{} {}, {} build{} recorded",
+            color::paint(color::GREEN, name),
+            profile,
+            entries.len(),
+            if entries.len() == 1 { "" } else { "s" }
+        );
+        println!(
+            "{}",
+            color::paint(
+                color::DIM,
+                &format!(
+                    "  {:<10} {:<10} {:>9}  {:<24} {}",
+                    "built", "commit", "duration", "wasm", "js"
+                )
+            )
+        );
+        let skip = entries.len().saturating_sub(LISTED_BUILDS);
+        for (j, entry) in entries.iter().enumerate().skip(skip) {
+            let previous = j.checked_sub(1).map(|j| &entries[j]);
+            let wasm = match entry.wasm_size {
+                Some(size) => format!(
+                    "{}{}",
+                    format_size(size),
+                    previous
+                        .and_then(|x| x.wasm_size)
+                        .map(|old| format_change(old, size))
+                        .unwrap_or_default()
+                ),
+                None => "-".to_string(),
+            };
+            println!(
+                "  {:<10} {:<10} {:>8.1}s  {:<24} {}",
+                format_age(now.saturating_sub(entry.time)),
+                entry.commit.as_deref().unwrap_or("-"),
+                entry.duration_secs,
+                wasm,
+                entry
+                    .js_size
+                    .map(format_size)
+                    .unwrap_or_else(|| "-".to_string())
+            );
+        }
+
+        let first = &entries[0];
+        let latest = &entries[entries.len() - 1];
+        let mut trends = vec![];
+        if let (Some(old), Some(new)) = (first.wasm_size, latest.wasm_size) {
+            let percent = (new as f64 - old as f64) / old.max(1) as f64 * 100.0;
+            trends.push(format!(
+                "wasm {} -> {} ({:+.1}%)",
+                format_size(old),
+                format_size(new),
+                percent
+            ));
+        }
+        // A rebuild that cargo mostly skips is far quicker than a clean one, so the median says more than the first and latest.
+        let mut durations: Vec<f64> = entries.iter().map(|x| x.duration_secs).collect();
+        durations.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        trends.push(format!(
+            "duration median {:.1}s, fastest {:.1}s, slowest {:.1}s",
+            durations[durations.len() / 2],
+            durations[0],
+            durations[durations.len() - 1]
+        ));
+        if entries.len() > 1 {
+            println!(
+                "  Since the first build {}: {}",
+                format_age(now.saturating_sub(first.time)),
+                trends.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The size at the end of the build of the first artifact whose file name ends with `extension`.
+fn artifact_size(report: &Value, extension: &str) -> Option<u64> {
+    report["artifacts"]
+        .as_array()?
+        .iter()
+        .find(|x| matches!(x["file"].as_str(), Some(file) if file.ends_with(extension)))
+        .and_then(|x| x["size"].as_u64().or_else(|| x["generated_size"].as_u64()))
+}
+
+/// How a size changed from `old` to `new`, e.g. ` (+1.5 KiB)`, or nothing when it didnt.
+fn format_change(old: u64, new: u64) -> String {
+    if new > old {
+        format!(" (+{})", format_size(new - old))
+    } else if new < old {
+        format!(" (-{})", format_size(old - new))
+    } else {
+        String::new()
+    }
+}
+
+/// `secs` ago in the largest unit it is at least one of, e.g. `3d ago`.
+fn format_age(secs: u64) -> String {
+    if secs >= 86400 {
+        format!("{}d ago", secs / 86400)
+    } else if secs >= 3600 {
+        format!("{}h ago", secs / 3600)
+    } else if secs >= 60 {
+        format!("{}m ago", secs / 60)
+    } else {
+        "just now".to_string()
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0)
+}
+
+/// The trimmed output of running git with `args` in `dir`, None when it isnt a git repository or git isnt installed.
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
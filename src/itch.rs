@@ -0,0 +1,119 @@
+//! Prepares the `--dist` output for uploading to itch.io as an HTML game, for `--publish-itch`.
+//!
+//! itch.io serves the files from its CDN in an iframe on the game page, which brings two problems the dev server doesnt have:
+//! the keyboard only reaches the game once it has been clicked, and keys the game doesnt handle scroll the page around the iframe.
+//! It also cant be told which headers to send, so notes written next to the zip list the project settings that stand in for them.
+
+use crate::server::ExtraHeader;
+use crate::Error;
+use std::path::Path;
+
+/// Injected into the head of the index.html.
+const IFRAME_SCRIPT: &str = r#"
+  <script>
+    // itch.io runs the game in an iframe, which only gets the keyboard once focused,
+    // and passes the keys that would scroll it on to the page around it.
+    window.addEventListener("load", () => window.focus());
+    window.addEventListener("pointerdown", () => window.focus());
+    window.addEventListener("keydown", (event) => {
+      const target = event.target;
+      if (target instanceof HTMLInputElement || target instanceof HTMLTextAreaElement || target.isContentEditable) return;
+      if (["ArrowUp", "ArrowDown", "ArrowLeft", "ArrowRight", " ", "PageUp", "PageDown", "Home", "End"].includes(event.key)) {
+        event.preventDefault();
+      }
+    });
+  </script>"#;
+
+/// Adds the script that keeps the keyboard within the iframe to the index.html in `dir`.
+pub(crate) fn prepare_index(dir: &Path) -> Result<(), Error> {
+    let path = dir.join("index.html");
+    let mut html =
+        std::fs::read(&path).map_err(Error::io(format!("Failed to read {}", path.display())))?;
+    crate::server::insert_after_head(&mut html, IFRAME_SCRIPT.as_bytes());
+    std::fs::write(&path, html).map_err(Error::io(format!("Failed to write {}", path.display())))
+}
+
+/// Writes the steps to upload `archive` to itch.io to `path`, including the settings that replace each of `headers`.
+pub(crate) fn write_notes(
+    path: &Path,
+    name: &str,
+    archive: &Path,
+    headers: &[ExtraHeader],
+) -> Result<(), Error> {
+    let archive_name = archive
+        .file_name()
+        .map(|x| x.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut notes = format!(
+        "Uploading {name} to itch.io
+{underline}
+
+1. Create a new project on itch.io, or edit an existing one, and set \"Kind of project\" to HTML.
+2. Upload {archive} and tick \"This file will be played in the browser\".
+3. Under \"Embed options\", set the viewport dimensions to the size the game is made for,
+   or pick \"Click to launch in fullscreen\" if it fills the window.
+4. Save, then \"View page\" to play it.
+
+Uploading a new {archive} later replaces the game, the settings are kept.
+
+",
+        name = name,
+        underline = "=".repeat("Uploading  to itch.io".len() + name.len()),
+        archive = archive_name,
+    );
+
+    let mut names: Vec<&str> = vec![];
+    for header in headers {
+        if !names.iter().any(|x| x.eq_ignore_ascii_case(&header.name)) {
+            names.push(&header.name);
+        }
+    }
+    if names.is_empty() {
+        notes.push_str(
+            "The dev server sent no extra headers, so nothing else needs to be set up.\n",
+        );
+    } else {
+        notes.push_str(
+            "itch.io serves the files itself and cant be told which headers to send, unlike the dev server:\n\n",
+        );
+        for name in names {
+            notes.push_str(&format!("- {}: {}\n", name, header_note(name, headers)));
+        }
+    }
+
+    std::fs::write(path, notes).map_err(Error::io(format!("Failed to write {}", path.display())))
+}
+
+/// What to do on itch.io in place of sending the header `name`.
+fn header_note(name: &str, headers: &[ExtraHeader]) -> String {
+    match name.to_ascii_lowercase().as_str() {
+        "cross-origin-opener-policy" | "cross-origin-embedder-policy" => {
+            "tick \"SharedArrayBuffer support\" under \"Embed options\", which makes itch.io send the headers needed for threads."
+                .to_string()
+        }
+        "permissions-policy" => {
+            let mut features: Vec<&str> = headers
+                .iter()
+                .filter(|x| x.name.eq_ignore_ascii_case(name))
+                .flat_map(|x| x.value.split(','))
+                .filter(|x| x.trim().ends_with("=*"))
+                .map(|x| x.trim().trim_end_matches("=*"))
+                .collect();
+            features.dedup();
+            let mut note = "itch.io decides which browser features its iframe allows".to_string();
+            if features.contains(&"fullscreen") {
+                note.push_str(", tick \"Fullscreen button\" under \"Embed options\" for fullscreen");
+            }
+            features.retain(|x| *x != "fullscreen");
+            if !features.is_empty() {
+                note.push_str(&format!(
+                    ", check that the game still gets {} once uploaded",
+                    features.join(", ")
+                ));
+            }
+            note.push('.');
+            note
+        }
+        _ => "not sent, the game has to work without it.".to_string(),
+    }
+}
@@ -1,52 +1,475 @@
+use config::PackageTarget;
 use pico_args::Arguments;
+use serde_json::json;
+#[cfg(feature = "server")]
+use server::dashboard::Dashboard;
+use server::events::Events;
+use server::proxy::Proxy;
+#[cfg(feature = "server")]
+use server::reload::Reloader;
+use server::status::BuildStatus;
+use server::throttle::Throttle;
+use server::{ExtraHeader, Mount, RequestHook, ResponseHook};
+#[cfg(feature = "server")]
+use server::{Listener, ServerConfig, Shutdown};
 use std::env;
-use std::path::Path;
+#[cfg(feature = "server")]
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
-const HELP: &str = "\
-cargo run-wasm
+mod annotations;
+mod archive;
+mod assets;
+#[cfg(feature = "server")]
+mod browser;
+mod cancel;
+mod color;
+mod completions;
+mod config;
+mod deploy;
+mod dist;
+mod error;
+mod fingerprint;
+mod gallery;
+#[cfg(feature = "server")]
+mod headless;
+mod help;
+mod history;
+mod itch;
+#[cfg(feature = "server")]
+mod links;
+mod lockfile;
+#[cfg(feature = "server")]
+mod mdns;
+#[cfg(feature = "server")]
+mod network;
+mod notify;
+mod picker;
+mod pkg;
+mod profiles;
+#[cfg(feature = "server")]
+mod registry;
+mod report;
+mod server;
+mod snapshot;
+mod steps;
+mod suggest;
+mod trunk;
+#[cfg(feature = "server")]
+mod tunnel;
 
-USAGE:
-  cargo run-wasm [OPTIONS] NAME
-
-OPTIONS:
-  --release                    Build in release mode, with optimizations
-  --example                    Build and run the example NAME instead of a package NAME
-  --features <FEATURES>...     Comma separated list of features to activate
-  --build-only                 Only build the WASM artifacts, do not run the dev server
-  --host <HOST>                Makes the dev server listen on host (default 'localhost')
-  --port <PORT>                Makes the dev server listen on port (default '8000')
-
-NAME:
-  Name of the package (crate) within the workspace to run.
-";
+pub use cancel::CancelToken;
+pub use error::Error;
+pub use report::{ArtifactSize, BuildReport};
+pub use server::{Request, Response};
+use steps::OutputFile;
+pub use steps::{Bindgen, BuildContext, BuildStep, CargoBuild, CopyAssets, RenderIndex};
 
+#[derive(Default)]
+#[cfg_attr(not(feature = "server"), allow(dead_code))]
 struct Args {
     release: bool,
+    verbosity: Option<Verbosity>,
     example: bool,
     name: String,
     features: Option<String>,
+    notify: bool,
     build_only: bool,
     host: Option<String>,
     port: Option<String>,
+    public_host: Option<String>,
+    unix_socket: Option<PathBuf>,
+    headers: Vec<ExtraHeader>,
+    permissions: Vec<(String, bool)>,
+    cors_origins: Vec<String>,
+    proxies: Vec<Proxy>,
+    assets_dirs: Vec<PathBuf>,
+    mounts: Vec<Mount>,
+    mime_types: Vec<(String, String)>,
+    spa: bool,
+    list_dirs: bool,
+    serve_all: bool,
+    gallery: bool,
+    no_streaming: bool,
+    no_bindgen: bool,
+    no_compression: bool,
+    throttle: Option<Throttle>,
+    cache_control: Option<String>,
+    no_immutable: bool,
+    open: bool,
+    browser: Option<String>,
+    browser_args: Vec<String>,
+    strict_port: bool,
+    auth: Option<String>,
+    mdns: bool,
+    tunnel: Option<String>,
+    no_qr: bool,
+    access_log: Option<PathBuf>,
+    access_log_format: Option<AccessLogFormat>,
+    message_format: Option<MessageFormat>,
+    color: Option<ColorChoice>,
+    dashboard: bool,
+    console: bool,
+    profile_startup: bool,
+    coverage: bool,
+    check_links: bool,
+    annotations: Option<Annotations>,
+    clean_on_exit: bool,
+    report: Option<PathBuf>,
+    history: bool,
+    out_dir: Option<PathBuf>,
+    dist: bool,
+    deploy_configs: Vec<DeployConfig>,
+    archive: Option<PathBuf>,
+    publish_itch: bool,
+    trunk_dist: bool,
+    dist_manifest: Option<PathBuf>,
+    check_dist: Option<PathBuf>,
+    pkg: Option<PkgTarget>,
+    profiles: Vec<&'static str>,
+    test_headless: bool,
+    ci_smoke: bool,
+    test_assertion: Option<String>,
+    webdriver: Option<String>,
+    test_timeout: Option<Duration>,
+    extra_args: ExtraArgs,
+    /// Only set from the config file.
+    css: Option<String>,
+    /// Only set from the package metadata.
+    canvas_id: Option<String>,
+    /// Only set when the package is in a nested workspace.
+    workspace_manifest: Option<PathBuf>,
+    /// Only set when the package is the one the runner is in.
+    package_target: Option<PackageTarget>,
+    /// Only set from cargo metadata.
+    package_metadata: serde_json::Value,
+    /// Resolved along with the args, since finding it may run cargo.
+    project_root: PathBuf,
 }
 
 impl Args {
-    pub fn from_env() -> Result<Self, String> {
-        let mut args = Arguments::from_env();
+    /// `extra_flags` and `extra_options` are the args declared by the runner, which are taken out before parsing our own.
+    /// `default_target` gives the package or example to run when no NAME is given.
+    pub fn from_env(
+        extra_flags: &[String],
+        extra_options: &[String],
+        default_target: impl FnOnce() -> Result<Option<(String, bool)>, String>,
+    ) -> Result<Self, String> {
+        Args::from_vec(process_args(), extra_flags, extra_options, default_target)
+    }
+
+    /// Like [`Args::from_env`], but reads `env_args` instead of the process args.
+    fn from_vec(
+        env_args: Vec<std::ffi::OsString>,
+        extra_flags: &[String],
+        extra_options: &[String],
+        default_target: impl FnOnce() -> Result<Option<(String, bool)>, String>,
+    ) -> Result<Self, String> {
+        let mut extra_args = ExtraArgs::default();
+        let mut remaining = vec![];
+        let mut env_args = env_args.into_iter();
+        while let Some(arg) = env_args.next() {
+            let arg_str = arg.to_str().unwrap_or_default();
+            if extra_flags.iter().any(|x| x == arg_str) {
+                extra_args.flags.push(arg_str.to_string());
+            } else if extra_options.iter().any(|x| x == arg_str) {
+                match env_args.next().and_then(|x| x.into_string().ok()) {
+                    Some(value) => extra_args.values.push((arg_str.to_string(), value)),
+                    None => return Err(format!("Expected a value for {}", arg_str)),
+                }
+            } else if let Some((name, value)) = arg_str
+                .split_once('=')
+                .filter(|(name, _)| extra_options.iter().any(|x| x == name))
+            {
+                extra_args
+                    .values
+                    .push((name.to_string(), value.to_string()));
+            } else {
+                remaining.push(arg);
+            }
+        }
+        let mut args = Arguments::from_vec(remaining);
         let release = args.contains("--release");
+        let verbosity = match (
+            args.contains(["-v", "--verbose"]),
+            args.contains(["-q", "--quiet"]),
+        ) {
+            (true, true) => return Err("--verbose and --quiet cannot be used together".to_string()),
+            (true, false) => Some(Verbosity::Verbose),
+            (false, true) => Some(Verbosity::Quiet),
+            (false, false) => None,
+        };
         let example = args.contains("--example");
+        let notify = args.contains("--notify");
         let build_only = args.contains("--build-only");
+        let spa = args.contains("--spa");
+        let list_dirs = args.contains("--list-dirs");
+        let serve_all = args.contains("--serve-all");
+        let gallery = args.contains("--gallery");
+        let no_streaming = args.contains("--no-streaming");
+        let no_bindgen = args.contains("--no-bindgen");
+        let no_compression = args.contains("--no-compression");
+        let no_immutable = args.contains("--no-immutable");
+        let open = args.contains("--open");
+        let strict_port = args.contains("--strict-port");
+        let no_qr = args.contains("--no-qr");
+        let mdns = args.contains("--mdns");
+        let dashboard = args.contains("--dashboard");
+        let console = args.contains("--console");
+        let profile_startup = args.contains("--profile-startup");
+        let coverage = args.contains("--coverage");
+        let check_links = args.contains("--check-links");
+        let annotations = match args
+            .opt_value_from_str::<_, String>("--annotations")
+            .map_err(|err| err.to_string())?
+            .as_deref()
+        {
+            Some("github") => Some(Annotations::Github),
+            Some("none") => Some(Annotations::None),
+            Some(format) => {
+                return Err(format!(
+                    "Invalid --annotations {:?}, expected 'github' or 'none'",
+                    format
+                ))
+            }
+            None => None,
+        };
+        let clean_on_exit = args.contains("--clean-on-exit");
+        let test_headless = args.contains("--test-headless");
+        let ci_smoke = args.contains("--ci-smoke");
+        let dist = args.contains("--dist");
+        // Handled before the args are read for each build, see RunWasm::gallery_dist.
+        let _ = args.contains("--gallery-dist");
 
-        let features: Option<String> = args.opt_value_from_str("--features").unwrap();
-        let host: Option<String> = args.opt_value_from_str("--host").unwrap();
-        let port: Option<String> = args.opt_value_from_str("--port").unwrap();
+        let features: Option<String> = args
+            .opt_value_from_str("--features")
+            .map_err(|err| err.to_string())?;
+        let host: Option<String> = args
+            .opt_value_from_str("--host")
+            .map_err(|err| err.to_string())?;
+        let port: Option<String> = args
+            .opt_value_from_str("--port")
+            .map_err(|err| err.to_string())?;
+        let public_host: Option<String> = args
+            .opt_value_from_str("--public-host")
+            .map_err(|err| err.to_string())?;
+        let unix_socket = match args.opt_value_from_str::<_, String>("--listen").map_err(|err| err.to_string())? {
+            Some(listen) => match listen.strip_prefix("unix:") {
+                Some(path) => Some(PathBuf::from(path)),
+                None => {
+                    return Err(format!(
+                        "Invalid --listen address {:?}, only unix:PATH is supported, use --host and --port to listen on TCP",
+                        listen
+                    ))
+                }
+            },
+            None => None,
+        };
+        let browser: Option<String> = args
+            .opt_value_from_str("--browser")
+            .map_err(|err| err.to_string())?;
+        let auth: Option<String> = args
+            .opt_value_from_str("--auth")
+            .map_err(|err| err.to_string())?;
+        let tunnel: Option<String> = args
+            .opt_value_from_str("--tunnel")
+            .map_err(|err| err.to_string())?;
+        if matches!(&auth, Some(auth) if !auth.contains(':')) {
+            return Err("--auth expects credentials in the form USER:PASSWORD".to_string());
+        }
+        let cache_control: Option<String> = args
+            .opt_value_from_str("--cache-control")
+            .map_err(|err| err.to_string())?;
+        let access_log: Option<PathBuf> = args
+            .opt_value_from_str("--access-log")
+            .map_err(|err| err.to_string())?;
+        let report: Option<PathBuf> = args
+            .opt_value_from_str("--report")
+            .map_err(|err| err.to_string())?;
+        let history = args.contains("--history");
+        let out_dir: Option<PathBuf> = args
+            .opt_value_from_str("--out-dir")
+            .map_err(|err| err.to_string())?;
+        let archive: Option<PathBuf> = args
+            .opt_value_from_str("--archive")
+            .map_err(|err| err.to_string())?;
+        let publish_itch = args.contains("--publish-itch");
+        let trunk_dist = args.contains("--trunk-dist");
+        let dist_manifest: Option<PathBuf> = args
+            .opt_value_from_str("--dist-manifest")
+            .map_err(|err| err.to_string())?;
+        let check_dist: Option<PathBuf> = args
+            .opt_value_from_str("--check-dist")
+            .map_err(|err| err.to_string())?;
+        let profiles = match args
+            .opt_value_from_str::<_, String>("--profiles")
+            .map_err(|err| err.to_string())?
+        {
+            Some(list) => profiles::parse(&list)?,
+            None => vec![],
+        };
+        let test_assertion: Option<String> = args
+            .opt_value_from_str("--test-assert")
+            .map_err(|err| err.to_string())?;
+        let webdriver: Option<String> = args
+            .opt_value_from_str("--webdriver")
+            .map_err(|err| err.to_string())?;
+        let test_timeout = match args
+            .opt_value_from_str::<_, String>("--test-timeout")
+            .map_err(|err| err.to_string())?
+        {
+            Some(secs) => match secs.parse() {
+                Ok(secs) => Some(Duration::from_secs(secs)),
+                Err(_) => {
+                    return Err(format!(
+                        "Invalid --test-timeout {:?}, expected a number of seconds",
+                        secs
+                    ))
+                }
+            },
+            None => None,
+        };
+        // Already read by RunWasm::resolve_project_root, since the config files are found from it.
+        let _: Option<PathBuf> = args
+            .opt_value_from_str("--project-root")
+            .map_err(|err| err.to_string())?;
+        let access_log_format = match args
+            .opt_value_from_str::<_, String>("--access-log-format")
+            .map_err(|err| err.to_string())?
+            .as_deref()
+        {
+            Some("common") => Some(AccessLogFormat::Common),
+            Some("json") => Some(AccessLogFormat::Json),
+            Some(format) => {
+                return Err(format!(
+                    "Invalid --access-log-format {:?}, expected 'common' or 'json'",
+                    format
+                ))
+            }
+            None => None,
+        };
+        let pkg = match args
+            .opt_value_from_str::<_, String>("--pkg")
+            .map_err(|err| err.to_string())?
+            .as_deref()
+        {
+            Some("web") => Some(PkgTarget::Web),
+            Some("bundler") => Some(PkgTarget::Bundler),
+            Some(target) => {
+                return Err(format!(
+                    "Invalid --pkg target {:?}, expected 'web' or 'bundler'",
+                    target
+                ))
+            }
+            None => None,
+        };
+        let message_format = match args
+            .opt_value_from_str::<_, String>("--message-format")
+            .map_err(|err| err.to_string())?
+            .as_deref()
+        {
+            Some("human") => Some(MessageFormat::Human),
+            Some("json") => Some(MessageFormat::Json),
+            Some(format) => {
+                return Err(format!(
+                    "Invalid --message-format {:?}, expected 'human' or 'json'",
+                    format
+                ))
+            }
+            None => None,
+        };
+        let color = match args
+            .opt_value_from_str::<_, String>("--color")
+            .map_err(|err| err.to_string())?
+            .as_deref()
+        {
+            Some("auto") => Some(ColorChoice::Auto),
+            Some("always") => Some(ColorChoice::Always),
+            Some("never") => Some(ColorChoice::Never),
+            Some(color) => {
+                return Err(format!(
+                    "Invalid --color {:?}, expected 'auto', 'always' or 'never'",
+                    color
+                ))
+            }
+            None => None,
+        };
+        let throttle = match args
+            .opt_value_from_str::<_, String>("--throttle")
+            .map_err(|err| err.to_string())?
+        {
+            Some(throttle) => Some(Throttle::parse(&throttle)?),
+            None => None,
+        };
+        let browser_args: Vec<String> = args
+            .values_from_str("--browser-arg")
+            .map_err(|err| err.to_string())?;
+        let headers = args
+            .values_from_str::<_, String>("--header")
+            .map_err(|err| err.to_string())?
+            .iter()
+            .map(|header| ExtraHeader::parse(header))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut permissions = vec![];
+        for (flag, allowed) in [("--allow-feature", true), ("--deny-feature", false)] {
+            for feature in args
+                .values_from_str::<_, String>(flag)
+                .map_err(|err| err.to_string())?
+            {
+                permissions.push((server::parse_permission_feature(&feature)?, allowed));
+            }
+        }
+        let cors_origins: Vec<String> = args
+            .values_from_str("--cors")
+            .map_err(|err| err.to_string())?;
+        let proxies = args
+            .values_from_str::<_, String>("--proxy")
+            .map_err(|err| err.to_string())?
+            .iter()
+            .map(|proxy| Proxy::parse(proxy))
+            .collect::<Result<Vec<_>, _>>()?;
+        let assets_dirs: Vec<PathBuf> = args
+            .values_from_str("--assets")
+            .map_err(|err| err.to_string())?;
+        let mounts = args
+            .values_from_str::<_, String>("--mount")
+            .map_err(|err| err.to_string())?
+            .iter()
+            .map(|mount| Mount::parse(mount))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mime_types = args
+            .values_from_str::<_, String>("--mime")
+            .map_err(|err| err.to_string())?
+            .iter()
+            .map(|mime_type| server::parse_mime_type(mime_type))
+            .collect::<Result<Vec<_>, _>>()?;
+        let deploy_configs = args
+            .values_from_str::<_, String>("--deploy-config")
+            .map_err(|err| err.to_string())?
+            .iter()
+            .map(|host| match host.as_str() {
+                "nginx" => Ok(DeployConfig::Nginx),
+                "netlify" => Ok(DeployConfig::Netlify),
+                "vercel" => Ok(DeployConfig::Vercel),
+                host => Err(format!(
+                    "Invalid --deploy-config {:?}, expected 'nginx', 'netlify' or 'vercel'",
+                    host
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
 
         let mut unused_args: Vec<String> = args
             .finish()
             .into_iter()
-            .map(|x| x.into_string().unwrap())
-            .collect();
+            .map(|x| {
+                x.into_string()
+                    .map_err(|x| format!("Invalid argument {:?}, expected UTF-8", x))
+            })
+            .collect::<Result<_, _>>()?;
 
         for unused_arg in &unused_args {
             if unused_arg.starts_with('-') {
@@ -54,23 +477,301 @@ impl Args {
             }
         }
 
-        match unused_args.len() {
-            0 => Err("Expected NAME arg, but there was no NAME arg".to_string()),
-            1 => Ok(Args {
-                release,
-                example,
-                name: unused_args.remove(0),
-                features,
-                build_only,
-                host,
-                port,
-            }),
-            len => Err(format!(
-                "Expected exactly one free arg, but there was {} free args: {:?}",
-                len, unused_args
-            )),
+        let (name, example) = match unused_args.len() {
+            0 => match default_target()? {
+                Some(target) => target,
+                None => return Err("Expected NAME arg, but there was no NAME arg".to_string()),
+            },
+            1 => (unused_args.remove(0), example),
+            len => {
+                return Err(format!(
+                    "Expected exactly one free arg, but there was {} free args: {:?}",
+                    len, unused_args
+                ))
+            }
+        };
+        Ok(Args {
+            release,
+            verbosity,
+            example,
+            name,
+            features,
+            notify,
+            build_only,
+            host,
+            port,
+            public_host,
+            unix_socket,
+            headers,
+            permissions,
+            cors_origins,
+            proxies,
+            assets_dirs,
+            mounts,
+            mime_types,
+            spa,
+            list_dirs,
+            serve_all,
+            gallery,
+            no_streaming,
+            no_bindgen,
+            no_compression,
+            throttle,
+            cache_control,
+            no_immutable,
+            open,
+            browser,
+            browser_args,
+            strict_port,
+            auth,
+            mdns,
+            tunnel,
+            no_qr,
+            access_log,
+            access_log_format,
+            message_format,
+            color,
+            dashboard,
+            console,
+            profile_startup,
+            coverage,
+            check_links,
+            annotations,
+            clean_on_exit,
+            report,
+            history,
+            out_dir,
+            dist,
+            deploy_configs,
+            archive,
+            publish_itch,
+            trunk_dist,
+            dist_manifest,
+            check_dist,
+            pkg,
+            profiles,
+            test_headless,
+            ci_smoke,
+            test_assertion,
+            webdriver,
+            test_timeout,
+            extra_args,
+            css: None,
+            canvas_id: None,
+            workspace_manifest: None,
+            package_target: None,
+            package_metadata: serde_json::Value::Null,
+            project_root: PathBuf::new(),
+        })
+    }
+}
+
+/// The version of cargo-run-wasm and of wasm-bindgen, which must match the version of the wasm-bindgen dependency of the app.
+fn version() -> String {
+    format!(
+        "cargo-run-wasm {}\nwasm-bindgen {}",
+        env!("CARGO_PKG_VERSION"),
+        wasm_bindgen_shared::version()
+    )
+}
+
+/// The process args without the program name.
+///
+/// When installed as the `cargo run-wasm` subcommand cargo passes `run-wasm` as the first arg, which is skipped too.
+fn process_args() -> Vec<std::ffi::OsString> {
+    let mut args = env::args_os();
+    let program = args.next().map(PathBuf::from);
+    let mut args: Vec<_> = args.collect();
+    let is_subcommand = matches!(
+        program.as_deref().and_then(Path::file_stem),
+        Some(stem) if stem == "cargo-run-wasm"
+    );
+    if is_subcommand && matches!(args.first(), Some(arg) if arg == "run-wasm") {
+        args.remove(0);
+    }
+    args
+}
+
+fn cargo() -> String {
+    env::var("CARGO").unwrap_or_else(|_| "cargo".to_string())
+}
+
+/// A package from `cargo metadata`, see [`workspace_packages`].
+struct Package {
+    metadata: serde_json::Value,
+    /// The manifest of the workspace the package belongs to, when that is not the workspace at the project root.
+    workspace_manifest: Option<PathBuf>,
+}
+
+/// The packages of the workspace at `project_root`, followed by those of the directories in its `workspace.exclude`.
+///
+/// Excluding a directory is how a nested workspace or a standalone package is kept out of the workspace,
+/// which cargo then only builds when given its manifest, so they are not found by `cargo metadata` for the workspace.
+fn workspace_packages(cargo: &str, project_root: &Path) -> Result<Vec<Package>, String> {
+    let mut packages = vec![];
+    let mut add = |metadata: serde_json::Value, workspace_manifest: Option<PathBuf>| {
+        if let serde_json::Value::Array(members) = metadata["packages"].clone() {
+            packages.extend(members.into_iter().map(|metadata| Package {
+                metadata,
+                workspace_manifest: workspace_manifest.clone(),
+            }));
+        }
+    };
+    add(cargo_metadata(cargo, project_root)?, None);
+    for dir in excluded_dirs(project_root) {
+        // Without a manifest cargo would find the workspace at the project root instead.
+        if !dir.join("Cargo.toml").is_file() {
+            continue;
+        }
+        // Excluded directories are not necessarily valid packages, e.g. test fixtures.
+        if let Ok(metadata) = cargo_metadata(cargo, &dir) {
+            let workspace_manifest = metadata["workspace_root"]
+                .as_str()
+                .map(|root| Path::new(root).join("Cargo.toml"));
+            add(metadata, workspace_manifest);
+        }
+    }
+    Ok(packages)
+}
+
+/// The directories listed in `workspace.exclude` of the Cargo.toml at `project_root`.
+fn excluded_dirs(project_root: &Path) -> Vec<PathBuf> {
+    let manifest = std::fs::read_to_string(project_root.join("Cargo.toml"))
+        .ok()
+        .and_then(|x| x.parse::<toml::Value>().ok());
+    let exclude = manifest
+        .as_ref()
+        .and_then(|x| x.get("workspace"))
+        .and_then(|x| x.get("exclude"))
+        .and_then(|x| x.as_array());
+    exclude
+        .into_iter()
+        .flatten()
+        .filter_map(|x| x.as_str())
+        .map(|x| project_root.join(x))
+        .collect()
+}
+
+/// The output of `cargo metadata` for the workspace packages, without their dependencies.
+fn cargo_metadata(cargo: &str, project_root: &Path) -> Result<serde_json::Value, String> {
+    let output = Command::new(cargo)
+        .current_dir(project_root)
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .output()
+        .map_err(|err| format!("Failed to run cargo metadata: {}", err))?;
+    if !output.status.success() {
+        return Err(format!(
+            "cargo metadata failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    serde_json::from_slice(&output.stdout)
+        .map_err(|err| format!("Failed to parse cargo metadata output: {}", err))
+}
+
+/// The wasm cargo builds for `args` into `target_dir`, relative to the project root.
+///
+/// Packages of nested workspaces are built from the project root too, so their wasm is found in the same place.
+fn wasm_path(args: &Args, target_dir: &str, profile: &str) -> PathBuf {
+    let target_profile = args
+        .project_root
+        .join(target_dir)
+        .join("wasm32-unknown-unknown")
+        .join(profile);
+    let wasm_name = match &args.package_target {
+        Some(PackageTarget::Bin(bin)) => bin.clone(),
+        // Library files are named after the crate, which has underscores in place of dashes.
+        Some(PackageTarget::Lib) => args.name.replace('-', "_"),
+        None => args.name.clone(),
+    };
+    if args.example {
+        target_profile.join("examples")
+    } else {
+        target_profile
+    }
+    .join(format!("{}.wasm", wasm_name))
+}
+
+/// The workspace root, which is the root of the workspace the run-wasm package is a member of, however deeply it is nested.
+///
+/// When not run via `cargo run`, e.g. as the installed `cargo run-wasm` subcommand, it is the workspace containing the current directory.
+fn project_root() -> Result<PathBuf, Error> {
+    let manifest_dir = env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from);
+    locate_workspace(manifest_dir.as_deref())
+}
+
+/// The manifest of the package the runner is in and the name of its binary or example, when run via `cargo run`.
+///
+/// The runner can share its package with what it runs, e.g. as an example of a standalone crate, so only its own target is left out.
+fn runner_target() -> Option<(PathBuf, String)> {
+    let manifest = Path::new(&env::var_os("CARGO_MANIFEST_DIR")?).join("Cargo.toml");
+    let exe = env::current_exe().ok()?;
+    Some((manifest, exe.file_stem()?.to_string_lossy().into_owned()))
+}
+
+/// Deletes the output directories of `name`, or of every package and example along with the wasm target directory.
+///
+/// `cargo clean` leaves these alone since they are outside of its target directory, so they would otherwise only grow.
+/// The target directory is shared by every package, so it is only deleted when cleaning everything.
+fn clean(project_root: &Path, name: Option<&str>) -> Result<(), Error> {
+    let dirs = match name {
+        Some(name) => vec![
+            project_root.join("target/wasm-examples").join(name),
+            project_root.join("target/wasm-dist").join(name),
+            project_root.join("target/wasm-pkg").join(name),
+            project_root.join("target/wasm-profiles").join(name),
+            project_root.join("target/wasm-coverage").join(name),
+        ],
+        None => vec![
+            project_root.join("target/wasm-examples"),
+            project_root.join("target/wasm-dist"),
+            project_root.join("target/wasm-pkg"),
+            project_root.join("target/wasm-gallery"),
+            project_root.join("target/wasm-profiles"),
+            project_root.join("target/wasm-coverage"),
+            project_root.join("target/wasm-examples-target"),
+            project_root.join("target/wasm-coverage-target"),
+        ],
+    };
+    for dir in dirs {
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)
+                .map_err(Error::io(format!("Failed to delete {}", dir.display())))?;
+            println!("Removed {}", dir.display());
         }
     }
+    Ok(())
+}
+
+/// Asks cargo for the root of the workspace containing `dir`, or the current directory when None.
+///
+/// Cargo knows which workspace a package belongs to from the `workspace` and `package.workspace` keys,
+/// so this is right for packages nested at any depth, unlike assuming the workspace is the parent directory.
+fn locate_workspace(dir: Option<&Path>) -> Result<PathBuf, Error> {
+    let cargo = cargo();
+    let mut command = Command::new(&cargo);
+    command.args(["locate-project", "--workspace", "--message-format", "plain"]);
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let output = command
+        .output()
+        .map_err(Error::io(format!("Failed to run {}", cargo)))?;
+    if !output.status.success() {
+        return Err(Error::InvalidArgs(format!(
+            "Could not find the workspace containing {}: {}",
+            dir.map_or_else(
+                || "the current directory".to_string(),
+                |dir| dir.display().to_string()
+            ),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let manifest_path = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    Ok(manifest_path
+        .parent()
+        .unwrap_or(&manifest_path)
+        .to_path_buf())
 }
 
 /// Call this in your run-wasm application.
@@ -82,16 +783,85 @@ impl Args {
 /// 4. Generate an index.html that runs the wasm
 /// 5. Launch a tiny webserver to serve index.html + your wasm
 ///
-/// It will block forever to keep the webserver running until killed with ctrl-c or similar
+/// It will block to keep the webserver running until stopped with ctrl-c
 ///
 /// The css argument will be included directly into a `<style type="text/css"></style>` element in the generated page.
 /// By default the body element will include some margin, so for full page apps you will want to remove that by calling like:
 /// ```no_run
 ///     cargo_run_wasm::run_wasm_with_css("body { margin: 0px; }");
 /// ```
+///
+/// Use [`RunWasm`] instead if you need more configuration than just the css.
 pub fn run_wasm_with_css(css: &str) {
-    // validate css
-    //
+    RunWasm::new().css(css).run();
+}
+
+/// Like [`run_wasm_with_css`], but the whole page is generated from `html`, for apps that need custom head contents or elements.
+///
+/// See [`RunWasm::html`] for the placeholders that are replaced.
+/// ```no_run
+///     cargo_run_wasm::run_wasm_with_html(
+///         r#"<!DOCTYPE html>
+///         <html>
+///           <head><script src="https://example.com/analytics.js"></script></head>
+///           <body>
+///             <div id="app"></div>
+///             <script type="module">
+///               import init from "./{{name}}.js";
+///               init({{init_arg}});
+///             </script>
+///           </body>
+///         </html>"#,
+///     );
+/// ```
+pub fn run_wasm_with_html(html: &str) {
+    RunWasm::new().html(html).run();
+}
+
+/// Renders an index.html that runs the wasm-bindgen output for `name`, exactly like [`RunWasm::build`] does.
+///
+/// This allows tools that serve the files themselves to generate the page without building through cargo-run-wasm.
+/// `template` replaces the bundled template when given, see [`RunWasm::html`] for the placeholders.
+/// When `streaming` is false the wasm is fetched as bytes, like with `--no-streaming`.
+///
+/// Panics if `css` contains `</style>`.
+/// ```
+/// let html = cargo_run_wasm::render_index(None, "demo", "body { margin: 0px; }", Some("game"), true);
+/// assert!(html.contains(r#"import init from "./demo.js";"#));
+/// assert!(html.contains(r#"<canvas id="game"></canvas>"#));
+/// ```
+pub fn render_index(
+    template: Option<&str>,
+    name: &str,
+    css: &str,
+    canvas_id: Option<&str>,
+    streaming: bool,
+) -> String {
+    validate_css(css);
+    let template = template.unwrap_or(include_str!("index.template.html"));
+    let init_arg = if streaming {
+        // wasm-bindgen defaults to fetching the wasm file and compiling it with `WebAssembly.instantiateStreaming`.
+        String::new()
+    } else {
+        // wasm-bindgen will only use `WebAssembly.instantiateStreaming` when given a Response, so give it the bytes instead.
+        format!(
+            "fetch(\"./{}_bg.wasm\").then((response) => response.arrayBuffer())",
+            name
+        )
+    };
+    let canvas = match canvas_id {
+        Some(id) => format!("<canvas id=\"{}\"></canvas>", server::escape_html(id)),
+        None => String::new(),
+    };
+    template
+        .replace("{{canvas}}", &canvas)
+        .replace("{{init_arg}}", &init_arg)
+        .replace("{{name}}", name)
+        // This is fine because a replaced {{name}} cant contain `{{css}} ` due to `{` not being valid in a crate name
+        .replace("{{css}}", css)
+}
+
+fn validate_css(css: &str) {
     // Someone could easily get around this with some extra spaces
     // but im not about to import regex or do a complicated implementation by hand.
     if css.contains("</style>") {
@@ -99,104 +869,3538 @@ pub fn run_wasm_with_css(css: &str) {
             "`</style>` detected in the css. This is disallowed to prevent injecting elements into the DOM."
         )
     }
+}
+
+/// Panics unless `path` stays within the output directory, since the file could otherwise be written anywhere.
+fn validate_output_path(path: &Path) -> PathBuf {
+    let within = path.components().all(|x| {
+        matches!(
+            x,
+            std::path::Component::Normal(_) | std::path::Component::CurDir
+        )
+    });
+    if !within || path.as_os_str().is_empty() {
+        panic!(
+            "Output file path {:?} must be relative to the output directory, without any `..`",
+            path
+        );
+    }
+    path.to_path_buf()
+}
+
+/// How much cargo-run-wasm prints to the terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only print errors and the url being served.
+    Quiet,
+    /// Also print warnings and noteworthy events such as missing files or assets being reloaded.
+    Normal,
+    /// Also log every request made to the dev server.
+    Verbose,
+}
+
+// Cant use `#[default]` until our MSRV is 1.62
+#[allow(clippy::derivable_impls)]
+impl Default for Verbosity {
+    fn default() -> Self {
+        Verbosity::Normal
+    }
+}
+
+/// The files generated by [`RunWasm::build`], ready to be served.
+pub struct BuildOutput {
+    status: BuildStatus,
+    report: BuildReport,
+    dir: PathBuf,
+    extra_args: ExtraArgs,
+    #[cfg_attr(not(feature = "server"), allow(dead_code))]
+    events: Arc<Events>,
+    /// The args resolved by [`RunWasm::build`], so that serving the output does not resolve them again.
+    #[cfg_attr(not(feature = "server"), allow(dead_code))]
+    args: Option<Args>,
+}
+
+impl BuildOutput {
+    /// The name of the package or example that was built.
+    pub fn name(&self) -> &str {
+        &self.status.name
+    }
+
+    /// The directory containing the generated index.html, js and wasm, which is served at the root of the dev server.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The cargo profile that was built, `debug` or `release`.
+    pub fn profile(&self) -> &str {
+        self.status.profile
+    }
+
+    /// The wasm file generated by wasm-bindgen, `<name>_bg.wasm`, or `<name>.wasm` as cargo built it when [`RunWasm::bindgen`] is disabled.
+    pub fn wasm_path(&self) -> &Path {
+        &self.status.artifacts[0]
+    }
+
+    /// The js glue generated by wasm-bindgen that loads the wasm, `<name>.js`, which does not exist when [`RunWasm::bindgen`] is disabled.
+    pub fn js_path(&self) -> &Path {
+        &self.status.artifacts[1]
+    }
+
+    /// The generated page that loads the js, `index.html`.
+    pub fn index_path(&self) -> &Path {
+        &self.status.artifacts[2]
+    }
+
+    /// The generated index.html, js and wasm files.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.status.artifacts
+    }
+
+    /// How long the build took, including running cargo and wasm-bindgen.
+    pub fn duration(&self) -> Duration {
+        self.status.duration
+    }
+
+    /// How long each phase of the build took and the sizes of the generated files.
+    pub fn report(&self) -> &BuildReport {
+        &self.report
+    }
+
+    /// The runner's own args that were passed on the command line.
+    pub fn extra_args(&self) -> &ExtraArgs {
+        &self.extra_args
+    }
+}
+
+/// The args declared by [`RunWasm::extra_flag`] and [`RunWasm::extra_option`] that were passed on the command line.
+#[derive(Clone, Debug, Default)]
+pub struct ExtraArgs {
+    flags: Vec<String>,
+    values: Vec<(String, String)>,
+}
+
+impl ExtraArgs {
+    /// Whether the flag `name`, e.g. `--fullscreen`, was passed.
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|x| x == name)
+    }
+
+    /// The value passed to the option `name`, e.g. `forest` for `--scene forest`.
+    ///
+    /// When the option is passed more than once the last value is returned.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(x, _)| x == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Describes the build that is about to run, given to the hooks registered with [`RunWasm::before_build`].
+pub struct BuildInfo {
+    name: String,
+    example: bool,
+    profile: &'static str,
+    project_root: PathBuf,
+    dir: PathBuf,
+    extra_args: ExtraArgs,
+}
+
+impl BuildInfo {
+    /// The name of the package or example being built.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether an example is being built rather than a package.
+    pub fn is_example(&self) -> bool {
+        self.example
+    }
+
+    /// The cargo profile being built, `debug` or `release`.
+    pub fn profile(&self) -> &str {
+        self.profile
+    }
+
+    /// The root of the workspace, which cargo is run from.
+    pub fn project_root(&self) -> &Path {
+        &self.project_root
+    }
+
+    /// The directory the generated index.html, js and wasm will be written to.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The runner's own args that were passed on the command line.
+    pub fn extra_args(&self) -> &ExtraArgs {
+        &self.extra_args
+    }
+}
+
+type BuildHook<T> = Box<dyn Fn(&T) -> Result<(), Box<dyn std::error::Error + Send + Sync>>>;
+
+/// The format of the access log enabled by [`RunWasm::access_log`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessLogFormat {
+    /// The common log format used by most web servers, e.g. `127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "GET /index.html HTTP/1.1" 200 2326`.
+    Common,
+    /// A JSON object per line, which also includes the duration, referer and user agent of each request.
+    Json,
+}
+
+// Cant use `#[default]` until our MSRV is 1.62
+#[allow(clippy::derivable_impls)]
+impl Default for AccessLogFormat {
+    fn default() -> Self {
+        AccessLogFormat::Common
+    }
+}
+
+/// The CI system to annotate errors for, set via [`RunWasm::annotations`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Annotations {
+    /// Only print errors as usual.
+    None,
+    /// Also print GitHub Actions workflow commands such as `::error file=src/lib.rs,line=3::...`,
+    /// which show errors and warnings on the lines of the pull request diff that caused them.
+    Github,
+}
+
+/// The format of the output printed by cargo-run-wasm, set via [`RunWasm::message_format`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageFormat {
+    /// Human readable output.
+    Human,
+    /// A JSON object per line describing progress, the generated files, the url being served and errors,
+    /// for editor plugins and scripts to consume.
+    ///
+    /// Each object has a `reason` field saying what it describes, e.g. `build-finished` or `server-started`.
+    /// The messages cargo emits with `--message-format json` are passed through as is,
+    /// apart from its `build-finished` which is replaced by ours since that also covers running wasm-bindgen.
+    Json,
+}
+
+// Cant use `#[default]` until our MSRV is 1.62
+#[allow(clippy::derivable_impls)]
+impl Default for MessageFormat {
+    fn default() -> Self {
+        MessageFormat::Human
+    }
+}
+
+/// A static host to write a config for with [`RunWasm::deploy_config`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeployConfig {
+    /// A `nginx.conf` snippet to include in the server block.
+    Nginx,
+    /// A Netlify `_headers` file.
+    Netlify,
+    /// A `vercel.json`.
+    Vercel,
+}
+
+/// Which environment the package written by [`RunWasm::pkg`] is for, matching the targets of wasm-pack of the same name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PkgTarget {
+    /// An ES module that can be imported directly by a browser, after calling its default export to load the wasm.
+    Web,
+    /// An ES module that imports the wasm, for bundlers such as webpack or vite to resolve.
+    Bundler,
+}
+
+/// Whether to color the output, set via [`RunWasm::color`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Color the output when printing to a terminal, unless the `NO_COLOR` environment variable is set.
+    /// `CARGO_TERM_COLOR` is respected as well.
+    Auto,
+    /// Always color the output.
+    Always,
+    /// Never color the output.
+    Never,
+}
+
+// Cant use `#[default]` until our MSRV is 1.62
+#[allow(clippy::derivable_impls)]
+impl Default for ColorChoice {
+    fn default() -> Self {
+        ColorChoice::Auto
+    }
+}
+
+/// A builder for configuring cargo-run-wasm beyond what [`run_wasm_with_css`] allows.
+///
+/// ```no_run
+///     cargo_run_wasm::RunWasm::new()
+///         .css("body { margin: 0px; }")
+///         .header("Cross-Origin-Opener-Policy", "same-origin")
+///         .header("Cross-Origin-Embedder-Policy", "require-corp")
+///         .run();
+/// ```
+///
+/// The command line takes priority over what is set here, while what is set here takes priority over the `CARGO_RUN_WASM_*` environment variables,
+/// the `[package.metadata.run-wasm]` of the package and `run-wasm.toml` in the workspace root, which only fill in the settings this builder leaves unset.
+/// The css is the exception, a `css` in the package metadata or `run-wasm.toml` replaces the css passed to [`run_wasm_with_css`].
+#[derive(Default)]
+pub struct RunWasm {
+    css: String,
+    /// Replaces the bundled index.template.html when set.
+    html: Option<String>,
+    /// The package or example to run and whether it is an example, reading them from the process args when None.
+    target: Option<(String, bool)>,
+    project_root: Option<PathBuf>,
+    release: Option<bool>,
+    features: Option<String>,
+    build_only: bool,
+    host: Option<String>,
+    port: Option<u16>,
+    verbosity: Verbosity,
+    message_format: MessageFormat,
+    color: ColorChoice,
+    notify: Option<bool>,
+    headers: Vec<ExtraHeader>,
+    permissions: Vec<(String, bool)>,
+    cors_origins: Vec<String>,
+    proxies: Vec<Proxy>,
+    assets_dirs: Vec<PathBuf>,
+    output_files: Vec<(PathBuf, OutputFile)>,
+    mounts: Vec<Mount>,
+    mime_types: Vec<(String, String)>,
+    spa: bool,
+    list_dirs: bool,
+    serve_all: bool,
+    gallery: bool,
+    gallery_dist: bool,
+    no_streaming: bool,
+    no_bindgen: bool,
+    no_compression: bool,
+    throttle: Option<Throttle>,
+    cache_control: Option<String>,
+    no_immutable: bool,
+    open: Option<bool>,
+    browser: Option<String>,
+    browser_args: Vec<String>,
+    strict_port: bool,
+    public_host: Option<String>,
+    unix_socket: Option<PathBuf>,
+    reuse_address: Option<bool>,
+    max_connections: Option<usize>,
+    read_timeout: Option<Duration>,
+    auth: Option<String>,
+    mdns: bool,
+    tunnel: Option<String>,
+    no_qr: Option<bool>,
+    access_log: Option<(PathBuf, AccessLogFormat)>,
+    dashboard: bool,
+    console: bool,
+    profile_startup: bool,
+    coverage: bool,
+    check_links: bool,
+    annotations: Option<Annotations>,
+    clean_on_exit: bool,
+    report: Option<PathBuf>,
+    history: bool,
+    out_dir: Option<PathBuf>,
+    dist: bool,
+    deploy_configs: Vec<DeployConfig>,
+    archive: Option<PathBuf>,
+    publish_itch: bool,
+    trunk_dist: bool,
+    dist_manifest: Option<PathBuf>,
+    check_dist: Option<PathBuf>,
+    pkg: Option<PkgTarget>,
+    profiles: Option<String>,
+    test_headless: bool,
+    ci_smoke: bool,
+    test_assertion: Option<String>,
+    webdriver: Option<String>,
+    test_timeout: Option<Duration>,
+    cancel: CancelToken,
+    on_serve: Option<Box<dyn FnOnce(SocketAddr)>>,
+    on_shutdown: Vec<Box<dyn FnOnce()>>,
+    request_hooks: Vec<RequestHook>,
+    response_hooks: Vec<ResponseHook>,
+    before_build: Vec<BuildHook<BuildInfo>>,
+    after_build: Vec<BuildHook<BuildOutput>>,
+    /// The default steps are used when None.
+    steps: Option<Vec<Box<dyn BuildStep>>>,
+    extra_flags: Vec<String>,
+    extra_options: Vec<String>,
+    help: help::Custom,
+}
+
+impl RunWasm {
+    pub fn new() -> Self {
+        RunWasm::default()
+    }
+
+    /// The css will be included directly into a `<style type="text/css"></style>` element in the generated page.
+    pub fn css(mut self, css: &str) -> Self {
+        self.css = css.to_string();
+        self
+    }
+
+    /// Use `html` as the template for the generated index.html instead of the bundled one, for full control over the page.
+    ///
+    /// These placeholders are replaced:
+    /// * `{{name}}` with the name of the package or example, the generated js is at `./{{name}}.js`
+    /// * `{{css}}` with the css given via [`RunWasm::css`] or configured for the package
+    /// * `{{init_arg}}` with the argument to pass to the generated `init` function, which `--no-streaming` relies on
+    /// * `{{canvas}}` with the `<canvas>` element configured for the package, if any
+    ///
+    /// The page must import and call `init` like the bundled template does:
+    /// ```html
+    /// <script type="module">
+    ///   import init from "./{{name}}.js";
+    ///   window.addEventListener("load", () => {
+    ///     init({{init_arg}});
+    ///   });
+    /// </script>
+    /// ```
+    pub fn html(mut self, html: &str) -> Self {
+        self.html = Some(html.to_string());
+        self
+    }
+
+    /// Run the package `name`, instead of reading what to run and any other options from the process args.
+    ///
+    /// This allows tools to embed cargo-run-wasm with a fully programmatic configuration:
+    /// ```no_run
+    ///     cargo_run_wasm::RunWasm::new()
+    ///         .package("demo")
+    ///         .release(true)
+    ///         .port(9000)
+    ///         .run();
+    /// ```
+    pub fn package(mut self, name: &str) -> Self {
+        self.target = Some((name.to_string(), false));
+        self
+    }
+
+    /// Run the example `name`, instead of reading what to run and any other options from the process args.
+    ///
+    /// See [`RunWasm::package`].
+    pub fn example(mut self, name: &str) -> Self {
+        self.target = Some((name.to_string(), true));
+        self
+    }
+
+    /// Use `dir` as the workspace root, which cargo is run from and the generated files are written below,
+    /// instead of finding it from the runner crate or the current directory.
+    ///
+    /// Useful when running from a prebuilt binary or a test, where neither is within the workspace.
+    /// Can also be set via `--project-root` on the command line, which takes priority.
+    pub fn project_root<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.project_root = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// When enabled the wasm is built in release mode, with optimizations.
+    ///
+    /// Can also be enabled via `--release` on the command line, or via `release` in `run-wasm.toml` or `CARGO_RUN_WASM_RELEASE` when this is not called,
+    /// see [`RunWasm`].
+    pub fn release(mut self, release: bool) -> Self {
+        self.release = Some(release);
+        self
+    }
+
+    /// Comma separated list of features to activate when building the wasm.
+    ///
+    /// Can also be set via `--features` on the command line, which takes priority.
+    pub fn features(mut self, features: &str) -> Self {
+        self.features = Some(features.to_string());
+        self
+    }
+
+    /// When enabled only the wasm artifacts are built, without running the dev server.
+    ///
+    /// Can also be enabled via `--build-only` on the command line.
+    pub fn build_only(mut self, build_only: bool) -> Self {
+        self.build_only = build_only;
+        self
+    }
+
+    /// The host the dev server listens on, defaults to `localhost`.
+    ///
+    /// Can also be set via `--host` on the command line, which takes priority.
+    pub fn host(mut self, host: &str) -> Self {
+        self.host = Some(host.to_string());
+        self
+    }
+
+    /// The port the dev server listens on, defaults to `8000`. Use `0` to pick any free port.
+    ///
+    /// Can also be set via `--port` on the command line, which takes priority.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Controls how much cargo-run-wasm prints, defaults to [`Verbosity::Normal`].
+    ///
+    /// This only affects what is printed to the terminal, spans and events are always reported via `tracing` for runners that install a subscriber.
+    /// Can also be set via `--verbose` or `--quiet` on the command line, which take priority.
+    pub fn verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Controls the format of the output, defaults to [`MessageFormat::Human`].
+    ///
+    /// With [`MessageFormat::Json`] only JSON objects are printed to stdout, apart from warnings about e.g. failing to open a browser,
+    /// so tools should skip lines that do not start with `{`. Cargo still renders its diagnostics to stderr.
+    /// Can also be set via `--message-format` on the command line, which takes priority.
+    pub fn message_format(mut self, message_format: MessageFormat) -> Self {
+        self.message_format = message_format;
+        self
+    }
+
+    /// Also print the errors and warnings of rustc, and errors such as wasm-bindgen failing, as annotations for a CI system,
+    /// so that a failing wasm build shows up on the lines of the pull request diff that caused it.
+    ///
+    /// By default [`Annotations::Github`] is used when the `GITHUB_ACTIONS` environment variable is `true`, unless using [`MessageFormat::Json`].
+    /// Cargo leaves the diagnostics out of its JSON messages while rendering them itself, so cargo-run-wasm renders them instead while annotating.
+    /// Can also be set via `--annotations <FORMAT>` on the command line, which takes priority.
+    pub fn annotations(mut self, annotations: Annotations) -> Self {
+        self.annotations = Some(annotations);
+        self
+    }
+
+    /// Controls whether the output is colored, defaults to [`ColorChoice::Auto`].
+    ///
+    /// Can also be set via `--color` on the command line, which takes priority.
+    pub fn color(mut self, color: ColorChoice) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// When enabled a desktop notification is shown when the build succeeds or fails,
+    /// so that you can switch to something else during a slow release build.
+    ///
+    /// Can also be enabled via `--notify` on the command line, or via `notify` in `run-wasm.toml` or `CARGO_RUN_WASM_NOTIFY` when this is not called,
+    /// see [`RunWasm`].
+    pub fn notify(mut self, notify: bool) -> Self {
+        self.notify = Some(notify);
+        self
+    }
+
+    /// Add a header to every response sent by the dev server.
+    ///
+    /// Headers passed via `--header` on the command line are added after these.
+    pub fn header(self, name: &str, value: &str) -> Self {
+        self.add_header(None, name, value)
+    }
+
+    /// Add a header to responses sent by the dev server for request paths matching `path_glob`.
+    ///
+    /// In the glob `*` matches any sequence of characters, including `/`, and `?` matches any single character.
+    /// The glob is matched against the full request path, e.g. `*.wasm` or `/assets/*`.
+    pub fn header_for_path(self, path_glob: &str, name: &str, value: &str) -> Self {
+        self.add_header(Some(path_glob.to_string()), name, value)
+    }
+
+    /// Allow cross origin requests to the dev server from `origin`, e.g. `http://localhost:3000`.
+    ///
+    /// Pass `*` to allow requests from any origin.
+    /// Origins passed via `--cors` on the command line are allowed in addition to these.
+    pub fn cors_origin(mut self, origin: &str) -> Self {
+        self.cors_origins.push(origin.to_string());
+        self
+    }
 
-    let args = match Args::from_env() {
-        Ok(args) => args,
-        Err(err) => {
-            println!("{}\n\n{}", err, HELP);
-            return;
+    /// Forward dev server requests for `prefix` and any paths below it to the http server at `target`.
+    ///
+    /// e.g. `.proxy("/api", "http://localhost:3000")` forwards a request for `/api/users` to `http://localhost:3000/api/users`.
+    /// If `target` includes a path it is prepended to the forwarded path.
+    /// Proxies passed via `--proxy` on the command line are checked after these.
+    pub fn proxy(mut self, prefix: &str, target: &str) -> Self {
+        match Proxy::new(prefix, target) {
+            Ok(proxy) => self.proxies.push(proxy),
+            Err(err) => panic!("{}", err),
         }
-    };
-    let profile = if args.release { "release" } else { "debug" };
-
-    // build wasm example via cargo
-    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
-    let project_root = Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap())
-        .ancestors()
-        .nth(1)
-        .unwrap()
-        .to_path_buf();
-    let mut cargo_args = vec![
-        "build",
-        "--target",
-        "wasm32-unknown-unknown",
-        // It is common to setup a faster linker such as mold or lld to run for just your native target.
-        // It cant be set for wasm as wasm doesnt support building with these linkers.
-        // This results in a separate rustflags value for native and wasm builds.
-        // Currently rust triggers a full rebuild every time the rustflags value changes.
-        //
-        // Therefore we have this hack where we use a different target dir for wasm builds to avoid constantly triggering full rebuilds.
-        // When this issue is resolved we might be able to remove this hack: https://github.com/rust-lang/cargo/issues/8716
-        "--target-dir",
-        "target/wasm-examples-target",
-    ];
-    if args.example {
-        cargo_args.extend(["--example", &args.name]);
-    } else {
-        cargo_args.extend(["--package", &args.name]);
+        self
     }
-    if let Some(features) = &args.features {
-        cargo_args.extend(["--features", features]);
+
+    /// Copy the contents of `dir` into the output directory so that its files are served alongside the generated files.
+    ///
+    /// A relative `dir` is relative to the workspace root.
+    /// While the dev server is running the directory is watched, any changes are copied over and the page is reloaded.
+    /// Directories passed via `--assets` on the command line are copied after these.
+    pub fn assets_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.assets_dirs.push(dir.as_ref().to_path_buf());
+        self
     }
-    if args.release {
-        cargo_args.push("--release");
+
+    /// Write a file containing `contents` to `path` in the output directory, e.g. a config or data file the page fetches.
+    ///
+    /// The file is written as part of the build along with the assets, so it is in place before the dev server starts.
+    /// `path` must be relative and within the output directory, e.g. `config.json` or `data/level1.json`.
+    /// ```no_run
+    ///     cargo_run_wasm::RunWasm::new()
+    ///         .output_file("config.json", r#"{ "debug": true }"#)
+    ///         .output_file_from("worker.js", "demo/worker.js")
+    ///         .run();
+    /// ```
+    pub fn output_file<P: AsRef<Path>, C: AsRef<[u8]>>(mut self, path: P, contents: C) -> Self {
+        let path = validate_output_path(path.as_ref());
+        self.output_files
+            .push((path, OutputFile::Contents(contents.as_ref().to_vec())));
+        self
     }
-    let status = Command::new(&cargo)
-        .current_dir(&project_root)
-        .args(&cargo_args)
-        .status()
-        .unwrap();
-    if !status.success() {
-        // We can return without printing anything because cargo will have already displayed an appropriate error.
-        return;
+
+    /// Copy the file at `source` to `path` in the output directory, e.g. a worker script.
+    ///
+    /// A relative `source` is relative to the workspace root, see [`RunWasm::output_file`] for `path`.
+    /// Unlike [`RunWasm::assets_dir`] the file is not watched for changes.
+    pub fn output_file_from<P: AsRef<Path>, S: AsRef<Path>>(mut self, path: P, source: S) -> Self {
+        let path = validate_output_path(path.as_ref());
+        self.output_files
+            .push((path, OutputFile::Copy(source.as_ref().to_path_buf())));
+        self
     }
 
-    // run wasm-bindgen on wasm file output by cargo, write to the destination folder
-    let target_profile = project_root
-        .join("target/wasm-examples-target/wasm32-unknown-unknown")
-        .join(profile);
-    let wasm_source = if args.example {
-        target_profile.join("examples")
-    } else {
-        target_profile
+    /// Serve the contents of `dir` at `prefix` and any paths below it, e.g. `.mount("/data", "testdata")`.
+    ///
+    /// Unlike [`RunWasm::assets_dir`] the files are served from their original location instead of being copied,
+    /// which suits large static fixtures.
+    /// A relative `dir` is relative to the workspace root.
+    /// Mounts passed via `--mount` on the command line are checked after these.
+    pub fn mount<P: AsRef<Path>>(mut self, prefix: &str, dir: P) -> Self {
+        match Mount::new(prefix, dir.as_ref()) {
+            Ok(mount) => self.mounts.push(mount),
+            Err(err) => panic!("{}", err),
+        }
+        self
     }
-    .join(format!("{}.wasm", &args.name));
-
-    let example_dest = project_root.join("target/wasm-examples").join(&args.name);
-    std::fs::create_dir_all(&example_dest).unwrap();
-    let mut bindgen = wasm_bindgen_cli_support::Bindgen::new();
-    bindgen
-        .web(true)
-        .unwrap()
-        .omit_default_module_path(false)
-        .input_path(&wasm_source)
-        .generate(&example_dest)
-        .unwrap();
 
-    // process template index.html and write to the destination folder
-    let index_template = include_str!("index.template.html");
-    let index_processed = index_template
-        .replace("{{name}}", &args.name)
-        // This is fine because a replaced {{name}} cant contain `{{css}} ` due to `{` not being valid in a crate name
-        .replace("{{css}}", css);
-    std::fs::write(example_dest.join("index.html"), index_processed).unwrap();
+    /// Serve files with the extension `extension` with the `Content-Type` `content_type`, e.g. `.mime_type("ktx2", "image/ktx2")`.
+    ///
+    /// Browsers reject some fetches, such as module scripts, when the content type is wrong or missing.
+    /// This overrides the built in content type for the extension, which is `application/octet-stream` for unknown extensions.
+    /// Mime types passed via `--mime` on the command line take priority.
+    pub fn mime_type(mut self, extension: &str, content_type: &str) -> Self {
+        let mime_type =
+            server::new_mime_type(extension, content_type).unwrap_or_else(|err| panic!("{}", err));
+        self.mime_types.push(mime_type);
+        self
+    }
 
-    if !args.build_only {
-        let host = args.host.unwrap_or_else(|| "localhost".into());
-        let port = args
-            .port
-            .unwrap_or_else(|| "8000".into())
-            .parse()
-            .expect("Port should be an integer");
+    /// When enabled directories without an index.html, e.g. in a [`RunWasm::mount`], show an index of their files,
+    /// which helps to debug wrong relative paths.
+    ///
+    /// Can also be enabled via `--list-dirs` on the command line.
+    pub fn directory_listing(mut self, directory_listing: bool) -> Self {
+        self.list_dirs = directory_listing;
+        self
+    }
 
-        // run webserver on destination folder
-        println!("\nServing `{}` on http://{}:{}", args.name, host, port);
-        devserver_lib::run(
-            &host,
-            port,
-            example_dest.as_os_str().to_str().unwrap(),
-            false,
-            "",
+    /// When enabled the dev server serves index.html instead of a 404 for unknown paths without an extension.
+    ///
+    /// This allows client side routers to handle deep links and refreshes.
+    /// Can also be enabled via `--spa` on the command line.
+    pub fn spa(mut self, spa: bool) -> Self {
+        self.spa = spa;
+        self
+    }
+
+    /// When enabled the dev server serves every previously built package and example, each under `/<name>/`,
+    /// instead of only the one being run.
+    ///
+    /// This allows leaving one dev server running that hosts every demo in the workspace.
+    /// Can also be enabled via `--serve-all` on the command line.
+    pub fn serve_all(mut self, serve_all: bool) -> Self {
+        self.serve_all = serve_all;
+        self
+    }
+
+    /// When enabled the dev server serves a page at `/` listing every package and example in the workspace,
+    /// with links to the ones that have been built. Implies [`RunWasm::serve_all`].
+    ///
+    /// Can also be enabled via `--gallery` on the command line.
+    pub fn gallery(mut self, gallery: bool) -> Self {
+        self.gallery = gallery;
+        self
+    }
+
+    /// When enabled every package and example in the workspace is built as with [`RunWasm::dist`] into `target/wasm-gallery/<name>`,
+    /// next to an index.html listing them like [`RunWasm::gallery`] does, giving one static site to deploy as a demo page.
+    /// The site is written to the [`RunWasm::out_dir`] instead when set.
+    ///
+    /// The listing uses the description of each package from its Cargo.toml, and the first line of the `//!` doc comment of each example.
+    /// Can also be enabled via `--gallery-dist` on the command line, in which case no NAME is needed.
+    pub fn gallery_dist(mut self, gallery_dist: bool) -> Self {
+        self.gallery_dist = gallery_dist;
+        self
+    }
+
+    /// By default the wasm is compiled with `WebAssembly.instantiateStreaming` while it is still downloading,
+    /// falling back to `WebAssembly.instantiate` if the server does not send the `application/wasm` MIME type.
+    ///
+    /// Pass false to always download the entire wasm file before compiling it, in case a proxy breaks streaming compilation.
+    /// Can also be disabled via `--no-streaming` on the command line.
+    pub fn streaming(mut self, streaming: bool) -> Self {
+        self.no_streaming = !streaming;
+        self
+    }
+
+    /// By default the wasm is run through wasm-bindgen, and the build fails early if the package does not depend on it,
+    /// since wasm-bindgen would fail on it only after it has been compiled.
+    ///
+    /// Pass false for apps that load their wasm with js of their own, e.g. macroquad, to serve the wasm as cargo built it as `<name>.wasm`.
+    /// The index.html is then expected to come from the assets, otherwise one is generated that instantiates the wasm without any imports.
+    /// Can also be disabled via `--no-bindgen` on the command line.
+    pub fn bindgen(mut self, bindgen: bool) -> Self {
+        self.no_bindgen = !bindgen;
+        self
+    }
+
+    /// By default the dev server compresses responses with brotli or gzip when the browser supports it.
+    ///
+    /// Pass false to disable compression.
+    /// Can also be disabled via `--no-compression` on the command line.
+    pub fn compression(mut self, compression: bool) -> Self {
+        self.no_compression = !compression;
+        self
+    }
+
+    /// Set the `Cache-Control` header sent with dev server responses, defaults to `no-cache`.
+    ///
+    /// `no-cache` makes the browser check for a newer version of every file before using its cached copy,
+    /// so that changes always show up after a rebuild.
+    /// Headers added via [`RunWasm::header`] or [`RunWasm::header_for_path`] take priority over this.
+    /// Can also be set via `--cache-control` on the command line, which takes priority.
+    pub fn cache_control(mut self, cache_control: &str) -> Self {
+        if cache_control.contains(&['\r', '\n'][..]) {
+            panic!("The Cache-Control value contains a newline");
+        }
+        self.cache_control = Some(cache_control.to_string());
+        self
+    }
+
+    /// By default files with a content hash in their name, e.g. `app-3f2a9c1b0d4e5f67.js`, are cached by the browser forever,
+    /// since any change to them results in a new name.
+    ///
+    /// Pass false to use the [`RunWasm::cache_control`] value for them too.
+    /// Can also be disabled via `--no-immutable` on the command line.
+    pub fn immutable(mut self, immutable: bool) -> Self {
+        self.no_immutable = !immutable;
+        self
+    }
+
+    /// When enabled the pages console output and uncaught errors, including panics, are printed in the terminal.
+    ///
+    /// Can also be enabled via `--console` on the command line.
+    pub fn console(mut self, console: bool) -> Self {
+        self.console = console;
+        self
+    }
+
+    /// When enabled served pages mark when the wasm is fetched, instantiated and the first frame after that is drawn,
+    /// and once loaded report back how long each took, which is printed as a startup summary.
+    ///
+    /// The marks also show up in the browser devtools. With [`MessageFormat::Json`] the summary is printed as a `startup-profile` event instead.
+    /// Can also be enabled via `--profile-startup` on the command line.
+    pub fn profile_startup(mut self, profile_startup: bool) -> Self {
+        self.profile_startup = profile_startup;
+        self
+    }
+
+    /// When enabled the wasm is built with coverage instrumentation, and served pages post its coverage back every few seconds and when closed,
+    /// which is written to `target/wasm-coverage/<name>/<page>.profraw` for `llvm-profdata` and `llvm-cov` to report on.
+    ///
+    /// This requires a nightly toolchain, and the app to depend on [minicov](https://docs.rs/minicov) and export a `run_wasm_coverage` function
+    /// returning the coverage captured by minicov, behind `#[cfg(run_wasm_coverage)]` which is set for these builds, see the readme.
+    /// Coverage builds use their own cargo target directory, since changing the rustflags rebuilds every dependency.
+    /// Can also be enabled via `--coverage` on the command line.
+    pub fn coverage(mut self, coverage: bool) -> Self {
+        self.coverage = coverage;
+        self
+    }
+
+    /// When enabled the served page is opened in the default browser once the dev server is running.
+    ///
+    /// Can also be enabled via `--open` on the command line, or via `open` in `run-wasm.toml` or `CARGO_RUN_WASM_OPEN` when this is not called,
+    /// see [`RunWasm`].
+    pub fn open(mut self, open: bool) -> Self {
+        self.open = Some(open);
+        self
+    }
+
+    /// Open the served page in `browser` instead of the default browser. Implies [`RunWasm::open`].
+    ///
+    /// `browser` can be the name of a browser, e.g. `firefox`, or a path to the browser executable.
+    /// Can also be set via `--browser` on the command line, which takes priority.
+    pub fn browser(mut self, browser: &str) -> Self {
+        self.browser = Some(browser.to_string());
+        self
+    }
+
+    /// Pass `arg` to the browser set by [`RunWasm::browser`], e.g. `--enable-unsafe-webgpu`.
+    ///
+    /// Args passed via `--browser-arg` on the command line are passed after these.
+    pub fn browser_arg(mut self, arg: &str) -> Self {
+        self.browser_args.push(arg.to_string());
+        self
+    }
+
+    /// By default if the dev servers port is already in use the next free port is used instead.
+    ///
+    /// When enabled the dev server will fail to start instead.
+    /// Can also be enabled via `--strict-port` on the command line.
+    pub fn strict_port(mut self, strict_port: bool) -> Self {
+        self.strict_port = strict_port;
+        self
+    }
+
+    /// Show and open urls with `host` instead of the address the dev server listens on, without changing what it listens on.
+    ///
+    /// Useful inside a container, where the dev server has to listen on `0.0.0.0` but should be visited via e.g. `localhost`.
+    /// Can also be set via `--public-host` on the command line, which takes priority.
+    pub fn public_host(mut self, host: &str) -> Self {
+        self.public_host = Some(host.to_string());
+        self
+    }
+
+    /// Listen on a unix domain socket at `path` instead of on a TCP port,
+    /// e.g. so that the dev server can sit behind a local reverse proxy without claiming a port.
+    ///
+    /// [`RunWasm::on_serve`] is not called and the page cannot be opened in a browser.
+    /// Can also be set via `--listen unix:PATH` on the command line, which takes priority.
+    pub fn unix_socket<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.unix_socket = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Simulate a slow network by delaying every response and limiting the rate they are sent at,
+    /// to see how the page behaves while a large wasm file is downloading.
+    ///
+    /// `preset` is one of `slow-3g`, `3g` or `4g`, roughly matching those of the chrome devtools,
+    /// or `KBITS,MS`, e.g. `1000,200` for 1000 kbit/s with 200ms of latency.
+    /// The bandwidth is shared by all connections, like a real network.
+    /// Can also be set via `--throttle` on the command line, which takes priority.
+    pub fn throttle(mut self, preset: &str) -> Self {
+        self.throttle = Some(Throttle::parse(preset).unwrap_or_else(|err| panic!("{}", err)));
+        self
+    }
+
+    /// Override whether the dev servers TCP socket sets `SO_REUSEADDR`, by default the rust standard library decides.
+    ///
+    /// On unix it is set by default, which allows restarting the dev server straight away on the same port.
+    /// On windows it is not, since there it allows another process to take over a port that is in use.
+    pub fn reuse_address(mut self, reuse_address: bool) -> Self {
+        self.reuse_address = Some(reuse_address);
+        self
+    }
+
+    /// Stop accepting new connections while `max` connections are open, by default there is no limit.
+    ///
+    /// Connections beyond the limit wait until another closes.
+    /// Note that pages hold connections open for reloading and [`RunWasm::console`], which count towards the limit.
+    pub fn max_connections(mut self, max: usize) -> Self {
+        if max == 0 {
+            panic!("max_connections must be at least 1");
+        }
+        self.max_connections = Some(max);
+        self
+    }
+
+    /// How long the dev server waits for the request on a new connection, or for the rest of it, before closing the connection.
+    ///
+    /// Defaults to 30 seconds.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        if timeout.is_zero() {
+            panic!("read_timeout must not be zero");
+        }
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Require the browser to log in to the dev server with `user` and `password` via HTTP basic authentication.
+    ///
+    /// Useful when the dev server is reachable from the LAN or a tunnel, so that in progress work isnt visible to everyone.
+    /// The credentials are not encrypted over http, so this only keeps out casual visitors.
+    /// Can also be set via `--auth user:password` on the command line, which takes priority.
+    pub fn auth(mut self, user: &str, password: &str) -> Self {
+        if user.contains(':') {
+            panic!("The auth user {:?} cannot contain a `:`", user);
+        }
+        self.auth = Some(format!("{}:{}", user, password));
+        self
+    }
+
+    /// When enabled the dev server is advertised on the LAN via mDNS as `<name>.local`, e.g. `http://my-game.local:8000`.
+    ///
+    /// This only has an effect when listening on all interfaces, e.g. `--host 0.0.0.0`.
+    /// Can also be enabled via `--mdns` on the command line.
+    pub fn mdns(mut self, mdns: bool) -> Self {
+        self.mdns = mdns;
+        self
+    }
+
+    /// Share the dev server on a public https url so that remote teammates can try it, by running a tunneling tool.
+    ///
+    /// `backend` is one of `cloudflared`, `ngrok` or `localhost.run`, or a custom command where `{port}` and `{url}`
+    /// are replaced with the dev servers port and local url, e.g. `bore local {port} --to bore.pub`.
+    /// The tool must be installed separately, except for `localhost.run` which only needs ssh.
+    /// Consider also setting [`RunWasm::auth`], since anyone with the url can view the page.
+    /// Can also be set via `--tunnel` on the command line, which takes priority.
+    pub fn tunnel(mut self, backend: &str) -> Self {
+        self.tunnel = Some(backend.to_string());
+        self
+    }
+
+    /// By default when the dev server is reachable from the LAN a QR code of its url is printed, for easy testing on phones.
+    ///
+    /// Pass false to disable the QR code.
+    /// Can also be disabled via `--no-qr` on the command line, or via `no-qr` in `run-wasm.toml` or `CARGO_RUN_WASM_NO_QR` when this is not called,
+    /// see [`RunWasm`].
+    pub fn qr_code(mut self, qr_code: bool) -> Self {
+        self.no_qr = Some(!qr_code);
+        self
+    }
+
+    /// Call `callback` with the address the dev server is listening on once it is running.
+    ///
+    /// This is useful when the port was picked automatically, e.g. via `--port 0` or because the requested port was in use.
+    pub fn on_serve<F: FnOnce(SocketAddr) + 'static>(mut self, callback: F) -> Self {
+        self.on_serve = Some(Box::new(callback));
+        self
+    }
+
+    /// Call `callback` when the dev server is stopped with ctrl-c, before the process exits.
+    pub fn on_shutdown<F: FnOnce() + 'static>(mut self, callback: F) -> Self {
+        self.on_shutdown.push(Box::new(callback));
+        self
+    }
+
+    /// Accept the flag `name`, e.g. `--fullscreen`, on the command line for use by the runner itself, rather than rejecting it as unknown.
+    ///
+    /// Whether it was passed is available from [`BuildInfo::extra_args`] and [`BuildOutput::extra_args`].
+    pub fn extra_flag(mut self, name: &str) -> Self {
+        self.extra_flags.push(name.to_string());
+        self
+    }
+
+    /// Accept the option `name` with a value, e.g. `--scene forest` or `--scene=forest`, on the command line for use by the runner itself,
+    /// rather than rejecting it as unknown.
+    ///
+    /// ```no_run
+    ///     cargo_run_wasm::RunWasm::new()
+    ///         .extra_option("--scene")
+    ///         .before_build(|build| {
+    ///             let scene = build.extra_args().value("--scene").unwrap_or("default");
+    ///             let dir = build.project_root().join("demo/src");
+    ///             std::fs::write(dir.join("scene.txt"), scene)?;
+    ///             Ok(())
+    ///         })
+    ///         .run();
+    /// ```
+    /// The value is available from [`BuildInfo::extra_args`] and [`BuildOutput::extra_args`].
+    pub fn extra_option(mut self, name: &str) -> Self {
+        self.extra_options.push(name.to_string());
+        self
+    }
+
+    /// The name the runner is invoked with, shown in `--help` instead of `cargo run-wasm`, e.g. `cargo run --bin run-wasm --`.
+    pub fn program_name(mut self, name: &str) -> Self {
+        self.help.program_name = Some(name.to_string());
+        self
+    }
+
+    /// Replace the usage line shown in `--help`, which defaults to `<program name> [OPTIONS] NAME`.
+    ///
+    /// Useful for runners that always run the same package, and so take no NAME.
+    pub fn usage(mut self, usage: &str) -> Self {
+        self.help.usage = Some(usage.to_string());
+        self
+    }
+
+    /// Describe an option of the runner in `--help`, listed after the built in options.
+    ///
+    /// `usage` is shown as is, e.g. `--scene <SCENE>`, so this also works for options the runner parses itself.
+    /// ```no_run
+    ///     cargo_run_wasm::RunWasm::new()
+    ///         .program_name("cargo run-wasm")
+    ///         .extra_option("--scene")
+    ///         .help_option("--scene <SCENE>", "The scene the demo starts in")
+    ///         .run();
+    /// ```
+    pub fn help_option(mut self, usage: &str, description: &str) -> Self {
+        self.help
+            .options
+            .push((usage.to_string(), description.to_string()));
+        self
+    }
+
+    /// Call `hook` before cargo builds the wasm, e.g. to generate code or preprocess assets.
+    ///
+    /// ```no_run
+    ///     cargo_run_wasm::RunWasm::new()
+    ///         .before_build(|build| {
+    ///             let shaders = build.project_root().join("shaders");
+    ///             std::fs::write(shaders.join("generated.wgsl"), "// generated")?;
+    ///             Ok(())
+    ///         })
+    ///         .run();
+    /// ```
+    /// Hooks are called in the order they were added, an error returned by a hook fails the build with [`Error::Hook`].
+    pub fn before_build<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&BuildInfo) -> Result<(), Box<dyn std::error::Error + Send + Sync>> + 'static,
+    {
+        self.before_build.push(Box::new(hook));
+        self
+    }
+
+    /// Call `hook` after wasm-bindgen has run and index.html has been written, e.g. to optimize the wasm or copy extra files into [`BuildOutput::dir`].
+    ///
+    /// Hooks are called in the order they were added, an error returned by a hook fails the build with [`Error::Hook`].
+    pub fn after_build<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&BuildOutput) -> Result<(), Box<dyn std::error::Error + Send + Sync>> + 'static,
+    {
+        self.after_build.push(Box::new(hook));
+        self
+    }
+
+    /// Run `step` as part of the build, after the steps that were already added, which by default are [`RunWasm::default_steps`].
+    pub fn step<S: BuildStep + 'static>(mut self, step: S) -> Self {
+        self.steps
+            .get_or_insert_with(RunWasm::default_steps)
+            .push(Box::new(step));
+        self
+    }
+
+    /// Replace the steps the build is made of, which allows reordering or replacing the built in ones.
+    ///
+    /// For example to run a step between cargo and wasm-bindgen:
+    /// ```no_run
+    /// # struct Preprocess;
+    /// # impl cargo_run_wasm::BuildStep for Preprocess {
+    /// #     fn name(&self) -> &str { "preprocess" }
+    /// #     fn run(&self, _: &mut cargo_run_wasm::BuildContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>> { Ok(()) }
+    /// # }
+    /// let mut steps = cargo_run_wasm::RunWasm::default_steps();
+    /// steps.insert(1, Box::new(Preprocess));
+    /// cargo_run_wasm::RunWasm::new().steps(steps).run();
+    /// ```
+    pub fn steps(mut self, steps: Vec<Box<dyn BuildStep>>) -> Self {
+        self.steps = Some(steps);
+        self
+    }
+
+    /// The steps a build is made of unless replaced by [`RunWasm::steps`]: [`CargoBuild`], [`CopyAssets`], [`Bindgen`] and [`RenderIndex`].
+    pub fn default_steps() -> Vec<Box<dyn BuildStep>> {
+        vec![
+            Box::new(CargoBuild),
+            Box::new(CopyAssets),
+            Box::new(Bindgen),
+            Box::new(RenderIndex),
+        ]
+    }
+
+    /// Call `hook` for every request made to the dev server, if it returns a response that is sent instead.
+    ///
+    /// This allows mocking endpoints, serving generated content or blocking certain paths:
+    /// ```no_run
+    ///     use cargo_run_wasm::{Response, RunWasm};
+    ///
+    ///     RunWasm::new()
+    ///         .on_request(|request| {
+    ///             if request.path() != "/api/user" {
+    ///                 return None;
+    ///             }
+    ///             let mut response = Response::new(200);
+    ///             response.header("Content-Type", "application/json");
+    ///             response.set_body(r#"{ "name": "Ferris" }"#);
+    ///             Some(response)
+    ///         })
+    ///         .run();
+    /// ```
+    /// Hooks are called in the order they were added until one returns a response,
+    /// before requests are forwarded by [`RunWasm::proxy`].
+    pub fn on_request<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Request) -> Option<Response> + Send + Sync + 'static,
+    {
+        self.request_hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Call `hook` with every response the dev server sends, allowing it to be inspected or modified, e.g. to add headers.
+    ///
+    /// Hooks are called in the order they were added, after headers from [`RunWasm::header`] have been added.
+    /// Responses forwarded by [`RunWasm::proxy`] and event streams are sent as is.
+    pub fn on_response<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Request, &mut Response) + Send + Sync + 'static,
+    {
+        self.response_hooks.push(Box::new(hook));
+        self
+    }
+
+    /// When enabled the terminal shows a screen summarizing the build, artifact sizes, open connections and recent requests,
+    /// which is redrawn in place rather than scrolling, for long running sessions.
+    ///
+    /// Console output from [`RunWasm::console`] is shown on it as well.
+    /// Can also be enabled via `--dashboard` on the command line.
+    pub fn dashboard(mut self, dashboard: bool) -> Self {
+        self.dashboard = dashboard;
+        self
+    }
+
+    /// Append a line for every request made to the dev server to the file at `path`, in the given format.
+    ///
+    /// This gives long running dev servers, e.g. on a shared machine, a record of requests that outlives the terminal scrollback.
+    /// Can also be set via `--access-log` and `--access-log-format` on the command line, which take priority.
+    pub fn access_log<P: AsRef<Path>>(mut self, path: P, format: AccessLogFormat) -> Self {
+        self.access_log = Some((path.as_ref().to_path_buf(), format));
+        self
+    }
+
+    /// Stop building or serving when `token` is cancelled, see [`CancelToken`].
+    ///
+    /// A build in progress fails with [`Error::Cancelled`], while a running dev server shuts down as it does on ctrl-c.
+    pub fn cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Write the [`BuildReport`] of every build to the file at `path` as JSON, e.g. to track build times and wasm size across commits.
+    ///
+    /// Can also be set via `--report` on the command line, which takes priority.
+    pub fn report<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.report = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Append the [`BuildReport`] of every build to `target/wasm-history.jsonl` in the workspace root as a line of JSON,
+    /// along with the time and the git commit and branch it was built from, to follow the wasm size and build time over the lifetime of a branch.
+    ///
+    /// `cargo run-wasm --stats [NAME]` summarizes the recorded builds, see [`RunWasm::print_stats`].
+    ///
+    /// Can also be enabled via `--history` on the command line.
+    pub fn history(mut self, history: bool) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Write the generated files to `dir`, relative to the current directory, instead of `target/wasm-examples/<name>` in the workspace root.
+    ///
+    /// This is meant for deploying the output of [`RunWasm::build_only`] or [`RunWasm::build`], e.g. from CI.
+    /// Files already in `dir` are left alone, so use a directory that only holds the output.
+    /// Cannot be combined with [`RunWasm::serve_all`], which serves the output of every package from `target/wasm-examples`.
+    ///
+    /// Can also be set via `--out-dir` on the command line, which takes priority.
+    pub fn out_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.out_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// When enabled a release build is written to `target/wasm-dist/<name>`, or the [`RunWasm::out_dir`], ready to upload to a static host
+    /// such as GitHub Pages or S3, instead of running the dev server.
+    ///
+    /// The js and wasm have a hash of their contents in their name so that they can be cached forever,
+    /// and a brotli and gzip compressed copy of every file worth compressing is written next to it, for hosts that serve precompressed files.
+    /// Every url in the generated page is relative, so the folder can be served from any path.
+    /// The hashing happens after the [`RunWasm::after_build`] hooks, which see the files under their usual names.
+    /// Compressing requires the `brotli` and `flate2` features, which the default `server` feature enables.
+    ///
+    /// Can also be enabled via `--dist` on the command line.
+    pub fn dist(mut self, dist: bool) -> Self {
+        self.dist = dist;
+        self
+    }
+
+    /// Write a config for `host` alongside the [`RunWasm::dist`] output, which this implies,
+    /// that makes it serve the files with the same headers as the dev server, so that what works locally also works deployed.
+    ///
+    /// This covers the headers added via [`RunWasm::header`] and the like, e.g. for cross origin isolation,
+    /// the content type of wasm files and of [`RunWasm::mime_type`] extensions, and the `Cache-Control` header.
+    /// The nginx config also serves the precompressed files, Netlify and Vercel compress responses themselves.
+    /// The config lists each file, so it is written after the [`RunWasm::after_build`] hooks.
+    ///
+    /// Can be called more than once for several hosts, those given via `--deploy-config <HOST>` on the command line are added to these.
+    pub fn deploy_config(mut self, host: DeployConfig) -> Self {
+        self.deploy_configs.push(host);
+        self
+    }
+
+    /// Pack the [`RunWasm::dist`] output, which this implies, into an archive at `path` with the index.html at its root,
+    /// as itch.io and other hosts taking HTML5 uploads expect. Relative to the current directory.
+    ///
+    /// The format is picked from the extension, `.zip`, `.tar.gz`, `.tgz` or `.tar`.
+    /// The precompressed copies of the files are left out, since hosts that take an archive serve the files themselves.
+    /// Without the `flate2` feature, which the default `server` feature enables, zip files are stored uncompressed and `.tar.gz` is not supported.
+    ///
+    /// Can also be set via `--archive <PATH>` on the command line, which takes priority.
+    pub fn archive<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.archive = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Prepare the [`RunWasm::dist`] output, which this implies, for uploading to itch.io as an HTML game,
+    /// packed into a zip at the [`RunWasm::archive`] path, or else next to the output directory, e.g. `target/wasm-dist/<name>.zip`.
+    ///
+    /// itch.io runs the game in an iframe on its page, so a script is added to the index.html that focuses the game on load and when clicked,
+    /// and stops the arrow keys and space from scrolling the page around it.
+    /// The wasm is downloaded before compiling it, as with [`RunWasm::streaming`], so it loads whatever the `Content-Type` itch.io serves it with.
+    /// Since itch.io cant be told which headers to send, the steps to set up the project, including the settings that replace the headers
+    /// the dev server sends, e.g. for cross origin isolation, are written next to the zip as `<name>.itch.txt`.
+    ///
+    /// Can also be enabled via `--publish-itch` on the command line.
+    pub fn publish_itch(mut self, publish_itch: bool) -> Self {
+        self.publish_itch = publish_itch;
+        self
+    }
+
+    /// Lay the [`RunWasm::dist`] output, which this implies, out the way `trunk build --release` does,
+    /// so that deploy scripts and hosting written for Trunk keep working when switching the build step to cargo-run-wasm.
+    ///
+    /// The output goes to `dist` next to the package, or the [`RunWasm::out_dir`], with the js and wasm named `<name>-<hash>.js` and `<name>-<hash>_bg.wasm`.
+    /// The index.html refers to them by absolute urls under the `public_url`, `/` by default, with preload links for both,
+    /// and like Trunk's it sets `window.wasmBindings` and dispatches a `TrunkApplicationStarted` event once the wasm has started.
+    /// The `dist` and `public_url` of the `[build]` table of an existing Trunk.toml, or the `TRUNK_BUILD_DIST` and `TRUNK_BUILD_PUBLIC_URL` environment variables, are used.
+    /// As Trunk doesnt write precompressed copies of the files, neither is done here.
+    ///
+    /// Can also be enabled via `--trunk-dist` on the command line.
+    pub fn trunk_dist(mut self, trunk_dist: bool) -> Self {
+        self.trunk_dist = trunk_dist;
+        self
+    }
+
+    /// Write a manifest of the hash of every file in the [`RunWasm::dist`] output, which this implies, to `path`,
+    /// relative to the current directory, for [`RunWasm::check_dist`] to compare a later build against.
+    ///
+    /// It is in the format of `sha1sum`, so it can also be checked with `sha1sum -c` from the output directory.
+    ///
+    /// Can also be set via `--dist-manifest <PATH>` on the command line, which takes priority.
+    pub fn dist_manifest<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.dist_manifest = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Rebuild the [`RunWasm::dist`] output, which this implies, and compare it against the manifest written by [`RunWasm::dist_manifest`] at `path`,
+    /// failing with [`Error::DistMismatch`] when any file was added, removed or changed.
+    ///
+    /// For CI to catch a committed build, e.g. on a GitHub Pages branch, that is out of date with the code it was built from.
+    /// The build must be reproducible for this to pass, so it should be run with the same toolchain the committed build was made with.
+    ///
+    /// Can also be set via `--check-dist <PATH>` on the command line, which takes priority.
+    pub fn check_dist<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.check_dist = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Serve the [`RunWasm::dist`] output, which this implies, or the [`RunWasm::gallery_dist`] site on a free port once it is built,
+    /// and request every page, script, stylesheet and asset it links to, failing with [`Error::BrokenLinks`] if any of them is not found.
+    ///
+    /// This catches a broken relative path or a missing asset in CI, before it is deployed.
+    /// The urls are found in html attributes such as `href` and `src`, the imports and fetches of scripts and the `url()`s of stylesheets,
+    /// so urls that are only built up at runtime are not checked. Links to other sites are not followed.
+    /// Requires the `server` feature, which is enabled by default.
+    ///
+    /// Can also be enabled via `--check-links` on the command line.
+    pub fn check_links(mut self, check_links: bool) -> Self {
+        self.check_links = check_links;
+        self
+    }
+
+    /// Write a release build laid out like the `pkg` directory of wasm-pack to `target/wasm-pkg/<name>`, or the [`RunWasm::out_dir`],
+    /// instead of running the dev server, for publishing to npm or tooling that expects the output of wasm-pack.
+    ///
+    /// Alongside the js and wasm it contains their TypeScript declarations, a `package.json` filled in from the Cargo.toml of the package,
+    /// and its readme and license files, but no index.html.
+    /// The package.json lists every file in the directory, so anything the [`RunWasm::after_build`] hooks add is published as well.
+    ///
+    /// Can also be set via `--pkg <TARGET>` on the command line, which takes priority.
+    pub fn pkg(mut self, target: PkgTarget) -> Self {
+        self.pkg = Some(target);
+        self
+    }
+
+    /// Build each of the comma separated `profiles`, `debug` and `release`, into sibling directories of `target/wasm-profiles/<name>`,
+    /// or the [`RunWasm::out_dir`], and serve them under `/debug/` and `/release/`,
+    /// with a page at `/` showing them side by side along with their sizes and build times.
+    /// For switching between them quickly when something only breaks with optimizations.
+    ///
+    /// Invalid profiles are reported when running.
+    /// Can also be set via `--profiles <PROFILES>` on the command line, which takes priority.
+    pub fn profiles(mut self, profiles: &str) -> Self {
+        self.profiles = Some(profiles.to_string());
+        self
+    }
+
+    /// When enabled, instead of serving until stopped, the page is loaded in a headless browser via WebDriver,
+    /// and [`RunWasm::try_run`] fails with [`Error::TestFailed`] unless it passes.
+    ///
+    /// The page fails on an uncaught error, which includes a panic, or when the summary logged by wasm-bindgen-test reports failures.
+    /// It passes once the assertion given to [`RunWasm::test_assertion`] is true, or without one when the wasm loads and runs for a second without errors.
+    /// The pages console output is printed as it runs, and the dev server listens on any free port unless one is given.
+    ///
+    /// The WebDriver is the one given to [`RunWasm::webdriver`], or else chromedriver, geckodriver or msedgedriver,
+    /// from the `CHROMEDRIVER`, `GECKODRIVER` or `MSEDGEDRIVER` environment variables or the PATH, which must be installed separately.
+    /// Requires the `server` feature.
+    ///
+    /// Can also be enabled via `--test-headless` on the command line.
+    pub fn test_headless(mut self, test_headless: bool) -> Self {
+        self.test_headless = test_headless;
+        self
+    }
+
+    /// When enabled the page is smoke tested, to gate pull requests on whether the demo at least boots.
+    ///
+    /// This is [`RunWasm::test_headless`] on a port picked by the OS, even if another was configured, that ignores [`RunWasm::test_assertion`].
+    /// So it passes once the wasm has loaded and run for a second, and fails on an uncaught error or panic or when the wasm does not load in time.
+    ///
+    /// Can also be enabled via `--ci-smoke` on the command line.
+    pub fn ci_smoke(mut self, ci_smoke: bool) -> Self {
+        self.ci_smoke = ci_smoke;
+        self
+    }
+
+    /// A JS expression, e.g. `document.title === "done"`, that passes the test run by [`RunWasm::test_headless`] once it is true.
+    ///
+    /// It is evaluated in the page repeatedly until it is true or the test times out.
+    /// Can also be set via `--test-assert` on the command line, which takes priority.
+    pub fn test_assertion(mut self, assertion: &str) -> Self {
+        self.test_assertion = Some(assertion.to_string());
+        self
+    }
+
+    /// The WebDriver for [`RunWasm::test_headless`], a path to a driver executable or the http url of a driver that is already running.
+    ///
+    /// Can also be set via `--webdriver` on the command line, which takes priority.
+    pub fn webdriver(mut self, webdriver: &str) -> Self {
+        self.webdriver = Some(webdriver.to_string());
+        self
+    }
+
+    /// How long [`RunWasm::test_headless`] waits for the page to pass before failing it, 60 seconds by default.
+    ///
+    /// Can also be set via `--test-timeout` on the command line, which takes priority.
+    pub fn test_timeout(mut self, timeout: Duration) -> Self {
+        self.test_timeout = Some(timeout);
+        self
+    }
+
+    /// When enabled the generated files are deleted when the dev server is stopped with ctrl-c.
+    ///
+    /// Can also be enabled via `--clean-on-exit` on the command line.
+    pub fn clean_on_exit(mut self, clean_on_exit: bool) -> Self {
+        self.clean_on_exit = clean_on_exit;
+        self
+    }
+
+    /// Allow the browser feature `feature` via a `Permissions-Policy` header, e.g. `gamepad`, `clipboard-read`,
+    /// `clipboard-write`, `xr-spatial-tracking` or `fullscreen`.
+    ///
+    /// Some features are disabled by default in cross origin iframes, this allows them for every origin so that embedding the page can be tested.
+    /// The embedding iframe must also allow the feature via its `allow` attribute.
+    /// Can also be set via `--allow-feature` on the command line, which takes priority.
+    pub fn allow_feature(mut self, feature: &str) -> Self {
+        let feature =
+            server::parse_permission_feature(feature).unwrap_or_else(|err| panic!("{}", err));
+        self.permissions.push((feature, true));
+        self
+    }
+
+    /// Deny the browser feature `feature` via a `Permissions-Policy` header, to test how the page handles it being unavailable.
+    ///
+    /// Can also be set via `--deny-feature` on the command line, which takes priority.
+    pub fn deny_feature(mut self, feature: &str) -> Self {
+        let feature =
+            server::parse_permission_feature(feature).unwrap_or_else(|err| panic!("{}", err));
+        self.permissions.push((feature, false));
+        self
+    }
+
+    fn add_header(mut self, path_glob: Option<String>, name: &str, value: &str) -> Self {
+        if !server::is_valid_header_name(name) {
+            panic!("{:?} is not a valid header name", name);
+        }
+        if value.contains(&['\r', '\n'][..]) {
+            panic!("The value of the header {:?} contains a newline", name);
+        }
+        self.headers.push(ExtraHeader {
+            path_glob,
+            name: name.to_string(),
+            value: value.to_string(),
+        });
+        self
+    }
+
+    /// Runs cargo-run-wasm as described in [`run_wasm_with_css`].
+    ///
+    /// The process args are only read when no package or example was given via [`RunWasm::package`] or [`RunWasm::example`].
+    /// Errors are printed and the process exits with [`Error::exit_code`], use [`RunWasm::try_run`] instead to handle them.
+    pub fn run(self) {
+        // Checked up front since try_run consumes self, and the args may be what failed.
+        let json = self.message_format == MessageFormat::Json
+            || self.target.is_none()
+                && matches!(
+                    Arguments::from_vec(process_args()).opt_value_from_str::<_, String>("--message-format"),
+                    Ok(Some(format)) if format == "json"
+                );
+        let annotations_arg = if self.target.is_none() {
+            Arguments::from_vec(process_args())
+                .opt_value_from_str::<_, String>("--annotations")
+                .ok()
+                .flatten()
+        } else {
+            None
+        };
+        let annotate = match (annotations_arg.as_deref(), self.annotations) {
+            (Some(format), _) => format == "github",
+            (None, Some(annotations)) => annotations == Annotations::Github,
+            (None, None) => !json && annotations::detect(),
+        };
+        let help = self.help.clone();
+        let err = match self.try_run() {
+            Ok(()) => return,
+            Err(err) => err,
+        };
+        match &err {
+            _ if json => println!(
+                "{}",
+                json!({ "reason": "error", "message": err.to_string() })
+            ),
+            Error::InvalidArgs(err) => {
+                println!("{}\n\n{}", color::paint(color::RED, err), help::help(&help))
+            }
+            // We dont need to print anything because cargo will have already displayed an appropriate error.
+            Error::BuildFailed { .. } => {}
+            err => println!("{}", color::paint(color::RED, &err.to_string())),
+        }
+        // The diagnostics of a failed build were already annotated as cargo reported them.
+        if annotate && !matches!(err, Error::BuildFailed { .. } | Error::Cancelled) {
+            println!("{}", annotations::error(&err.to_string()));
+        }
+        std::process::exit(err.exit_code());
+    }
+
+    /// Like [`RunWasm::run`], but returns an error instead of printing it, for tools that need to react to failures.
+    pub fn try_run(self) -> Result<(), Error> {
+        if self.print_info()? {
+            return Ok(());
+        }
+        if self.gallery_dist_requested() {
+            return match self.build_gallery_dist()? {
+                Some((output, args)) => self.run_link_check(output, args, true),
+                None => Ok(()),
+            };
+        }
+        let mut args = self.args()?;
+        let output = if args.profiles.is_empty() {
+            self.build_with(&args)?
+        } else {
+            self.build_profiles(&mut args)?
+        };
+        if args.check_links {
+            self.run_link_check(output, args, false)
+        } else if args.build_only {
+            Ok(())
+        } else if args.test_headless {
+            self.run_headless_test(output, args)
+        } else {
+            self.serve_with(output, args)
+        }
+    }
+
+    /// Like [`RunWasm::try_run`], but awaits cargo and ctrl-c instead of blocking on them, for use from an async runtime.
+    ///
+    /// Requires the `tokio` feature.
+    /// The returned future is not `Send`, since the hooks given to [`RunWasm`] need not be, so await it directly rather than spawning it.
+    #[cfg(feature = "tokio")]
+    pub async fn run_async(self) -> Result<(), Error> {
+        if self.print_info()? {
+            return Ok(());
+        }
+        if self.gallery_dist_requested() {
+            // Blocks the current task while building, which a one off build of the whole site can afford.
+            return match self.build_gallery_dist()? {
+                Some((output, args)) => self.run_link_check(output, args, true),
+                None => Ok(()),
+            };
+        }
+        let mut args = self.args()?;
+        let output = if args.profiles.is_empty() {
+            self.build_async_with(&args).await?
+        } else {
+            // Blocks the current task while building, as with the gallery.
+            self.build_profiles(&mut args)?
+        };
+        if args.check_links {
+            self.run_link_check(output, args, false)
+        } else if args.build_only {
+            Ok(())
+        } else if args.test_headless {
+            // Blocks the current task while the browser runs, which only tests are expected to be doing.
+            self.run_headless_test(output, args)
+        } else {
+            self.serve_async_with(output, args).await
+        }
+    }
+
+    /// Handles the args that print something instead of running, e.g. `--help`, returning whether one was given.
+    fn print_info(&self) -> Result<bool, Error> {
+        if self.target.is_none() {
+            let mut args = Arguments::from_vec(process_args());
+            if args.contains(["-h", "--help"]) {
+                print!("{}", help::help(&self.help));
+                return Ok(true);
+            }
+            if args.contains(["-V", "--version"]) {
+                println!("{}", version());
+                return Ok(true);
+            }
+            // Hidden since they are only useful when setting up completions, which the readme explains.
+            if let Some(shell) = args
+                .opt_value_from_str::<_, String>("--completions")
+                .map_err(|err| Error::InvalidArgs(err.to_string()))?
+            {
+                print!(
+                    "{}",
+                    completions::script(&shell).map_err(Error::InvalidArgs)?
+                );
+                return Ok(true);
+            }
+            if args.contains("--clean") {
+                // Read by resolve_project_root, but it must not be mistaken for NAME.
+                let _: Option<PathBuf> = args
+                    .opt_value_from_str("--project-root")
+                    .map_err(|err| Error::InvalidArgs(err.to_string()))?;
+                let name: Option<String> = args
+                    .opt_free_from_str()
+                    .map_err(|err| Error::InvalidArgs(err.to_string()))?;
+                clean(&self.resolve_project_root()?, name.as_deref())?;
+                return Ok(true);
+            }
+            if args.contains("--stats") {
+                let _: Option<PathBuf> = args
+                    .opt_value_from_str("--project-root")
+                    .map_err(|err| Error::InvalidArgs(err.to_string()))?;
+                let name: Option<String> = args
+                    .opt_free_from_str()
+                    .map_err(|err| Error::InvalidArgs(err.to_string()))?;
+                history::print_stats(&self.resolve_project_root()?, name.as_deref())?;
+                return Ok(true);
+            }
+            if args.contains("--list") {
+                let json = self.message_format == MessageFormat::Json
+                    || matches!(
+                        args.opt_value_from_str::<_, String>("--message-format"),
+                        Ok(Some(format)) if format == "json"
+                    );
+                let entries = gallery::workspace_entries(&cargo(), &self.resolve_project_root()?)
+                    .map_err(Error::InvalidArgs)?;
+                gallery::print_list(&entries, json);
+                return Ok(true);
+            }
+            if args.contains(completions::NAMES_OPTION) {
+                let entries = gallery::workspace_entries(&cargo(), &self.resolve_project_root()?)
+                    .map_err(Error::InvalidArgs)?;
+                for entry in entries {
+                    println!("{}", entry.name);
+                }
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn gallery_dist_requested(&self) -> bool {
+        self.gallery_dist
+            || self.target.is_none()
+                && Arguments::from_vec(process_args()).contains("--gallery-dist")
+    }
+
+    /// Builds every package and example into the site directory and writes the page linking them, for [`RunWasm::gallery_dist`].
+    ///
+    /// Returns the output and args of the last build when [`RunWasm::check_links`] is enabled, which serving the site starts from.
+    fn build_gallery_dist(&self) -> Result<Option<(BuildOutput, Args)>, Error> {
+        let project_root = self.resolve_project_root()?;
+        let cargo = cargo();
+        let entries =
+            gallery::workspace_entries(&cargo, &project_root).map_err(Error::InvalidArgs)?;
+        if entries.is_empty() {
+            return Err(Error::InvalidArgs(
+                "No packages or examples found in the workspace to build the gallery from"
+                    .to_string(),
+            ));
+        }
+        let mut out_dir = None;
+        if self.target.is_none() {
+            out_dir = Arguments::from_vec(process_args())
+                .opt_value_from_str::<_, PathBuf>("--out-dir")
+                .map_err(|err| Error::InvalidArgs(err.to_string()))?;
+        }
+        let site_dir = match out_dir.or_else(|| self.out_dir.clone()) {
+            Some(dir) => env::current_dir()
+                .map_err(Error::io("Failed to get the current directory"))?
+                .join(dir),
+            None => project_root.join("target/wasm-gallery"),
+        };
+        // Packages that were since removed would otherwise be deployed too.
+        if site_dir.exists() {
+            std::fs::remove_dir_all(&site_dir).map_err(Error::io(format!(
+                "Failed to delete {}",
+                site_dir.display()
+            )))?;
+        }
+        let mut last = None;
+        for entry in &entries {
+            let args = self.resolve_args(
+                project_root.clone(),
+                Some((&entry.name, entry.example, &site_dir)),
+            )?;
+            let output = self.build_with(&args)?;
+            last = Some((output, args));
+        }
+        let (_, args) = last.as_ref().unwrap();
+        let check_links = args.check_links;
+        let human = args.message_format.unwrap_or(self.message_format) == MessageFormat::Human;
+        gallery::write(&cargo, &project_root, &site_dir)?;
+        if human {
+            println!(
+                "{} the gallery of {} packages and examples to {}",
+                color::paint(color::GREEN, "Wrote"),
+                entries.len(),
+                site_dir.display()
+            );
+        }
+        Ok(if check_links { last } else { None })
+    }
+
+    /// Builds each profile into its own directory and writes the page comparing them, for [`RunWasm::profiles`].
+    ///
+    /// Returns the output of the last profile, the directory above which is then served.
+    /// The args are left as they were for the last profile.
+    fn build_profiles(&self, args: &mut Args) -> Result<BuildOutput, Error> {
+        let dir = match &args.out_dir {
+            Some(dir) => env::current_dir()
+                .map_err(Error::io("Failed to get the current directory"))?
+                .join(dir),
+            None => args
+                .project_root
+                .join("target/wasm-profiles")
+                .join(&args.name),
+        };
+        let mut outputs = vec![];
+        for profile in args.profiles.clone() {
+            args.release = profile == "release";
+            args.out_dir = Some(dir.join(profile));
+            outputs.push(self.build_with(args)?);
+        }
+        profiles::write(&dir, &args.name, &outputs)?;
+        Ok(outputs.pop().unwrap())
+    }
+
+    /// The workspace root from `--project-root`, [`RunWasm::project_root`] or else [`project_root`].
+    fn resolve_project_root(&self) -> Result<PathBuf, Error> {
+        let mut from_args = None;
+        if self.target.is_none() {
+            from_args = Arguments::from_vec(process_args())
+                .opt_value_from_str::<_, PathBuf>("--project-root")
+                .map_err(|err| Error::InvalidArgs(err.to_string()))?;
+        }
+        match from_args.or_else(|| self.project_root.clone()) {
+            Some(dir) => {
+                let dir = env::current_dir()
+                    .map_err(Error::io("Failed to get the current directory"))?
+                    .join(dir);
+                if !dir.join("Cargo.toml").is_file() {
+                    return Err(Error::InvalidArgs(format!(
+                        "The project root {} does not contain a Cargo.toml",
+                        dir.display()
+                    )));
+                }
+                Ok(dir)
+            }
+            None => project_root(),
+        }
+    }
+
+    /// Lets the user pick what to run when no NAME was given, if they are running us from a terminal.
+    fn pick_target(&self, project_root: &Path) -> Result<Option<(String, bool)>, String> {
+        if !picker::is_interactive() {
+            return Ok(None);
+        }
+        let entries = gallery::workspace_entries(&cargo(), project_root)?;
+        picker::pick(&entries)
+    }
+
+    /// Resolves the args of a single build, see [`RunWasm::resolve_args`].
+    fn args(&self) -> Result<Args, Error> {
+        self.resolve_args(self.resolve_project_root()?, None)
+    }
+
+    /// Reads the args, from the process args unless a package or example was configured, and merges in the config files and build options.
+    ///
+    /// Done once before building, the stages after it are given the result.
+    /// `gallery_entry` is the package or example [`RunWasm::gallery_dist`] is building and the directory of the site.
+    fn resolve_args(
+        &self,
+        project_root: PathBuf,
+        gallery_entry: Option<(&str, bool, &Path)>,
+    ) -> Result<Args, Error> {
+        let env = config::load_env().map_err(Error::InvalidArgs)?;
+        let mut config = env.over(config::load(&project_root).map_err(Error::InvalidArgs)?);
+        let mut args = match &self.target {
+            // Configured programmatically, so the process args are left to the embedding tool.
+            Some((name, example)) => Args {
+                name: name.clone(),
+                example: *example,
+                ..Args::default()
+            },
+            None => {
+                let default_target = match gallery_entry {
+                    Some((name, example, _)) => Some((name.to_string(), example)),
+                    None => config.target.take(),
+                };
+                Args::from_env(
+                    &self.extra_flags,
+                    &self.extra_options,
+                    || match default_target {
+                        Some(target) => Ok(Some(target)),
+                        None => self.pick_target(&project_root),
+                    },
+                )
+                .map_err(Error::InvalidArgs)?
+            }
+        };
+        if let Some((name, example, _)) = gallery_entry {
+            args.name = name.to_string();
+            args.example = example;
+        }
+        let package = config::load_package(&cargo(), &project_root, &args.name, args.example)
+            .map_err(Error::InvalidArgs)?;
+        let mut headers = config.headers;
+        headers.extend(args.headers);
+        let mut assets_dirs = config.assets_dirs;
+        assets_dirs.extend(package.assets_dirs);
+        assets_dirs.extend(args.assets_dirs);
+        let mut features: Vec<String> = args
+            .features
+            .or_else(|| self.features.clone())
+            .or(config.features)
+            .into_iter()
+            .collect();
+        features.extend(package.features);
+        let out_dir = match gallery_entry {
+            Some((name, _, site_dir)) => Some(site_dir.join(name)),
+            None => args.out_dir.or_else(|| self.out_dir.clone()),
+        };
+        let serve_all = args.serve_all || args.gallery || self.serve_all || self.gallery;
+        // The site built by --gallery-dist is itself served like a gallery for --check-links.
+        if out_dir.is_some() && serve_all && gallery_entry.is_none() {
+            return Err(Error::InvalidArgs(
+                "--out-dir cannot be combined with --serve-all or --gallery, which serve every package from target/wasm-examples".to_string(),
+            ));
+        }
+        let mut deploy_configs = self.deploy_configs.clone();
+        deploy_configs.extend(args.deploy_configs);
+        let archive = args.archive.or_else(|| self.archive.clone());
+        let publish_itch = args.publish_itch || self.publish_itch;
+        if let Some(archive) = &archive {
+            let format = archive::Format::from_path(archive).map_err(Error::InvalidArgs)?;
+            if publish_itch && format != archive::Format::Zip {
+                return Err(Error::InvalidArgs(
+                    "--publish-itch expects an --archive ending in .zip, the only archive itch.io takes for HTML games".to_string(),
+                ));
+            }
+        }
+        let trunk_dist = args.trunk_dist || self.trunk_dist;
+        if trunk_dist && (publish_itch || gallery_entry.is_some()) {
+            return Err(Error::InvalidArgs(
+                "--trunk-dist cannot be combined with --publish-itch or --gallery-dist, which need the urls of the page to be relative".to_string(),
+            ));
+        }
+        if publish_itch && gallery_entry.is_some() {
+            return Err(Error::InvalidArgs(
+                "--publish-itch cannot be combined with --gallery-dist, upload each game on its own instead".to_string(),
+            ));
+        }
+        let dist_manifest = args.dist_manifest.or_else(|| self.dist_manifest.clone());
+        let check_dist = args.check_dist.or_else(|| self.check_dist.clone());
+        if dist_manifest.is_some() && check_dist.is_some() {
+            return Err(Error::InvalidArgs(
+                "--check-dist cannot be combined with --dist-manifest, it compares against the manifest instead of writing it".to_string(),
+            ));
+        }
+        let check_links = args.check_links || self.check_links;
+        let annotations = args.annotations.or(self.annotations);
+        let history = args.history || self.history;
+        let dist = args.dist
+            || self.dist
+            || check_links
+            || !deploy_configs.is_empty()
+            || archive.is_some()
+            || publish_itch
+            || trunk_dist
+            || dist_manifest.is_some()
+            || check_dist.is_some()
+            || gallery_entry.is_some();
+        let pkg = args.pkg.or(self.pkg);
+        let no_bindgen = args.no_bindgen || self.no_bindgen;
+        let coverage = args.coverage || self.coverage;
+        if coverage && (dist || pkg.is_some()) {
+            return Err(Error::InvalidArgs(
+                "--coverage cannot be combined with --dist or --pkg, the coverage is collected by the dev server".to_string(),
+            ));
+        }
+        if no_bindgen && (dist || pkg.is_some()) {
+            return Err(Error::InvalidArgs(
+                "--no-bindgen cannot be combined with --dist or --pkg, which expect the js generated by wasm-bindgen".to_string(),
+            ));
+        }
+        if dist && pkg.is_some() {
+            return Err(Error::InvalidArgs(
+                "--dist cannot be combined with --pkg, which keeps the file names wasm-bindgen generates".to_string(),
+            ));
+        }
+        let ci_smoke = args.ci_smoke || self.ci_smoke;
+        let test_headless = args.test_headless || self.test_headless || ci_smoke;
+        let profiles = match &self.profiles {
+            Some(list) if args.profiles.is_empty() => {
+                profiles::parse(list).map_err(Error::InvalidArgs)?
+            }
+            _ => args.profiles,
+        };
+        if !profiles.is_empty() {
+            if dist || pkg.is_some() {
+                return Err(Error::InvalidArgs(
+                    "--profiles cannot be combined with --dist or --pkg, which always build in release mode".to_string(),
+                ));
+            }
+            if serve_all || test_headless {
+                return Err(Error::InvalidArgs(
+                    "--profiles cannot be combined with --serve-all, --gallery or --test-headless, which expect a single build".to_string(),
+                ));
+            }
+        }
+        Ok(Args {
+            release: args.release
+                || dist
+                || pkg.is_some()
+                || self.release.or(config.release).unwrap_or(false),
+            features: if features.is_empty() {
+                None
+            } else {
+                Some(features.join(","))
+            },
+            build_only: args.build_only || self.build_only || dist || pkg.is_some(),
+            dist,
+            deploy_configs,
+            archive,
+            publish_itch,
+            trunk_dist,
+            dist_manifest,
+            check_dist,
+            pkg,
+            profiles,
+            no_bindgen,
+            coverage,
+            check_links,
+            annotations,
+            history,
+            host: args.host.or_else(|| self.host.clone()).or(config.host),
+            // Smoke tests run alongside whatever else the CI machine is running.
+            port: if ci_smoke {
+                Some("0".to_string())
+            } else {
+                args.port
+                    .or_else(|| self.port.map(|port| port.to_string()))
+                    .or(config.port)
+            },
+            public_host: args
+                .public_host
+                .or_else(|| self.public_host.clone())
+                .or(config.public_host),
+            // A headless test is run without a user watching, even if the config file is set up for one.
+            open: !test_headless && (args.open || self.open.or(config.open).unwrap_or(false)),
+            browser: if test_headless {
+                None
+            } else {
+                args.browser
+                    .or_else(|| self.browser.clone())
+                    .or(config.browser)
+            },
+            no_qr: test_headless || args.no_qr || self.no_qr.or(config.no_qr).unwrap_or(false),
+            notify: !test_headless
+                && (args.notify || self.notify.or(config.notify).unwrap_or(false)),
+            css: package.css.or(config.css),
+            canvas_id: package.canvas_id,
+            out_dir,
+            test_headless,
+            ci_smoke,
+            test_assertion: if ci_smoke {
+                None
+            } else {
+                args.test_assertion.or_else(|| self.test_assertion.clone())
+            },
+            webdriver: args.webdriver.or_else(|| self.webdriver.clone()),
+            test_timeout: args.test_timeout.or(self.test_timeout),
+            workspace_manifest: package.workspace_manifest,
+            package_target: package.target,
+            package_metadata: package.metadata,
+            project_root,
+            headers,
+            assets_dirs,
+            ..args
+        })
+    }
+
+    /// Deletes the files generated for the package or example given via [`RunWasm::package`] or [`RunWasm::example`],
+    /// or when neither was given everything cargo-run-wasm has written to `target`, including the cargo target directory used for wasm builds.
+    ///
+    /// Can also be done via `--clean [NAME]` on the command line.
+    pub fn clean(&self) -> Result<(), Error> {
+        let name = self.target.as_ref().map(|(name, _)| name.as_str());
+        clean(&self.resolve_project_root()?, name)
+    }
+
+    /// Prints the builds recorded by [`RunWasm::history`] of the package or example given via [`RunWasm::package`] or [`RunWasm::example`],
+    /// or of every one when neither was given, with how the wasm size and build time changed since the first.
+    ///
+    /// Can also be done via `--stats [NAME]` on the command line.
+    pub fn print_stats(&self) -> Result<(), Error> {
+        let name = self.target.as_ref().map(|(name, _)| name.as_str());
+        history::print_stats(&self.resolve_project_root()?, name)
+    }
+
+    /// Builds the wasm and generates the files to serve, without running the dev server.
+    ///
+    /// The output can then be served by [`RunWasm::serve`], or by your own server from [`BuildOutput::dir`].
+    /// This ignores [`RunWasm::build_only`] and `--build-only`.
+    pub fn build(&self) -> Result<BuildOutput, Error> {
+        let args = self.args()?;
+        let mut output = self.build_with(&args)?;
+        output.args = Some(args);
+        Ok(output)
+    }
+
+    fn build_with(&self, args: &Args) -> Result<BuildOutput, Error> {
+        let mut build = self.prepare_build(args)?;
+        let default_steps;
+        let steps = match &self.steps {
+            Some(steps) => steps,
+            None => {
+                default_steps = RunWasm::default_steps();
+                &default_steps
+            }
+        };
+        for step in steps {
+            build.run_step(step.as_ref())?;
+        }
+        self.finish_build(build)
+    }
+
+    /// Like [`RunWasm::build`], but awaits cargo instead of blocking on it, for use from an async runtime.
+    ///
+    /// Requires the `tokio` feature, cargo is run via [`tokio::process`] so the runtime must have IO enabled.
+    /// Only the built in [`CargoBuild`] step is awaited, other steps still run on the current task.
+    #[cfg(feature = "tokio")]
+    pub async fn build_async(&self) -> Result<BuildOutput, Error> {
+        let args = self.args()?;
+        let mut output = self.build_async_with(&args).await?;
+        output.args = Some(args);
+        Ok(output)
+    }
+
+    #[cfg(feature = "tokio")]
+    async fn build_async_with(&self, args: &Args) -> Result<BuildOutput, Error> {
+        let mut build = self.prepare_build(args)?;
+        let default_steps;
+        let steps = match &self.steps {
+            Some(steps) => steps,
+            None => {
+                default_steps = RunWasm::default_steps();
+                &default_steps
+            }
+        };
+        for step in steps {
+            if step.is_cargo_build() {
+                if self.cancel.is_cancelled() {
+                    return Err(build.fail(Error::Cancelled));
+                }
+                let start = Instant::now();
+                let result = build.run_cargo_async().await;
+                result.map_err(|err| build.fail(err))?;
+                build.finished_phase(step.name(), start);
+            } else {
+                build.run_step(step.as_ref())?;
+            }
+        }
+        self.finish_build(build)
+    }
+
+    /// Runs the before build hooks, leaving the steps to be run by the caller.
+    fn prepare_build(&self, args: &Args) -> Result<PendingBuild, Error> {
+        let css = args.css.clone().unwrap_or_else(|| self.css.clone());
+
+        // Checked before building too, rather than only when rendering the index.html, so that it fails fast.
+        validate_css(&css);
+
+        let profile = if args.release { "release" } else { "debug" };
+        let json = args.message_format.unwrap_or(self.message_format) == MessageFormat::Json;
+        let verbosity = if json {
+            Verbosity::Quiet
+        } else {
+            args.verbosity.unwrap_or(self.verbosity)
+        };
+        let color = args.color.unwrap_or(self.color);
+        color::init(if json { ColorChoice::Never } else { color });
+
+        // build wasm example via cargo
+        let cargo = cargo();
+        let project_root = args.project_root.clone();
+        // Instrumenting changes the rustflags, which would otherwise rebuild everything when switching between coverage and normal builds.
+        let target_dir = if args.coverage {
+            "target/wasm-coverage-target"
+        } else {
+            "target/wasm-examples-target"
+        };
+        let annotate = match args.annotations {
+            Some(annotations) => annotations == Annotations::Github,
+            None => !json && annotations::detect(),
+        };
+        let mut cargo_args = vec![
+            "build",
+            "--target",
+            "wasm32-unknown-unknown",
+            // It is common to setup a faster linker such as mold or lld to run for just your native target.
+            // It cant be set for wasm as wasm doesnt support building with these linkers.
+            // This results in a separate rustflags value for native and wasm builds.
+            // Currently rust triggers a full rebuild every time the rustflags value changes.
+            //
+            // Therefore we have this hack where we use a different target dir for wasm builds to avoid constantly triggering full rebuilds.
+            // When this issue is resolved we might be able to remove this hack: https://github.com/rust-lang/cargo/issues/8716
+            "--target-dir",
+            target_dir,
+            // Cargo still renders diagnostics to the terminal as usual, while the json messages on stdout are forwarded as events.
+            // When annotating, the diagnostics are rendered by BuildContext::cargo_message instead, since cargo leaves them out of the json otherwise.
+            "--message-format",
+            if !annotate {
+                "json-render-diagnostics"
+            } else if color::enabled() {
+                "json-diagnostic-rendered-ansi"
+            } else {
+                "json"
+            },
+        ];
+        // Cargo is still run from the project root, so the target directory is shared with the rest of the workspace.
+        let workspace_manifest = args
+            .workspace_manifest
+            .as_ref()
+            .map(|x| x.to_string_lossy());
+        if let Some(manifest) = &workspace_manifest {
+            cargo_args.extend(["--manifest-path", manifest]);
+        }
+        if args.example {
+            cargo_args.extend(["--example", &args.name]);
+        } else {
+            cargo_args.extend(["--package", &args.name]);
+        }
+        match &args.package_target {
+            Some(PackageTarget::Bin(bin)) => cargo_args.extend(["--bin", bin]),
+            Some(PackageTarget::Lib) => cargo_args.push("--lib"),
+            None => {}
+        }
+        if let Some(features) = &args.features {
+            cargo_args.extend(["--features", features]);
+        }
+        if args.release {
+            cargo_args.push("--release");
+        }
+        if verbosity == Verbosity::Quiet {
+            cargo_args.push("--quiet");
+        }
+        // Cargo decides for itself when left on auto, since it prints to stderr which may differ from stdout.
+        match color {
+            ColorChoice::Auto => {}
+            ColorChoice::Always => cargo_args.extend(["--color", "always"]),
+            ColorChoice::Never => cargo_args.extend(["--color", "never"]),
+        }
+        let cargo_args = cargo_args.into_iter().map(|x| x.to_string()).collect();
+        let mut cargo_env = vec![];
+        if args.coverage {
+            // Cargo merges the target rustflags with those in the config files, unless RUSTFLAGS is set, which replaces them all.
+            let name = if env::var_os("RUSTFLAGS").is_some() {
+                "RUSTFLAGS"
+            } else {
+                "CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUSTFLAGS"
+            };
+            let mut rustflags = env::var(name).unwrap_or_default();
+            rustflags
+                .push_str(" -Cinstrument-coverage -Zno-profiler-runtime --cfg run_wasm_coverage");
+            cargo_env.push((name.to_string(), rustflags.trim_start().to_string()));
+        }
+        let runs_bindgen = match &self.steps {
+            Some(steps) => steps.iter().any(|x| x.name() == "bindgen"),
+            None => true,
+        };
+        if runs_bindgen && !args.no_bindgen {
+            let lockfile = match &args.workspace_manifest {
+                Some(manifest) => manifest.with_file_name("Cargo.lock"),
+                None => project_root.join("Cargo.lock"),
+            };
+            if lockfile::depends_on(&lockfile, &args.package_metadata, "wasm-bindgen")
+                == Some(false)
+            {
+                let package = args.package_metadata["name"].as_str().unwrap_or(&args.name);
+                return Err(Error::Bindgen(format!(
+                    "`{}` does not depend on wasm-bindgen, which generates the js that loads the wasm.\n\
+                     Add wasm-bindgen to the [dependencies] of `{}`, or pass --no-bindgen if the wasm is loaded by js of its own",
+                    args.name, package
+                )));
+            }
+        }
+        let events = Arc::new(Events::new(json));
+        events.emit(
+            "build-started",
+            json!({ "name": args.name, "profile": profile }),
+        );
+        let trunk = if args.trunk_dist {
+            let package_dir = args.package_metadata["manifest_path"]
+                .as_str()
+                .and_then(|x| Path::new(x).parent())
+                .unwrap_or(&project_root);
+            Some(trunk::read_config(package_dir, &project_root)?)
+        } else {
+            None
+        };
+        let example_dest = match &args.out_dir {
+            Some(out_dir) => env::current_dir()
+                .map_err(Error::io("Failed to get the current directory"))?
+                .join(out_dir),
+            None if args.dist || args.pkg.is_some() => {
+                let dir = match &trunk {
+                    Some(trunk) => trunk.dist.clone(),
+                    None => project_root
+                        .join(if args.dist {
+                            "target/wasm-dist"
+                        } else {
+                            "target/wasm-pkg"
+                        })
+                        .join(&args.name),
+                };
+                // Left over files from a previous build would otherwise be deployed or published too.
+                if dir.exists() {
+                    std::fs::remove_dir_all(&dir)
+                        .map_err(Error::io(format!("Failed to delete {}", dir.display())))?;
+                }
+                dir
+            }
+            None => project_root.join("target/wasm-examples").join(&args.name),
+        };
+        let wasm_path = wasm_path(args, target_dir, profile);
+        let mut assets_dirs = self.assets_dirs.clone();
+        assets_dirs.extend(args.assets_dirs.iter().cloned());
+        let package_metadata = args.package_metadata.clone();
+        // Merged the same way as when starting the dev server.
+        let mut headers = self.headers.clone();
+        headers.extend(args.headers.iter().cloned());
+        let mut permissions = self.permissions.clone();
+        permissions.extend(args.permissions.iter().cloned());
+        if !permissions.is_empty() {
+            headers.push(ExtraHeader::permissions_policy(&permissions));
+        }
+        let deploy = if args.deploy_configs.is_empty() {
+            None
+        } else {
+            let mut mime_types = self.mime_types.clone();
+            mime_types.extend(args.mime_types.iter().cloned());
+            let hosting = deploy::Hosting {
+                headers: headers.clone(),
+                mime_types,
+                cache_control: args
+                    .cache_control
+                    .clone()
+                    .or_else(|| self.cache_control.clone())
+                    .unwrap_or_else(|| "no-cache".to_string()),
+                immutable: !(self.no_immutable || args.no_immutable),
+            };
+            Some((args.deploy_configs.clone(), hosting))
+        };
+        let archive = match &args.archive {
+            Some(archive) => Some(
+                env::current_dir()
+                    .map_err(Error::io("Failed to get the current directory"))?
+                    .join(archive),
+            ),
+            None if args.publish_itch => {
+                let mut archive = example_dest.clone().into_os_string();
+                archive.push(".zip");
+                Some(PathBuf::from(archive))
+            }
+            None => None,
+        };
+        let mut build = PendingBuild {
+            span: tracing::info_span!("build", name = %args.name, profile),
+            started_at: SystemTime::now(),
+            start: Instant::now(),
+            notify: args.notify,
+            features: args.features.clone(),
+            report_path: args.report.clone().or_else(|| self.report.clone()),
+            history: args.history,
+            // The dist and pkg output is rewritten once the steps are done, so theres nothing to compare against,
+            // and the files custom steps depend on are unknown.
+            fingerprint_path: if args.dist || args.pkg.is_some() || self.steps.is_some() {
+                None
+            } else {
+                Some(fingerprint::path(
+                    &project_root.join(target_dir),
+                    &example_dest,
+                ))
+            },
+            up_to_date: None,
+            dist: args.dist,
+            deploy,
+            archive: archive.clone(),
+            trunk_public_url: trunk.as_ref().map(|x| x.public_url.clone()),
+            itch: if args.publish_itch {
+                // The notes are kept out of the zip, which is all itch.io is given.
+                let notes = archive.as_ref().unwrap().with_extension("itch.txt");
+                Some((notes, headers))
+            } else {
+                None
+            },
+            dist_manifest: match args
+                .dist_manifest
+                .clone()
+                .map(|path| (path, false))
+                .or(args.check_dist.clone().map(|path| (path, true)))
+            {
+                Some((path, check)) => Some((
+                    env::current_dir()
+                        .map_err(Error::io("Failed to get the current directory"))?
+                        .join(path),
+                    check,
+                )),
+                None => None,
+            },
+            pkg: args.pkg.map(|target| (target, package_metadata)),
+            phases: vec![],
+            generated_sizes: vec![None; 3],
+            context: BuildContext {
+                info: BuildInfo {
+                    name: args.name.clone(),
+                    example: args.example,
+                    profile,
+                    project_root,
+                    dir: example_dest,
+                    extra_args: args.extra_args.clone(),
+                },
+                cargo,
+                cargo_args,
+                cargo_env,
+                wasm_path,
+                assets_dirs,
+                output_files: self.output_files.clone(),
+                css,
+                html: self
+                    .html
+                    .clone()
+                    .or_else(|| trunk.as_ref().map(|_| trunk::INDEX_TEMPLATE.to_string())),
+                canvas_id: args.canvas_id.clone(),
+                streaming: !(self.no_streaming || args.no_streaming || args.publish_itch),
+                pkg: args.pkg,
+                bindgen: !args.no_bindgen,
+                annotate,
+                events,
+                cancel: self.cancel.clone(),
+            },
+        };
+        let _build_span = build.span.clone().entered();
+        let start = Instant::now();
+        for hook in &self.before_build {
+            let _span = tracing::info_span!("before_build").entered();
+            hook(&build.context.info).map_err(|err| build.fail(Error::Hook(err)))?;
+        }
+        build.finished_phase("before_build", start);
+        drop(_build_span);
+        let dir = &build.context.info.dir;
+        std::fs::create_dir_all(dir)
+            .map_err(Error::io(format!("Failed to create {}", dir.display())))
+            .map_err(|err| build.fail(err))?;
+        Ok(build)
+    }
+
+    /// Runs the after build hooks once the steps have finished.
+    fn finish_build(&self, mut build: PendingBuild) -> Result<BuildOutput, Error> {
+        let _build_span = build.span.clone().entered();
+        if build.context.cancel.is_cancelled() {
+            return Err(build.fail(Error::Cancelled));
+        }
+        let artifacts = build.artifacts();
+        let report = BuildReport {
+            name: build.context.info.name.clone(),
+            profile: build.context.info.profile,
+            features: build.features.take(),
+            cargo_args: build.context.cargo_args.clone(),
+            phases: std::mem::take(&mut build.phases),
+            artifacts: artifacts
+                .iter()
+                .zip(&build.generated_sizes)
+                .map(|(path, generated_size)| {
+                    let size = report::file_size(path);
+                    ArtifactSize {
+                        path: path.clone(),
+                        // Filesystems with a coarse modification time can make a file look older than the build.
+                        generated_size: generated_size.or(size),
+                        size,
+                    }
+                })
+                .collect(),
+            duration: build.start.elapsed(),
+        };
+        let info = &build.context.info;
+        let example_dest = &info.dir;
+        let mut output = BuildOutput {
+            status: BuildStatus {
+                name: info.name.clone(),
+                profile: info.profile,
+                started_at: build.started_at,
+                duration: build.start.elapsed(),
+                artifacts,
+            },
+            report,
+            dir: example_dest.clone(),
+            extra_args: info.extra_args.clone(),
+            events: build.context.events.clone(),
+            args: None,
+        };
+        let start = Instant::now();
+        for hook in &self.after_build {
+            let _span = tracing::info_span!("after_build").entered();
+            hook(&output).map_err(|err| build.fail(Error::Hook(err)))?;
+        }
+        output.status.duration = build.start.elapsed();
+        output
+            .report
+            .phases
+            .push(("after_build".to_string(), start.elapsed()));
+        // Written after the hooks, which may have changed the generated files.
+        if let Some(path) = &build.fingerprint_path {
+            let fingerprint = fingerprint::compute(&build.context, &output.status.artifacts);
+            fingerprint::write(path, &fingerprint).map_err(|err| build.fail(err))?;
+        }
+        if build.dist {
+            let start = Instant::now();
+            let renamed = match &build.trunk_public_url {
+                Some(public_url) => trunk::rename(&output.dir, &output.status.name, public_url),
+                None => dist::hash_names(&output.dir, &output.status.name)
+                    .and_then(|paths| dist::precompress(&output.dir).map(|()| paths)),
+            };
+            let (wasm_path, js_path) = renamed
+                .and_then(|paths| match &build.itch {
+                    Some(_) => itch::prepare_index(&output.dir).map(|()| paths),
+                    None => Ok(paths),
+                })
+                .and_then(|paths| match &build.deploy {
+                    Some((configs, hosting)) => {
+                        deploy::write_configs(&output.dir, configs, hosting).map(|()| paths)
+                    }
+                    None => Ok(paths),
+                })
+                .and_then(|paths| match &build.archive {
+                    Some(archive) => archive::write(&output.dir, archive).map(|()| paths),
+                    None => Ok(paths),
+                })
+                .and_then(|paths| match (&build.itch, &build.archive) {
+                    (Some((notes, headers)), Some(archive)) => {
+                        itch::write_notes(notes, &output.status.name, archive, headers)
+                            .map(|()| paths)
+                    }
+                    _ => Ok(paths),
+                })
+                .and_then(|paths| match &build.dist_manifest {
+                    Some((manifest, false)) => {
+                        snapshot::write(&output.dir, manifest).map(|()| paths)
+                    }
+                    Some((manifest, true)) => {
+                        snapshot::check(&output.dir, manifest, &output.status.name).map(|()| paths)
+                    }
+                    None => Ok(paths),
+                })
+                .map_err(|err| build.fail(err))?;
+            output.status.artifacts[0] = wasm_path;
+            output.status.artifacts[1] = js_path;
+            for (artifact, path) in output
+                .report
+                .artifacts
+                .iter_mut()
+                .zip(&output.status.artifacts)
+            {
+                artifact.path = path.clone();
+            }
+            output
+                .report
+                .phases
+                .push(("dist".to_string(), start.elapsed()));
+            output.status.duration = build.start.elapsed();
+        }
+        if let Some((target, package)) = &build.pkg {
+            let start = Instant::now();
+            pkg::write_package(&output.dir, &output.status.name, *target, package)
+                .map_err(|err| build.fail(err))?;
+            output
+                .report
+                .phases
+                .push(("pkg".to_string(), start.elapsed()));
+            output.status.duration = build.start.elapsed();
+        }
+        let report = &mut output.report;
+        report.duration = output.status.duration;
+        for artifact in &mut report.artifacts {
+            artifact.size = report::file_size(&artifact.path);
+        }
+        if let Some(path) = &build.report_path {
+            std::fs::write(path, report.to_json())
+                .map_err(Error::io(format!(
+                    "Failed to write the report to {}",
+                    path.display()
+                )))
+                .map_err(|err| build.fail(err))?;
+        }
+        if build.history {
+            history::append(&build.context.info.project_root, report)
+                .map_err(|err| build.fail(err))?;
+        }
+        let status = &output.status;
+        let artifacts: Vec<_> = status
+            .artifacts
+            .iter()
+            .map(|x| x.display().to_string())
+            .collect();
+        tracing::info!(duration = ?status.duration, dir = %output.dir.display(), "build finished");
+        build.context.events.emit(
+            "build-finished",
+            json!({
+                "success": true,
+                "duration_secs": status.duration.as_secs_f64(),
+                "dir": output.dir.display().to_string(),
+                "artifacts": artifacts,
+            }),
+        );
+        if build.notify {
+            notify::notify(
+                "Build finished",
+                &format!("`{}` built in {:.1?}", info.name, status.duration),
+            );
+        }
+        Ok(output)
+    }
+
+    /// Serves the output of [`RunWasm::build`] until stopped with ctrl-c.
+    ///
+    /// The package, command line args and config files resolved by the build are reused, so the package picker is not shown again,
+    /// and the options the build resolved from them, such as the host and port, are those of the [`RunWasm`] that built the output.
+    /// The other dev server options, such as [`RunWasm::assets_dir`], are those of this one.
+    /// Requires the `server` feature, which is enabled by default.
+    #[cfg(feature = "server")]
+    pub fn serve(self, mut output: BuildOutput) -> Result<(), Error> {
+        let args = self.output_args(&mut output)?;
+        self.serve_with(output, args)
+    }
+
+    /// The args resolved by the build of `output`, resolving them again only for an output that was built without them.
+    #[cfg(feature = "server")]
+    fn output_args(&self, output: &mut BuildOutput) -> Result<Args, Error> {
+        match output.args.take() {
+            Some(args) => Ok(args),
+            None => self.args(),
+        }
+    }
+
+    #[cfg(feature = "server")]
+    fn serve_with(self, output: BuildOutput, args: Args) -> Result<(), Error> {
+        let started = self.start(output, args)?;
+        handle_ctrl_c(started.shutdown.clone());
+        started.run();
+        Ok(())
+    }
+
+    /// Without the `server` feature there is no dev server, so [`RunWasm::try_run`] only builds.
+    #[cfg(not(feature = "server"))]
+    fn serve_with(self, _output: BuildOutput, _args: Args) -> Result<(), Error> {
+        Ok(())
+    }
+
+    #[cfg(all(not(feature = "server"), feature = "tokio"))]
+    async fn serve_async_with(self, _output: BuildOutput, _args: Args) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Like [`RunWasm::serve`], but awaits ctrl-c instead of blocking on it, for use from an async runtime.
+    ///
+    /// Requires the `tokio` feature.
+    /// Requests are still handled on the dev servers own threads, as with [`RunWasm::serve_in_background`].
+    #[cfg(all(feature = "server", feature = "tokio"))]
+    pub async fn serve_async(self, mut output: BuildOutput) -> Result<(), Error> {
+        let args = self.output_args(&mut output)?;
+        self.serve_async_with(output, args).await
+    }
+
+    #[cfg(all(feature = "server", feature = "tokio"))]
+    async fn serve_async_with(self, output: BuildOutput, args: Args) -> Result<(), Error> {
+        let cancel = self.cancel.clone();
+        let handle = self.serve_in_background_with(output, args)?;
+        // The dev server has already stopped itself when cancelled.
+        if let Some(result) = cancel::or_cancelled(&cancel, tokio::signal::ctrl_c()).await {
+            result.map_err(Error::io("Failed to listen for ctrl-c"))?;
+        }
+        handle.shutdown();
+        Ok(())
+    }
+
+    /// Like [`RunWasm::serve`], but returns as soon as the dev server is listening, rather than blocking until it is stopped.
+    ///
+    /// Requests are handled on a background thread until [`ServerHandle::shutdown`] is called or the handle is dropped.
+    /// No ctrl-c handler is installed unless [`ServerHandle::wait`] is called, so this is suitable for integration tests.
+    #[cfg(feature = "server")]
+    pub fn serve_in_background(self, mut output: BuildOutput) -> Result<ServerHandle, Error> {
+        let args = self.output_args(&mut output)?;
+        self.serve_in_background_with(output, args)
+    }
+
+    #[cfg(feature = "server")]
+    fn serve_in_background_with(
+        self,
+        output: BuildOutput,
+        args: Args,
+    ) -> Result<ServerHandle, Error> {
+        let Started {
+            listener,
+            root,
+            config,
+            shutdown,
+            addr,
+            url,
+            cleanup,
+        } = self.start(output, args)?;
+        let thread_shutdown = shutdown.clone();
+        let thread = std::thread::Builder::new()
+            .name("run-wasm-server".to_string())
+            .spawn(move || server::serve(listener, &root, config, &thread_shutdown))
+            .map_err(Error::io("Failed to start the dev server thread"))?;
+        Ok(ServerHandle {
+            addr,
+            url,
+            shutdown,
+            thread: Some(thread),
+            cleanup: Some(cleanup),
+        })
+    }
+
+    /// Serves the output in the background while [`headless::run`] tests it, see [`RunWasm::test_headless`].
+    #[cfg(feature = "server")]
+    fn run_headless_test(mut self, output: BuildOutput, args: Args) -> Result<(), Error> {
+        if args.unix_socket.is_some() || self.unix_socket.is_some() {
+            return Err(Error::InvalidArgs(
+                "--test-headless cannot be combined with --listen, the browser needs a TCP port"
+                    .to_string(),
+            ));
+        }
+        // So that a test can run alongside a dev server or other tests.
+        if args.port.is_none() {
+            self.port = Some(0);
+        }
+        self.dashboard = false;
+        self.tunnel = None;
+        self.mdns = false;
+        let test = headless::HeadlessTest {
+            assertion: args.test_assertion.clone(),
+            webdriver: args.webdriver.clone(),
+            timeout: args.test_timeout.unwrap_or(Duration::from_secs(60)),
+        };
+        let name = args.name.clone();
+        let handle = self.serve_in_background_with(output, args)?;
+        let result = headless::run(handle.url(), &name, &test);
+        handle.shutdown();
+        result
+    }
+
+    #[cfg(not(feature = "server"))]
+    fn run_headless_test(self, _output: BuildOutput, _args: Args) -> Result<(), Error> {
+        Err(Error::InvalidArgs(
+            "--test-headless requires the server feature of cargo-run-wasm".to_string(),
+        ))
+    }
+
+    /// Serves the output of a dist build, or the whole gallery while [`RunWasm::gallery_dist`] is building it, and checks its links.
+    #[cfg(feature = "server")]
+    fn run_link_check(
+        mut self,
+        output: BuildOutput,
+        args: Args,
+        gallery: bool,
+    ) -> Result<(), Error> {
+        if args.unix_socket.is_some() || self.unix_socket.is_some() {
+            return Err(Error::InvalidArgs(
+                "--check-links cannot be combined with --listen, the links are requested over TCP"
+                    .to_string(),
+            ));
+        }
+        if args.port.is_none() {
+            self.port = Some(0);
+        }
+        self.dashboard = false;
+        self.tunnel = None;
+        self.mdns = false;
+        let what = if gallery {
+            // Serves the site directory containing the output of each build.
+            self.gallery = true;
+            "the --gallery-dist output".to_string()
+        } else {
+            format!("the --dist output of `{}`", args.name)
+        };
+        let quiet = args.message_format.unwrap_or(self.message_format) == MessageFormat::Json
+            || args.verbosity.unwrap_or(self.verbosity) == Verbosity::Quiet;
+        let handle = self.serve_in_background_with(output, args)?;
+        let result = links::check(handle.addr().unwrap(), &what, quiet);
+        handle.shutdown();
+        result
+    }
+
+    #[cfg(not(feature = "server"))]
+    fn run_link_check(
+        self,
+        _output: BuildOutput,
+        _args: Args,
+        _gallery: bool,
+    ) -> Result<(), Error> {
+        Err(Error::InvalidArgs(
+            "--check-links requires the server feature of cargo-run-wasm".to_string(),
+        ))
+    }
+
+    /// Binds the dev server and prepares everything it needs, without accepting connections yet.
+    #[cfg(feature = "server")]
+    fn start(self, output: BuildOutput, args: Args) -> Result<Started, Error> {
+        let json = args.message_format.unwrap_or(self.message_format) == MessageFormat::Json;
+        let verbosity = if json {
+            Verbosity::Quiet
+        } else {
+            args.verbosity.unwrap_or(self.verbosity)
+        };
+        color::init(if json {
+            ColorChoice::Never
+        } else {
+            args.color.unwrap_or(self.color)
+        });
+        let _serve_span = tracing::info_span!("serve", name = %args.name).entered();
+        let cargo = cargo();
+        let project_root = args.project_root.clone();
+        let BuildOutput {
+            status: build,
+            dir: example_dest,
+            events,
+            ..
+        } = output;
+        let mut assets_dirs = self.assets_dirs;
+        assets_dirs.extend(args.assets_dirs);
+        // Each profile is output to its own directory in here.
+        let profiles_dir = if args.profiles.is_empty() {
+            None
+        } else {
+            Some(example_dest.parent().unwrap().to_path_buf())
+        };
+
+        let host = args.host.unwrap_or_else(|| "localhost".into());
+        // Allow IPv6 addresses to be given in their bracketed url form, e.g. `[::1]`
+        let host = host
+            .strip_prefix('[')
+            .and_then(|x| x.strip_suffix(']'))
+            .unwrap_or(&host)
+            .to_string();
+        let port = args.port.unwrap_or_else(|| "8000".into());
+        let port: u16 = port.parse().map_err(|_| {
+            Error::InvalidArgs(format!(
+                "Invalid --port {:?}, expected a number from 0 to 65535",
+                port
+            ))
+        })?;
+
+        // watch assets so that changes to them are visible without having to rebuild
+        let reloader = if assets_dirs.is_empty() {
+            None
+        } else {
+            let reloader = Arc::new(Reloader::default());
+            let dests = match &profiles_dir {
+                Some(dir) => args.profiles.iter().map(|x| dir.join(x)).collect(),
+                None => vec![example_dest.clone()],
+            };
+            assets::watch(
+                assets_dirs,
+                dests,
+                reloader.clone(),
+                events.clone(),
+                verbosity,
+            );
+            Some(reloader)
+        };
+
+        let mut headers = self.headers;
+        headers.extend(args.headers);
+        let mut permissions = self.permissions;
+        permissions.extend(args.permissions);
+        if !permissions.is_empty() {
+            headers.push(ExtraHeader::permissions_policy(&permissions));
+        }
+        let mut cors_origins = self.cors_origins;
+        cors_origins.extend(args.cors_origins);
+        let mut proxies = self.proxies;
+        proxies.extend(args.proxies);
+        let mut mime_types = self.mime_types;
+        mime_types.extend(args.mime_types);
+        let mut mounts = self.mounts;
+        mounts.extend(args.mounts);
+        for mount in &mut mounts {
+            mount.dir = project_root.join(&mount.dir);
+        }
+
+        let gallery = self.gallery || args.gallery;
+        let serve_all = self.serve_all || args.serve_all || gallery;
+        if serve_all {
+            let output_dir = example_dest.parent().unwrap();
+            if let Err(err) = gallery::write(&cargo, &project_root, output_dir) {
+                println!("{}", err);
+            }
+        }
+        let url_path = if serve_all && !gallery {
+            format!("/{}/", args.name)
+        } else {
+            String::new()
+        };
+
+        let (access_log_path, access_log_format) = match self.access_log {
+            Some((path, format)) => (Some(path), Some(format)),
+            None => (None, None),
+        };
+        let access_log = match args.access_log.or(access_log_path) {
+            Some(path) => {
+                let format = args
+                    .access_log_format
+                    .or(access_log_format)
+                    .unwrap_or_default();
+                let access_log = server::access_log::AccessLog::open(&path, format).map_err(
+                    Error::io(format!("Failed to open the access log {}", path.display())),
+                )?;
+                Some(access_log)
+            }
+            None => None,
+        };
+
+        // Point at other dev servers in the workspace, rather than leaving the user to work out why e.g. a port is in use.
+        let registry_dir = project_root.join("target/wasm-examples-target/run-wasm-servers");
+        let running = registry::running(&registry_dir);
+        let running_on_port = |port| running.iter().find(|x| x.port == Some(port));
+        if verbosity >= Verbosity::Normal {
+            for server in &running {
+                if server.name != args.name {
+                    continue;
+                }
+                println!(
+                    "`{}` is already being served on {} by process {}",
+                    server.name, server.url, server.pid
+                );
+            }
+        }
+
+        // run webserver on destination folder
+        let mut tunnel = None;
+        let (listener, url, addr) = if let Some(path) = args.unix_socket.or(self.unix_socket) {
+            #[cfg(unix)]
+            let result = server::bind_unix(&path);
+            #[cfg(not(unix))]
+            let result = Err(Error::InvalidArgs(
+                "Unix domain sockets are not supported on this platform".to_string(),
+            ));
+            let listener = result?;
+            if !json {
+                println!(
+                    "\n{} `{}` on {}",
+                    color::paint(color::GREEN, "Serving"),
+                    args.name,
+                    color::paint(color::CYAN, &format!("unix:{}", path.display()))
+                );
+            }
+            (listener, format!("unix:{}", path.display()), None)
+        } else {
+            let listener = match server::bind(
+                &host,
+                port,
+                self.strict_port || args.strict_port,
+                self.reuse_address,
+            ) {
+                Ok(listener) => listener,
+                Err(Error::PortInUse(err)) => match running_on_port(port) {
+                    Some(server) => {
+                        return Err(Error::PortInUse(format!(
+                            "{}\nPort {} is used by the dev server for `{}` in process {}",
+                            err, port, server.name, server.pid
+                        )))
+                    }
+                    None => return Err(Error::PortInUse(err)),
+                },
+                Err(err) => return Err(err),
+            };
+            let addr = listener
+                .local_addr()
+                .map_err(Error::io("Failed to get the address listened on"))?;
+            if port != 0 && addr.port() != port && verbosity >= Verbosity::Normal {
+                match running_on_port(port) {
+                    Some(server) => println!(
+                        "Port {} is used by the dev server for `{}` in process {}, using port {} instead",
+                        port,
+                        server.name,
+                        server.pid,
+                        addr.port()
+                    ),
+                    None => println!(
+                        "Port {} is in use, using port {} instead",
+                        port,
+                        addr.port()
+                    ),
+                }
+            }
+            let base_urls = network::served_urls(&host, addr.port());
+            let urls: Vec<String> = base_urls
+                .iter()
+                .map(|url| url.clone() + &url_path)
+                .collect();
+            let mdns_url = if self.mdns || args.mdns {
+                if urls.len() == 1 {
+                    println!("--mdns has no effect unless listening on all interfaces, e.g. --host 0.0.0.0");
+                    None
+                } else {
+                    let host_name = mdns::host_name(&args.name);
+                    match mdns::advertise(host_name.clone()) {
+                        Ok(()) => Some(format!("http://{}:{}{}", host_name, addr.port(), url_path)),
+                        Err(err) => {
+                            println!("Failed to advertise the dev server via mDNS: {}", err);
+                            None
+                        }
+                    }
+                }
+            } else {
+                None
+            };
+            // Only changes the url shown to the user, e.g. inside a container the address bound to is not the one to visit.
+            let public_url = args.public_host.map(|public_host| {
+                format!(
+                    "http://{}:{}{}",
+                    network::url_host(&public_host),
+                    addr.port(),
+                    url_path
+                )
+            });
+            let shown_urls: Vec<&String> = match &public_url {
+                Some(public_url) => vec![public_url],
+                None => urls.iter().collect(),
+            };
+            let url = shown_urls[0];
+            if json {
+                // Reported by the server-started event instead
+            } else if shown_urls.len() == 1 && mdns_url.is_none() {
+                println!(
+                    "\n{} `{}` on {}",
+                    color::paint(color::GREEN, "Serving"),
+                    args.name,
+                    color::paint(color::CYAN, url)
+                );
+            } else {
+                println!(
+                    "\n{} `{}` on:",
+                    color::paint(color::GREEN, "Serving"),
+                    args.name
+                );
+                for url in shown_urls.iter().copied().chain(&mdns_url) {
+                    println!("  {}", color::paint(color::CYAN, url));
+                }
+            }
+            if urls.len() > 1 && !args.no_qr && verbosity >= Verbosity::Normal {
+                println!(
+                    "\n{}",
+                    network::qr_code(public_url.as_ref().unwrap_or(&urls[1]))
+                );
+            }
+            tunnel = match args.tunnel.or(self.tunnel) {
+                Some(backend) => {
+                    match tunnel::Tunnel::start(
+                        &backend,
+                        addr.port(),
+                        &base_urls[0],
+                        &url_path,
+                        verbosity,
+                    ) {
+                        Ok(tunnel) => Some(tunnel),
+                        Err(err) => {
+                            println!("{}", err);
+                            None
+                        }
+                    }
+                }
+                None => None,
+            };
+            if let Some(on_serve) = self.on_serve {
+                on_serve(addr);
+            }
+            if args.open || args.browser.is_some() {
+                let mut browser_args = self.browser_args;
+                browser_args.extend(args.browser_args);
+                browser::open(url, args.browser.as_deref(), &browser_args);
+            }
+            (Listener::Tcp(listener), url.clone(), Some(addr))
+        };
+        tracing::info!(%url, "serving");
+        events.emit("server-started", json!({ "url": url }));
+        let registration = registry::register(
+            &registry_dir,
+            &args.name,
+            &url,
+            &listener.connect_address().to_string(),
+        );
+
+        let shutdown = Arc::new(Shutdown::new(&listener));
+        let cancel_shutdown = shutdown.clone();
+        let cancellation = self.cancel.on_cancel(move || cancel_shutdown.trigger());
+
+        let throttle = args.throttle.or(self.throttle);
+        if let Some(throttle) = &throttle {
+            if verbosity >= Verbosity::Normal {
+                println!("Throttling responses to {}", throttle.describe());
+            }
+        }
+        let coverage_dir = if args.coverage {
+            let dir = project_root.join("target/wasm-coverage").join(&args.name);
+            if verbosity >= Verbosity::Normal {
+                println!(
+                    "Writing the coverage of each page load to {}",
+                    dir.display()
+                );
+            }
+            Some(dir)
+        } else {
+            None
+        };
+
+        Ok(Started {
+            listener,
+            root: if serve_all {
+                // Every package is output to its own directory in here.
+                example_dest.parent().unwrap().to_path_buf()
+            } else {
+                profiles_dir.clone().unwrap_or_else(|| example_dest.clone())
+            },
+            config: ServerConfig {
+                headers,
+                cors_origins,
+                proxies,
+                mounts,
+                request_hooks: self.request_hooks,
+                response_hooks: self.response_hooks,
+                reloader,
+                spa: self.spa || args.spa,
+                serve_all,
+                mime_types,
+                directory_listing: self.list_dirs || args.list_dirs,
+                compression: !(self.no_compression || args.no_compression),
+                verbosity,
+                console: self.console || args.console,
+                headless_test: args.test_headless,
+                profile_startup: self.profile_startup || args.profile_startup,
+                coverage_dir,
+                cache_control: args
+                    .cache_control
+                    .or(self.cache_control)
+                    .unwrap_or_else(|| "no-cache".to_string()),
+                immutable: !(self.no_immutable || args.no_immutable),
+                auth: args.auth.or(self.auth),
+                build,
+                events,
+                started: Instant::now(),
+                max_connections: self.max_connections,
+                read_timeout: self.read_timeout.unwrap_or(Duration::from_secs(30)),
+                throttle,
+                access_log,
+                dashboard: if self.dashboard || args.dashboard {
+                    Some(Dashboard::new(&url))
+                } else {
+                    None
+                },
+            },
+            shutdown,
+            addr,
+            url,
+            cleanup: Cleanup {
+                verbosity,
+                tunnel,
+                registration,
+                cancellation,
+                on_shutdown: self.on_shutdown,
+                clean_dir: if self.clean_on_exit || args.clean_on_exit {
+                    Some(profiles_dir.unwrap_or(example_dest))
+                } else {
+                    None
+                },
+            },
+        })
+    }
+}
+
+/// A build whose args have been read and before build hooks run, returned by [`RunWasm::prepare_build`].
+struct PendingBuild {
+    context: BuildContext,
+    notify: bool,
+    dist: bool,
+    /// The hosts to write configs for after the dist step, and what they should match.
+    deploy: Option<(Vec<DeployConfig>, deploy::Hosting)>,
+    /// Where to write the archive of the dist output to.
+    archive: Option<PathBuf>,
+    /// The `public_url` of [`RunWasm::trunk_dist`], which names and links the js and wasm like Trunk does when set.
+    trunk_public_url: Option<String>,
+    /// Where to write the notes on setting up the itch.io project, and the headers the dev server would send which they cover.
+    itch: Option<(PathBuf, Vec<ExtraHeader>)>,
+    /// The manifest of the dist output to write, or to check against when true.
+    dist_manifest: Option<(PathBuf, bool)>,
+    /// The target and package metadata to write a `package.json` from.
+    pkg: Option<(PkgTarget, serde_json::Value)>,
+    features: Option<String>,
+    report_path: Option<PathBuf>,
+    /// Whether to append the report to [`history::PATH`].
+    history: bool,
+    /// Where the [`fingerprint`] of the last build into the same directory is kept, None when the steps after cargo always run.
+    fingerprint_path: Option<PathBuf>,
+    /// Whether the steps after cargo are skipped, decided once cargo has finished.
+    up_to_date: Option<bool>,
+    /// How long the before build hooks and each step took, for the [`BuildReport`].
+    phases: Vec<(String, Duration)>,
+    /// The sizes of [`PendingBuild::artifacts`] after the step that generated them.
+    generated_sizes: Vec<Option<u64>>,
+    span: tracing::Span,
+    started_at: SystemTime,
+    start: Instant,
+}
+
+impl PendingBuild {
+    fn run_step(&mut self, step: &dyn BuildStep) -> Result<(), Error> {
+        let _build_span = self.span.clone().entered();
+        if self.context.cancel.is_cancelled() {
+            return Err(self.fail(Error::Cancelled));
+        }
+        if !step.is_cargo_build() {
+            if self.up_to_date.is_none() {
+                self.up_to_date = Some(self.is_up_to_date());
+            }
+            if self.up_to_date == Some(true) {
+                return Ok(());
+            }
+        }
+        let start = Instant::now();
+        let result = step.run(&mut self.context);
+        if result.is_ok() {
+            self.finished_phase(step.name(), start);
+        }
+        result.map_err(|err| {
+            let err = match err.downcast::<Error>() {
+                // The step most likely failed because it was interrupted, e.g. cargo was killed.
+                _ if self.context.cancel.is_cancelled() => Error::Cancelled,
+                Ok(err) => *err,
+                Err(source) => Error::Step {
+                    step: step.name().to_string(),
+                    source,
+                },
+            };
+            self.fail(err)
+        })
+    }
+
+    /// Whether the generated files are those of the last build into the same directory, and nothing they are generated from changed since.
+    fn is_up_to_date(&self) -> bool {
+        let path = match &self.fingerprint_path {
+            Some(path) => path,
+            None => return false,
+        };
+        let up_to_date = fingerprint::matches(
+            path,
+            &fingerprint::compute(&self.context, &self.artifacts()),
+        );
+        if up_to_date {
+            tracing::info!("generated files are up to date, skipping the steps after cargo");
+        }
+        up_to_date
+    }
+
+    /// The generated wasm, js and index.html in that order.
+    fn artifacts(&self) -> Vec<PathBuf> {
+        let info = &self.context.info;
+        let wasm_name = if self.context.bindgen {
+            format!("{}_bg.wasm", info.name)
+        } else {
+            format!("{}.wasm", info.name)
+        };
+        vec![
+            info.dir.join(wasm_name),
+            info.dir.join(format!("{}.js", info.name)),
+            info.dir.join("index.html"),
+        ]
+    }
+
+    /// Records how long the phase `name` took and the size of any artifact it generated.
+    fn finished_phase(&mut self, name: &str, start: Instant) {
+        self.phases.push((name.to_string(), start.elapsed()));
+        for (path, size) in self.artifacts().iter().zip(&mut self.generated_sizes) {
+            if size.is_none() {
+                *size = report::file_size_since(path, self.started_at);
+            }
+        }
+    }
+
+    /// Like running the [`CargoBuild`] step, but awaiting cargo.
+    #[cfg(feature = "tokio")]
+    async fn run_cargo_async(&self) -> Result<(), Error> {
+        use tokio::io::AsyncBufReadExt;
+        use tracing::Instrument;
+
+        let context = &self.context;
+        let cargo_span =
+            tracing::info_span!(parent: &self.span, "cargo", args = ?context.cargo_args);
+        async {
+            let mut child = tokio::process::Command::from(context.cargo_command())
+                .stdout(std::process::Stdio::piped())
+                .spawn()
+                .map_err(Error::io(format!("Failed to run {}", context.cargo)))?;
+            let mut lines = tokio::io::BufReader::new(child.stdout.take().unwrap()).lines();
+            let read = async {
+                while let Some(line) = lines.next_line().await? {
+                    context.cargo_message(&line);
+                }
+                Ok(())
+            };
+            match cancel::or_cancelled(&context.cancel, read).await {
+                Some(result) => result.map_err(Error::io("Failed to read the output of cargo"))?,
+                None => {
+                    child.kill().await.ok();
+                    return Err(Error::Cancelled);
+                }
+            }
+            let status = child
+                .wait()
+                .await
+                .map_err(Error::io("Failed to wait for cargo"))?;
+            if !status.success() {
+                return Err(Error::BuildFailed {
+                    exit_code: status.code(),
+                });
+            }
+            Ok(())
+        }
+        .instrument(cargo_span)
+        .await
+    }
+
+    /// Reports that the build failed, returning `err` for the caller to return.
+    fn fail(&self, err: Error) -> Error {
+        tracing::error!(error = %err, "build failed");
+        self.context.events.emit(
+            "build-finished",
+            json!({ "success": false, "error": err.to_string() }),
+        );
+        if self.notify {
+            notify::notify(
+                "Build failed",
+                &format!("`{}` failed to build", self.context.info.name),
+            );
+        }
+        err
+    }
+}
+
+/// A dev server that is listening, returned by [`RunWasm::start`].
+#[cfg(feature = "server")]
+struct Started {
+    listener: Listener,
+    root: PathBuf,
+    config: ServerConfig,
+    shutdown: Arc<Shutdown>,
+    /// None when listening on a unix socket.
+    addr: Option<SocketAddr>,
+    url: String,
+    cleanup: Cleanup,
+}
+
+#[cfg(feature = "server")]
+impl Started {
+    /// Serves requests on this thread until shutdown is triggered.
+    fn run(self) {
+        server::serve(self.listener, &self.root, self.config, &self.shutdown);
+        self.cleanup.run();
+    }
+}
+
+/// What is left to do once the dev server has stopped.
+#[cfg(feature = "server")]
+struct Cleanup {
+    verbosity: Verbosity,
+    tunnel: Option<tunnel::Tunnel>,
+    registration: Option<registry::Registration>,
+    /// Shuts the dev server down when the [`CancelToken`] is cancelled.
+    cancellation: cancel::Registration,
+    on_shutdown: Vec<Box<dyn FnOnce()>>,
+    /// The output directory, when it should be deleted.
+    clean_dir: Option<PathBuf>,
+}
+
+#[cfg(feature = "server")]
+impl Cleanup {
+    fn run(self) {
+        tracing::info!("shutting down");
+        if self.verbosity >= Verbosity::Normal {
+            println!("Shutting down");
+        }
+        if let Some(tunnel) = self.tunnel {
+            tunnel.stop();
+        }
+        drop(self.registration);
+        drop(self.cancellation);
+        for on_shutdown in self.on_shutdown {
+            on_shutdown();
+        }
+        if let Some(dir) = self.clean_dir {
+            if let Err(err) = std::fs::remove_dir_all(&dir) {
+                println!("Failed to delete {}: {}", dir.display(), err);
+            }
+        }
+        std::io::stdout().flush().ok();
+    }
+}
+
+/// Shuts the dev server down on ctrl-c.
+#[cfg(feature = "server")]
+fn handle_ctrl_c(shutdown: Arc<Shutdown>) {
+    let result = ctrlc::set_handler(move || {
+        if shutdown.is_requested() {
+            // The user is impatient, a second ctrl-c exits immediately.
+            std::process::exit(130);
+        }
+        shutdown.trigger();
+    });
+    if let Err(err) = result {
+        println!(
+            "Failed to install ctrl-c handler, the dev server will not shut down gracefully: {}",
+            err
+        );
+    }
+}
+
+/// A dev server running on a background thread, returned by [`RunWasm::serve_in_background`].
+///
+/// Dropping the handle shuts the dev server down, like [`ServerHandle::shutdown`].
+/// Requires the `server` feature, which is enabled by default.
+#[cfg(feature = "server")]
+pub struct ServerHandle {
+    addr: Option<SocketAddr>,
+    url: String,
+    shutdown: Arc<Shutdown>,
+    thread: Option<std::thread::JoinHandle<()>>,
+    cleanup: Option<Cleanup>,
+}
+
+#[cfg(feature = "server")]
+impl ServerHandle {
+    /// The address listened on, or `None` when listening on a unix domain socket.
+    ///
+    /// With port 0 this gives the port that was picked.
+    pub fn addr(&self) -> Option<SocketAddr> {
+        self.addr
+    }
+
+    /// The url the dev server was announced on.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Stops accepting connections and returns once requests in progress have finished and the dev server has cleaned up,
+    /// as happens on ctrl-c with [`RunWasm::serve`].
+    pub fn shutdown(mut self) {
+        self.shutdown.trigger();
+        self.join();
+    }
+
+    /// Blocks until the dev server is stopped with ctrl-c, for when there is nothing left to do but serve.
+    pub fn wait(mut self) {
+        handle_ctrl_c(self.shutdown.clone());
+        self.join();
+    }
+
+    fn join(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+        if let Some(cleanup) = self.cleanup.take() {
+            cleanup.run();
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+impl Drop for ServerHandle {
+    fn drop(&mut self) {
+        if self.thread.is_some() {
+            self.shutdown.trigger();
+            self.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::config::{self, PackageTarget};
+    use super::{cargo, locate_workspace, wasm_path, workspace_packages, Args, Error, RunWasm};
+    use std::ffi::OsString;
+    use std::path::{Path, PathBuf};
+
+    /// The error of parsing `args`, as `RunWasm::try_run` would return it.
+    fn parse_error(args: Vec<OsString>) -> Option<Error> {
+        Args::from_vec(args, &[], &[], || Ok(None))
+            .err()
+            .map(Error::InvalidArgs)
+    }
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn parses_valid_args() {
+        let parsed = Args::from_vec(
+            args(&["demo", "--example", "--port", "9000", "--release"]),
+            &[],
+            &[],
+            || Ok(None),
+        )
+        .ok()
+        .unwrap();
+        assert_eq!(parsed.name, "demo");
+        assert!(parsed.example && parsed.release);
+        assert_eq!(parsed.port.as_deref(), Some("9000"));
+    }
+
+    #[test]
+    fn invalid_args_exit_with_the_invalid_args_code() {
+        for invalid in [
+            args(&["demo", "--port"]),
+            args(&["demo", "--features"]),
+            args(&["demo", "--color", "sometimes"]),
+            args(&["demo", "--unknown"]),
+            args(&["demo", "other"]),
+            args(&[]),
+        ] {
+            let err = parse_error(invalid).unwrap();
+            assert_eq!(err.exit_code(), 2, "{}", err);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_args_are_invalid() {
+        use std::os::unix::ffi::OsStringExt;
+        let arg = OsString::from_vec(vec![b'd', 0xff]);
+        let err = parse_error(vec![arg]).unwrap();
+        assert_eq!(err.exit_code(), 2, "{}", err);
+    }
+
+    /// Writes `files` into an empty temporary directory named after `name`, returning the directory.
+    fn write_workspace(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "cargo-run-wasm-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+        for (path, contents) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+        // Cargo reports canonical paths, which the temporary directory need not be.
+        dir.canonicalize().unwrap()
+    }
+
+    fn package(name: &str) -> String {
+        format!(
+            "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2018\"\n",
+            name
+        )
+    }
+
+    /// The names of the packages found in the workspace at `root` and the workspace manifest of each.
+    fn packages(root: &Path) -> Vec<(String, Option<PathBuf>)> {
+        workspace_packages(&cargo(), root)
+            .unwrap()
+            .into_iter()
+            .map(|x| {
+                (
+                    x.metadata["name"].as_str().unwrap().to_string(),
+                    x.workspace_manifest,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_the_root_of_a_virtual_workspace_from_a_deeply_nested_runner() {
+        let runner = package("run-wasm");
+        let app = package("app");
+        let root = write_workspace(
+            "virtual",
+            &[
+                (
+                    "Cargo.toml",
+                    "[workspace]\nmembers = [\"tools/dev/run-wasm\", \"app\"]\n",
+                ),
+                ("tools/dev/run-wasm/Cargo.toml", &runner),
+                ("tools/dev/run-wasm/src/main.rs", "fn main() {}"),
+                ("app/Cargo.toml", &app),
+                ("app/src/main.rs", "fn main() {}"),
+            ],
+        );
+        let found = locate_workspace(Some(&root.join("tools/dev/run-wasm"))).unwrap();
+        assert_eq!(found, root);
+        let names: Vec<String> = packages(&root).into_iter().map(|x| x.0).collect();
+        assert!(names.contains(&"app".to_string()), "{:?}", names);
+
+        // The wasm is written into the target directory of the workspace root.
+        let args = Args {
+            name: "app".to_string(),
+            project_root: found,
+            ..Args::default()
+        };
+        assert_eq!(
+            wasm_path(&args, "target/wasm-examples-target", "debug"),
+            root.join("target/wasm-examples-target/wasm32-unknown-unknown/debug/app.wasm")
+        );
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn finds_packages_of_nested_workspaces() {
+        let app = package("app");
+        let cube = package("cube");
+        let root = write_workspace(
+            "nested",
+            &[
+                (
+                    "Cargo.toml",
+                    "[workspace]\nmembers = [\"app\"]\nexclude = [\"demos\"]\n",
+                ),
+                ("app/Cargo.toml", &app),
+                ("app/src/main.rs", "fn main() {}"),
+                ("demos/Cargo.toml", "[workspace]\nmembers = [\"cube\"]\n"),
+                ("demos/cube/Cargo.toml", &cube),
+                ("demos/cube/src/main.rs", "fn main() {}"),
+                ("demos/cube/examples/spin.rs", "fn main() {}"),
+            ],
+        );
+        assert_eq!(
+            packages(&root),
+            vec![
+                ("app".to_string(), None),
+                ("cube".to_string(), Some(root.join("demos/Cargo.toml"))),
+            ]
+        );
+        // Built via the manifest of the nested workspace, but from the project root, so the output is still under it.
+        let cube = config::load_package(&cargo(), &root, "spin", true).unwrap();
+        assert_eq!(cube.workspace_manifest, Some(root.join("demos/Cargo.toml")));
+        let args = Args {
+            name: "spin".to_string(),
+            example: true,
+            project_root: root.clone(),
+            ..Args::default()
+        };
+        assert_eq!(
+            wasm_path(&args, "target/wasm-examples-target", "release"),
+            root.join(
+                "target/wasm-examples-target/wasm32-unknown-unknown/release/examples/spin.wasm"
+            )
+        );
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn finds_excluded_standalone_packages() {
+        let app = package("app");
+        let tool = package("level-editor");
+        let root = write_workspace(
+            "excluded",
+            &[
+                (
+                    "Cargo.toml",
+                    "[workspace]\nmembers = [\"app\"]\nexclude = [\"tools/level-editor\", \"fixtures\"]\n",
+                ),
+                ("app/Cargo.toml", &app),
+                ("app/src/main.rs", "fn main() {}"),
+                ("tools/level-editor/Cargo.toml", &tool),
+                ("tools/level-editor/src/lib.rs", ""),
+                // Not a package, so it is skipped rather than failing the whole workspace.
+                ("fixtures/data.txt", ""),
+            ],
+        );
+        assert_eq!(
+            packages(&root),
+            vec![
+                ("app".to_string(), None),
+                (
+                    "level-editor".to_string(),
+                    Some(root.join("tools/level-editor/Cargo.toml"))
+                ),
+            ]
+        );
+        let args = Args {
+            name: "level-editor".to_string(),
+            package_target: Some(PackageTarget::Lib),
+            project_root: root.clone(),
+            ..Args::default()
+        };
+        assert_eq!(
+            wasm_path(&args, "target/wasm-examples-target", "debug"),
+            root.join("target/wasm-examples-target/wasm32-unknown-unknown/debug/level_editor.wasm")
+        );
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn builder_settings_take_priority_over_run_wasm_toml() {
+        let app = package("app");
+        let root = write_workspace(
+            "builder-priority",
+            &[
+                ("Cargo.toml", &app),
+                ("src/main.rs", "fn main() {}"),
+                (
+                    "run-wasm.toml",
+                    "release = false\nopen = true\nhost = \"0.0.0.0\"\nport = 9000\n",
+                ),
+            ],
         );
+        let run_wasm = RunWasm::new()
+            .package("app")
+            .project_root(&root)
+            .release(true)
+            .host("127.0.0.1");
+        let args = run_wasm.resolve_args(root.clone(), None).unwrap();
+        assert!(args.release);
+        assert_eq!(args.host.as_deref(), Some("127.0.0.1"));
+        // Left unset by the builder, so the file decides them.
+        assert!(args.open);
+        assert_eq!(args.port.as_deref(), Some("9000"));
+        std::fs::remove_dir_all(root).unwrap();
     }
 }
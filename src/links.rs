@@ -0,0 +1,323 @@
+//! Requests every page, script, stylesheet and asset that the served `--dist` or `--gallery-dist` output links to, for `--check-links`.
+//!
+//! Starting from the index.html, the urls are found in html attributes, the imports and fetches of scripts and the `url()`s of stylesheets,
+//! which covers what the generated files and typical hand written pages refer to, though not urls built up at runtime.
+//! Only urls on the site itself are followed, a broken link to another site is not something a rebuild can fix.
+
+use crate::{color, Error};
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+/// Html attributes holding a url, `srcset` holds several.
+const URL_ATTRIBUTES: &[&str] = &["href", "src", "srcset", "poster", "data"];
+
+/// What js refers to other files with, each followed by a string literal, and whether it only counts when that starts with `.` or `/`.
+///
+/// Module specifiers are always relative or absolute in a page without an import map, which tells them apart from the words in strings.
+const JS_REFERENCES: &[(&str, bool)] = &[
+    ("import(", true),
+    ("import", true),
+    ("from", true),
+    ("fetch(", false),
+    ("new URL(", false),
+];
+
+/// Following a link should not take anywhere near this long, even on a slow CI machine.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Gives up on a site that keeps linking to new pages, e.g. ones generated from their own url.
+const MAX_REQUESTS: usize = 10_000;
+
+/// The response to a request for a url path on the site.
+struct Response {
+    status: u16,
+    content_type: String,
+    location: Option<String>,
+    body: Vec<u8>,
+}
+
+/// Crawls the site served at `addr` from `/`, failing with [`Error::BrokenLinks`] listing every url that wasnt found,
+/// along with the file that linked to it. `what` describes the site in the messages, e.g. ``the --dist output of `app` ``.
+pub(crate) fn check(addr: SocketAddr, what: &str, quiet: bool) -> Result<(), Error> {
+    // By the url that first linked to each url.
+    let mut referrers: HashMap<String, String> = HashMap::new();
+    let mut queue = vec![("/".to_string(), String::new())];
+    let mut seen = HashSet::new();
+    let mut broken = vec![];
+    while let Some((path, referrer)) = queue.pop() {
+        if !seen.insert(path.clone()) {
+            continue;
+        }
+        if seen.len() > MAX_REQUESTS {
+            return Err(Error::BrokenLinks(format!(
+                "Gave up checking the links of {} after {} urls, it keeps linking to new ones",
+                what, MAX_REQUESTS
+            )));
+        }
+        referrers.insert(path.clone(), referrer);
+        let response = get(addr, &path)?;
+        let links = match response.status {
+            200 => find_links(&path, &response),
+            // Hosts commonly redirect e.g. a directory without a trailing slash, so only where it ends up matters.
+            301 | 302 | 307 | 308 if response.location.is_some() => {
+                resolve(&path, response.location.as_deref().unwrap())
+                    .into_iter()
+                    .collect()
+            }
+            status => {
+                broken.push((status, path));
+                continue;
+            }
+        };
+        for link in links {
+            if !seen.contains(&link) {
+                queue.push((link, path.clone()));
+            }
+        }
+    }
+
+    if broken.is_empty() {
+        if !quiet {
+            println!(
+                "{} {} urls linked from {}, none are broken",
+                color::paint(color::GREEN, "Checked"),
+                seen.len(),
+                what
+            );
+        }
+        return Ok(());
+    }
+    broken.sort_by(|a, b| a.1.cmp(&b.1));
+    let lines: Vec<String> = broken
+        .iter()
+        .map(|(status, path)| {
+            let referrer = &referrers[path];
+            if referrer.is_empty() {
+                format!("  {} {}", status, path)
+            } else {
+                format!("  {} {}, linked from {}", status, path, referrer)
+            }
+        })
+        .collect();
+    Err(Error::BrokenLinks(format!(
+        "Found {} broken link{} in {}:\n{}",
+        broken.len(),
+        if broken.len() == 1 { "" } else { "s" },
+        what,
+        lines.join("\n")
+    )))
+}
+
+/// The url paths on the site that `response`, served at `path`, links to.
+fn find_links(path: &str, response: &Response) -> Vec<String> {
+    let extension = path
+        .rsplit('/')
+        .next()
+        .and_then(|x| x.rsplit_once('.'))
+        .map(|(_, x)| x)
+        .unwrap_or_default();
+    let body = String::from_utf8_lossy(&response.body);
+    let urls = if response.content_type.starts_with("text/html") {
+        let mut urls = html_urls(&body);
+        // Inline scripts and styles.
+        urls.extend(js_urls(&body));
+        urls.extend(css_urls(&body));
+        urls
+    } else if response.content_type.starts_with("text/javascript") || extension == "mjs" {
+        js_urls(&body)
+    } else if response.content_type.starts_with("text/css") {
+        css_urls(&body)
+    } else {
+        return vec![];
+    };
+    urls.iter().filter_map(|url| resolve(path, url)).collect()
+}
+
+/// The values of the attributes in [`URL_ATTRIBUTES`].
+fn html_urls(html: &str) -> Vec<String> {
+    let mut urls = vec![];
+    let lower = html.to_ascii_lowercase();
+    for attribute in URL_ATTRIBUTES {
+        let pattern = format!("{}=", attribute);
+        for (i, _) in lower.match_indices(&pattern) {
+            // Not the end of a longer attribute name, such as `data-src`.
+            let before = lower[..i].chars().next_back();
+            if !matches!(before, Some(x) if x.is_whitespace()) {
+                continue;
+            }
+            let value = match quoted(&html[i + pattern.len()..]) {
+                Some(value) => value,
+                None => html[i + pattern.len()..]
+                    .split(|x: char| x.is_whitespace() || x == '>')
+                    .next()
+                    .unwrap_or_default(),
+            };
+            if *attribute == "srcset" {
+                urls.extend(
+                    value
+                        .split(',')
+                        .filter_map(|x| x.split_whitespace().next())
+                        .map(|x| x.to_string()),
+                );
+            } else {
+                urls.push(value.to_string());
+            }
+        }
+    }
+    urls
+}
+
+/// The string literals following each of [`JS_REFERENCES`].
+fn js_urls(js: &str) -> Vec<String> {
+    let mut urls = vec![];
+    for (reference, module) in JS_REFERENCES {
+        for (i, _) in js.match_indices(reference) {
+            match quoted(js[i + reference.len()..].trim_start()) {
+                Some(url) if !module || url.starts_with('.') || url.starts_with('/') => {
+                    urls.push(url.to_string())
+                }
+                _ => {}
+            }
+        }
+    }
+    urls
+}
+
+/// The urls of the `url()`s and `@import`s of a stylesheet.
+fn css_urls(css: &str) -> Vec<String> {
+    let mut urls = vec![];
+    for (i, _) in css.match_indices("url(") {
+        let rest = css[i + 4..].trim_start();
+        match quoted(rest) {
+            Some(url) => urls.push(url.to_string()),
+            None => urls.extend(rest.split(')').next().map(|x| x.trim().to_string())),
+        }
+    }
+    for (i, _) in css.match_indices("@import") {
+        urls.extend(quoted(css[i + 7..].trim_start()).map(|x| x.to_string()));
+    }
+    urls
+}
+
+/// The contents of the string literal `text` starts with, if it does.
+fn quoted(text: &str) -> Option<&str> {
+    let quote = text
+        .chars()
+        .next()
+        .filter(|x| matches!(x, '"' | '\'' | '`'))?;
+    let rest = &text[1..];
+    let end = rest.find(quote)?;
+    let value = &rest[..end];
+    // A template literal with substitutions is built at runtime.
+    if quote == '`' && value.contains("${") {
+        return None;
+    }
+    Some(value)
+}
+
+/// Resolves `url` against the url path `base`, returning the path it refers to on the site,
+/// or None when it is on another site or isnt a file, e.g. a `data:` url or a link to a fragment of the same page.
+fn resolve(base: &str, url: &str) -> Option<String> {
+    let url = url.trim();
+    let url = url.split('#').next().unwrap_or_default();
+    let url = url.split('?').next().unwrap_or_default();
+    let has_scheme = url
+        .split_once(':')
+        .map(|(scheme, _)| {
+            !scheme.is_empty()
+                && scheme
+                    .chars()
+                    .all(|x| x.is_ascii_alphanumeric() || "+-.".contains(x))
+        })
+        .unwrap_or(false);
+    if url.is_empty() || has_scheme || url.starts_with("//") || url.contains("${") {
+        return None;
+    }
+    let mut segments: Vec<&str> = if url.starts_with('/') {
+        vec![]
+    } else {
+        // The directory of the base, the last segment is the file itself.
+        let mut segments: Vec<&str> = base.split('/').filter(|x| !x.is_empty()).collect();
+        if !base.ends_with('/') {
+            segments.pop();
+        }
+        segments
+    };
+    for segment in url.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    let mut path = format!("/{}", segments.join("/"));
+    if url.ends_with('/') && path != "/" {
+        path.push('/');
+    }
+    Some(path)
+}
+
+/// Requests `path` from the dev server at `addr`.
+///
+/// Only the dev server is ever requested, so a minimal HTTP/1.1 client is enough, as with the WebDriver client in [`crate::headless`].
+fn get(addr: SocketAddr, path: &str) -> Result<Response, Error> {
+    let error = |err: std::io::Error| Error::Io {
+        context: format!("Failed to request {} from the dev server", path),
+        source: err,
+    };
+    let mut stream = TcpStream::connect(addr).map_err(error)?;
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(error)?;
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        path, addr
+    )
+    .map_err(error)?;
+    let mut response = vec![];
+    stream.read_to_end(&mut response).map_err(error)?;
+
+    let invalid = || {
+        error(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "invalid response",
+        ))
+    };
+    let split = response
+        .windows(4)
+        .position(|x| x == b"\r\n\r\n")
+        .ok_or_else(invalid)?;
+    let head = String::from_utf8_lossy(&response[..split]).into_owned();
+    let mut body = response[split + 4..].to_vec();
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|x| x.split_whitespace().nth(1))
+        .and_then(|x| x.parse().ok())
+        .ok_or_else(invalid)?;
+    let mut content_type = String::new();
+    let mut location = None;
+    for line in lines {
+        let (name, value) = match line.split_once(':') {
+            Some((name, value)) => (name.trim().to_ascii_lowercase(), value.trim()),
+            None => continue,
+        };
+        match name.as_str() {
+            "content-type" => content_type = value.to_ascii_lowercase(),
+            "location" => location = Some(value.to_string()),
+            "transfer-encoding" if value.eq_ignore_ascii_case("chunked") => {
+                body = crate::headless::dechunk(&body).ok_or_else(invalid)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(Response {
+        status,
+        content_type,
+        location,
+        body,
+    })
+}
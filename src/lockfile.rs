@@ -0,0 +1,83 @@
+//! Reads the dependency graph from `Cargo.lock`, to check that a package depends on wasm-bindgen before spending minutes compiling it.
+//!
+//! The lockfile covers every feature and platform, so a dependency found in it may not end up being built,
+//! but one missing from it is certainly not, which is all that is needed to rule a package out.
+
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use toml::Value as Toml;
+
+/// A `[[package]]` entry of the lockfile.
+struct Locked<'a> {
+    version: &'a str,
+    /// Workspace packages have no source, which tells them apart from a crates.io package of the same name and version.
+    source: Option<&'a str>,
+    /// Each is `name`, or `name version` when several versions are locked, optionally followed by the source.
+    dependencies: Vec<&'a str>,
+}
+
+/// Whether `package`, as described by `cargo metadata`, depends on `dependency` directly or through its own dependencies,
+/// including dev-dependencies since examples can use those.
+///
+/// None when it cant be told, e.g. there is no lockfile yet or it is out of date with the Cargo.toml of the package.
+pub(crate) fn depends_on(lockfile: &Path, package: &Value, dependency: &str) -> Option<bool> {
+    let direct: Vec<&str> = package["dependencies"]
+        .as_array()?
+        .iter()
+        .filter_map(|x| x["name"].as_str())
+        .collect();
+    if direct.contains(&dependency) {
+        return Some(true);
+    }
+
+    let lockfile = std::fs::read_to_string(lockfile)
+        .ok()?
+        .parse::<Toml>()
+        .ok()?;
+    let mut by_name: HashMap<&str, Vec<Locked>> = HashMap::new();
+    for entry in lockfile.get("package")?.as_array()? {
+        let str = |key: &str| entry.get(key).and_then(|x| x.as_str());
+        by_name.entry(str("name")?).or_default().push(Locked {
+            version: str("version")?,
+            source: str("source"),
+            dependencies: entry
+                .get("dependencies")
+                .and_then(|x| x.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|x| x.as_str())
+                .collect(),
+        });
+    }
+    let root = by_name
+        .get(package["name"].as_str()?)?
+        .iter()
+        .find(|x| x.source.is_none() && Some(x.version) == package["version"].as_str())?;
+    let name_of = |entry: &str| entry.split(' ').next().unwrap_or_default().to_string();
+    // A dependency added since the lockfile was written could bring in anything.
+    let locked: Vec<String> = root.dependencies.iter().map(|x| name_of(x)).collect();
+    if direct.iter().any(|x| !locked.iter().any(|y| y == x)) {
+        return None;
+    }
+
+    let mut seen = HashSet::new();
+    let mut queue = root.dependencies.clone();
+    while let Some(entry) = queue.pop() {
+        if !seen.insert(entry) {
+            continue;
+        }
+        let mut parts = entry.split(' ');
+        let name = parts.next().unwrap_or_default();
+        let version = parts.next();
+        if name == dependency {
+            return Some(true);
+        }
+        for locked in by_name.get(name).into_iter().flatten() {
+            if version.is_none() || version == Some(locked.version) {
+                queue.extend(&locked.dependencies);
+            }
+        }
+    }
+    Some(false)
+}
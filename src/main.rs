@@ -0,0 +1,7 @@
+//! The `cargo run-wasm` subcommand installed by `cargo install cargo-run-wasm`, for running packages and examples without a runner crate.
+//!
+//! Runner crates that call into the library are still needed to customize anything beyond the command line args and config files.
+
+fn main() {
+    cargo_run_wasm::RunWasm::new().run();
+}
@@ -0,0 +1,192 @@
+//! Advertises the dev server on the LAN as `<name>.local` via multicast DNS, so that phones and tablets can reach it by name
+//! instead of by an IP address that changes from network to network.
+//!
+//! This is a minimal responder that only answers IPv4 address queries for a single host name,
+//! it does not probe for conflicts or advertise services.
+
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::thread;
+
+const MDNS_ADDR: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+
+const TYPE_A: u16 = 1;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+/// Tells receivers to replace any records they cached for this name, rather than adding to them.
+const CACHE_FLUSH: u16 = 0x8000;
+const TTL_SECS: u32 = 120;
+
+/// Turns a crate name into a valid host name, e.g. `my_game` into `my-game.local`.
+pub(crate) fn host_name(name: &str) -> String {
+    let label: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    format!("{}.local", label.trim_matches('-'))
+}
+
+/// Start answering queries for `host_name` with this machines LAN addresses, from a background thread.
+pub(crate) fn advertise(host_name: String) -> io::Result<()> {
+    let socket = bind()?;
+    // Announce ourselves straight away so that anything that previously cached a different address forgets it.
+    send_response(&socket, &host_name, 0, false, multicast_addr());
+    thread::spawn(move || {
+        let mut buffer = [0; 9000];
+        loop {
+            let (len, source) = match socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(_) => continue,
+            };
+            let packet = &buffer[..len];
+            if !is_query_for(packet, &host_name) {
+                continue;
+            }
+            if source.port() == MDNS_PORT {
+                send_response(&socket, &host_name, 0, false, multicast_addr());
+            } else {
+                // A simple resolver that is not itself an mDNS responder, it expects a normal unicast DNS reply.
+                let id = u16::from_be_bytes([packet[0], packet[1]]);
+                send_response(&socket, &host_name, id, true, source);
+            }
+        }
+    });
+    Ok(())
+}
+
+fn bind() -> io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    // The OS usually runs its own mDNS responder already, so the port has to be shared with it.
+    socket.set_reuse_address(true)?;
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    socket.set_reuse_port(true)?;
+    socket.bind(&SockAddr::from(SocketAddrV4::new(
+        Ipv4Addr::UNSPECIFIED,
+        MDNS_PORT,
+    )))?;
+    socket.join_multicast_v4(&MDNS_ADDR, &Ipv4Addr::UNSPECIFIED)?;
+    Ok(socket.into())
+}
+
+fn multicast_addr() -> SocketAddr {
+    SocketAddrV4::new(MDNS_ADDR, MDNS_PORT).into()
+}
+
+fn send_response(
+    socket: &UdpSocket,
+    host_name: &str,
+    id: u16,
+    include_question: bool,
+    destination: SocketAddr,
+) {
+    let addresses = lan_addresses();
+    if addresses.is_empty() {
+        return;
+    }
+    let mut packet = vec![];
+    packet.extend(id.to_be_bytes());
+    // Flags: this is an authoritative response
+    packet.extend(0x8400u16.to_be_bytes());
+    packet.extend((include_question as u16).to_be_bytes());
+    packet.extend((addresses.len() as u16).to_be_bytes());
+    packet.extend([0; 4]);
+    if include_question {
+        write_name(&mut packet, host_name);
+        packet.extend(TYPE_A.to_be_bytes());
+        packet.extend(CLASS_IN.to_be_bytes());
+    }
+    for address in addresses {
+        write_name(&mut packet, host_name);
+        packet.extend(TYPE_A.to_be_bytes());
+        // Unicast replies go to resolvers that dont understand the cache flush bit.
+        let class = if include_question {
+            CLASS_IN
+        } else {
+            CLASS_IN | CACHE_FLUSH
+        };
+        packet.extend(class.to_be_bytes());
+        packet.extend(TTL_SECS.to_be_bytes());
+        packet.extend(4u16.to_be_bytes());
+        packet.extend(address.octets());
+    }
+    // If this fails the next query will just try again.
+    socket.send_to(&packet, destination).ok();
+}
+
+fn lan_addresses() -> Vec<Ipv4Addr> {
+    if_addrs::get_if_addrs()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|x| !x.is_loopback())
+        .filter_map(|x| match x.ip() {
+            IpAddr::V4(ip) => Some(ip),
+            IpAddr::V6(_) => None,
+        })
+        .collect()
+}
+
+fn write_name(packet: &mut Vec<u8>, name: &str) {
+    for label in name.split('.') {
+        packet.push(label.len() as u8);
+        packet.extend(label.as_bytes());
+    }
+    packet.push(0);
+}
+
+/// Returns true if `packet` is a query containing a question for the address of `host_name`.
+fn is_query_for(packet: &[u8], host_name: &str) -> bool {
+    if packet.len() < 12 || packet[2] & 0x80 != 0 {
+        // Too short to be valid, or a response from another responder.
+        return false;
+    }
+    let question_count = u16::from_be_bytes([packet[4], packet[5]]);
+    let mut offset = 12;
+    for _ in 0..question_count {
+        let (name, next) = match read_name(packet, offset) {
+            Some(name) => name,
+            None => return false,
+        };
+        let question_type = match packet.get(next..next + 2) {
+            Some(bytes) => u16::from_be_bytes([bytes[0], bytes[1]]),
+            None => return false,
+        };
+        if name.eq_ignore_ascii_case(host_name)
+            && (question_type == TYPE_A || question_type == TYPE_ANY)
+        {
+            return true;
+        }
+        // Skip the type and class
+        offset = next + 4;
+    }
+    false
+}
+
+/// Read the possibly compressed name starting at `offset`, returning it and the offset just after it.
+fn read_name(packet: &[u8], mut offset: usize) -> Option<(String, usize)> {
+    let mut labels: Vec<String> = vec![];
+    let mut end = None;
+    // Guard against pointer loops in malicious packets.
+    for _ in 0..128 {
+        let len = *packet.get(offset)? as usize;
+        if len == 0 {
+            return Some((labels.join("."), end.unwrap_or(offset + 1)));
+        } else if len & 0xc0 == 0xc0 {
+            let pointer = (len & 0x3f) << 8 | *packet.get(offset + 1)? as usize;
+            end.get_or_insert(offset + 2);
+            offset = pointer;
+        } else {
+            let label = packet.get(offset + 1..offset + 1 + len)?;
+            labels.push(String::from_utf8_lossy(label).into_owned());
+            offset += 1 + len;
+        }
+    }
+    None
+}
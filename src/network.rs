@@ -0,0 +1,54 @@
+//! Working out the urls the dev server can be reached at.
+
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// The urls the dev server listening on `host:port` can be reached at.
+///
+/// The first url is the one that should be used from this machine.
+/// When listening on all interfaces, urls for every non-loopback address follow it, so that other devices on the LAN know what to visit.
+pub(crate) fn served_urls(host: &str, port: u16) -> Vec<String> {
+    let url = |host: &str| format!("http://{}:{}", url_host(host), port);
+    let include_ipv6 = match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) if ip == Ipv4Addr::UNSPECIFIED => false,
+        Ok(IpAddr::V6(ip)) if ip == Ipv6Addr::UNSPECIFIED => true,
+        _ => return vec![url(host)],
+    };
+
+    let mut urls = vec![url("localhost")];
+    // If we fail to list the interfaces the user can still figure out the address themselves, so just carry on.
+    for interface in if_addrs::get_if_addrs().unwrap_or_default() {
+        let ip = interface.ip();
+        let reachable = match ip {
+            IpAddr::V4(_) => true,
+            // Link local addresses need a zone id to be visited which makes them useless to print.
+            IpAddr::V6(ip) => include_ipv6 && ip.segments()[0] & 0xffc0 != 0xfe80,
+        };
+        if !interface.is_loopback() && reachable {
+            urls.push(url(&ip.to_string()));
+        }
+    }
+    urls
+}
+
+/// Format a host for use in a url, IPv6 addresses need to be wrapped in brackets.
+pub(crate) fn url_host(host: &str) -> String {
+    if host.parse::<Ipv6Addr>().is_ok() {
+        format!("[{}]", host)
+    } else {
+        host.to_string()
+    }
+}
+
+/// Render `url` as a QR code made of unicode block characters, so it can be scanned from the terminal by a phone.
+pub(crate) fn qr_code(url: &str) -> String {
+    QrCode::new(url)
+        .unwrap()
+        .render::<Dense1x2>()
+        // Light modules are drawn as blocks since most terminals have a dark background.
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .quiet_zone(true)
+        .build()
+}
@@ -0,0 +1,49 @@
+//! Desktop notifications, so that the user can switch away during a slow build and know when to look back.
+
+use std::process::Command;
+
+/// Show a desktop notification with `title` and `body` using the tools that ship with each platform.
+pub(crate) fn notify(title: &str, body: &str) {
+    let mut command = if cfg!(target_os = "windows") {
+        // Windows has no notification command, so show a balloon tip from the tray via powershell instead.
+        let script = format!(
+            "Add-Type -AssemblyName System.Windows.Forms; \
+             $icon = New-Object System.Windows.Forms.NotifyIcon; \
+             $icon.Icon = [System.Drawing.SystemIcons]::Information; \
+             $icon.Visible = $true; \
+             $icon.ShowBalloonTip(5000, '{}', '{}', 'None'); \
+             Start-Sleep -Seconds 5; \
+             $icon.Dispose()",
+            powershell_escape(title),
+            powershell_escape(body)
+        );
+        let mut command = Command::new("powershell");
+        command.args(["-NoProfile", "-Command", &script]);
+        command
+    } else if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification \"{}\" with title \"{}\"",
+            applescript_escape(body),
+            applescript_escape(title)
+        );
+        let mut command = Command::new("osascript");
+        command.args(["-e", &script]);
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.args([title, body]);
+        command
+    };
+    // Dont wait on the notification since some of the tools block until it is dismissed.
+    if let Err(err) = command.spawn() {
+        println!("Failed to show a desktop notification: {}", err);
+    }
+}
+
+fn powershell_escape(text: &str) -> String {
+    text.replace('\'', "''")
+}
+
+fn applescript_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,151 @@
+//! Picking the package or example to run from a list when no NAME is given in a terminal.
+//!
+//! Unix terminals are switched to raw mode so that the list can be navigated with the arrow keys,
+//! elsewhere the entries are numbered and the number of the chosen one is read from stdin instead.
+
+use crate::color;
+use crate::gallery::Entry;
+use std::io::Write;
+
+/// How many entries are shown at once, the list scrolls when there are more.
+#[cfg(unix)]
+const VISIBLE: usize = 10;
+
+/// Whether a user is there to pick, rather than e.g. a CI job or an editor plugin running us.
+pub(crate) fn is_interactive() -> bool {
+    stdin_is_terminal()
+}
+
+/// Asks the user to pick one of `entries`, returning the name and whether it is an example, or None if they cancelled.
+pub(crate) fn pick(entries: &[Entry]) -> Result<Option<(String, bool)>, String> {
+    if entries.is_empty() {
+        return Ok(None);
+    }
+    println!("No NAME given, pick a package or example to run:");
+    let picked = pick_index(entries).map_err(|err| format!("Failed to read the pick: {}", err))?;
+    Ok(picked.map(|i| (entries[i].name.clone(), entries[i].example)))
+}
+
+fn describe(entry: &Entry) -> String {
+    let kind = if entry.example { "example" } else { "package" };
+    let mut line = format!("{} {}", entry.name, color::paint(color::DIM, kind));
+    if !entry.description.is_empty() {
+        line.push_str(&format!(" - {}", entry.description));
+    }
+    line
+}
+
+#[cfg(unix)]
+fn pick_index(entries: &[Entry]) -> std::io::Result<Option<usize>> {
+    use std::io::Read;
+
+    let _raw = RawMode::enable()?;
+    let mut stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+    let visible = entries.len().min(VISIBLE);
+    let mut selected = 0;
+    let mut offset = 0;
+    let mut drawn = false;
+    loop {
+        if selected < offset {
+            offset = selected;
+        } else if selected >= offset + visible {
+            offset = selected + 1 - visible;
+        }
+        if drawn {
+            // Move back up to redraw the list in place.
+            write!(stdout, "\x1b[{}A", visible)?;
+        }
+        for (i, entry) in entries.iter().enumerate().skip(offset).take(visible) {
+            let line = if i == selected {
+                format!("{} {}", color::paint(color::CYAN, ">"), describe(entry))
+            } else {
+                format!("  {}", describe(entry))
+            };
+            write!(stdout, "\r\x1b[2K{}\n", line)?;
+        }
+        stdout.flush()?;
+        drawn = true;
+
+        let mut key = [0; 3];
+        let len = stdin.read(&mut key)?;
+        match &key[..len] {
+            b"\x1b[A" | b"k" => selected = selected.saturating_sub(1),
+            b"\x1b[B" | b"j" => selected = (selected + 1).min(entries.len() - 1),
+            b"\r" | b"\n" => return Ok(Some(selected)),
+            // Escape, q, ctrl-c and ctrl-d cancel, ctrl-c is read as a key since raw mode disables signals.
+            b"\x1b" | b"q" | b"\x03" | b"\x04" | b"" => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn pick_index(entries: &[Entry]) -> std::io::Result<Option<usize>> {
+    for (i, entry) in entries.iter().enumerate() {
+        println!("{:>3}) {}", i + 1, describe(entry));
+    }
+    loop {
+        print!("Number (empty to cancel): ");
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(None);
+        }
+        match line.parse::<usize>() {
+            Ok(n) if n >= 1 && n <= entries.len() => return Ok(Some(n - 1)),
+            _ => println!("Expected a number from 1 to {}", entries.len()),
+        }
+    }
+}
+
+/// Puts the terminal in raw mode until dropped, so that keys are read as they are pressed without being echoed.
+#[cfg(unix)]
+struct RawMode {
+    original: libc::termios,
+}
+
+#[cfg(unix)]
+impl RawMode {
+    fn enable() -> std::io::Result<Self> {
+        // Safe because termios is plain data that tcgetattr fills in, and only stdin is modified.
+        unsafe {
+            let mut original = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            let mut raw = original;
+            libc::cfmakeraw(&mut raw);
+            // Keep translating \n to \r\n when printing.
+            raw.c_oflag = original.c_oflag;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(RawMode { original })
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        // Safe because it restores the settings read by tcgetattr.
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn stdin_is_terminal() -> bool {
+    // Safe because isatty only inspects the file descriptor.
+    unsafe { libc::isatty(libc::STDIN_FILENO) == 1 }
+}
+
+#[cfg(not(unix))]
+fn stdin_is_terminal() -> bool {
+    // Without a way to query the console on our MSRV, only assume a user is there in terminals we know of.
+    std::env::var_os("WT_SESSION").is_some() || std::env::var_os("TERM").is_some()
+}
@@ -0,0 +1,114 @@
+//! Turns the generated files into a package laid out like the `pkg` directory of wasm-pack, for `--pkg`.
+//!
+//! This lets the output be published to npm or consumed by tooling written for wasm-pack,
+//! so the `package.json` follows what wasm-pack writes for its `web` and `bundler` targets.
+
+use crate::{Error, PkgTarget};
+use serde_json::{json, Map, Value};
+use std::path::Path;
+
+/// Writes the `package.json` for `name` in `dir` and copies the readme and license files of the package next to it.
+///
+/// `package` is the package as described by `cargo metadata`, which the name, version and other details are taken from.
+pub(crate) fn write_package(
+    dir: &Path,
+    name: &str,
+    target: PkgTarget,
+    package: &Value,
+) -> Result<(), Error> {
+    let package_dir = package["manifest_path"]
+        .as_str()
+        .and_then(|x| Path::new(x).parent());
+    if let Some(package_dir) = package_dir {
+        copy_readme_and_licenses(package_dir, package, dir)?;
+    }
+
+    let mut files = vec![];
+    let entries =
+        std::fs::read_dir(dir).map_err(Error::io(format!("Failed to read {}", dir.display())))?;
+    for entry in entries {
+        let entry = entry.map_err(Error::io(format!("Failed to read {}", dir.display())))?;
+        let file = entry.file_name().to_string_lossy().into_owned();
+        if file != "package.json" {
+            files.push(file);
+        }
+    }
+    files.sort();
+
+    let js = format!("{}.js", name);
+    let mut package_json = Map::new();
+    package_json.insert("name".to_string(), json!(name));
+    package_json.insert("type".to_string(), json!("module"));
+    for key in ["version", "description", "license", "homepage"] {
+        if let Some(value) = package[key].as_str() {
+            package_json.insert(key.to_string(), json!(value));
+        }
+    }
+    if let Some(authors) = package["authors"].as_array().filter(|x| !x.is_empty()) {
+        package_json.insert("collaborators".to_string(), json!(authors));
+    }
+    if let Some(repository) = package["repository"].as_str() {
+        package_json.insert(
+            "repository".to_string(),
+            json!({ "type": "git", "url": repository }),
+        );
+    }
+    if let Some(keywords) = package["keywords"].as_array().filter(|x| !x.is_empty()) {
+        package_json.insert("keywords".to_string(), json!(keywords));
+    }
+    package_json.insert("files".to_string(), json!(files));
+    package_json.insert("types".to_string(), json!(format!("{}.d.ts", name)));
+    match target {
+        PkgTarget::Web => {
+            package_json.insert("module".to_string(), json!(js));
+            package_json.insert("sideEffects".to_string(), json!(["./snippets/*"]));
+        }
+        // Bundlers need to know the entry point imports the wasm, which has side effects, so it cant be left out.
+        PkgTarget::Bundler => {
+            package_json.insert("main".to_string(), json!(js));
+            package_json.insert(
+                "sideEffects".to_string(),
+                json!([format!("./{}", js), "./snippets/*"]),
+            );
+        }
+    }
+
+    let path = dir.join("package.json");
+    let contents = serde_json::to_string_pretty(&Value::Object(package_json)).unwrap();
+    std::fs::write(&path, contents + "\n")
+        .map_err(Error::io(format!("Failed to write {}", path.display())))
+}
+
+/// Copies the readme, either the one set in the manifest or a `README.md`, and any `LICENSE*` files, as wasm-pack does.
+fn copy_readme_and_licenses(package_dir: &Path, package: &Value, dir: &Path) -> Result<(), Error> {
+    // npm only shows a readme named README, whatever it is called in the package.
+    let mut copies = vec![];
+    match package["readme"].as_str() {
+        Some(readme) => copies.push((package_dir.join(readme), "README.md".into())),
+        None if package_dir.join("README.md").is_file() => {
+            copies.push((package_dir.join("README.md"), "README.md".into()))
+        }
+        None => {}
+    }
+    if let Ok(entries) = std::fs::read_dir(package_dir) {
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let is_license = file_name
+                .to_string_lossy()
+                .to_ascii_uppercase()
+                .starts_with("LICENSE");
+            if is_license && entry.path().is_file() {
+                copies.push((entry.path(), file_name));
+            }
+        }
+    }
+    for (source, file_name) in copies {
+        let dest = dir.join(file_name);
+        std::fs::copy(&source, &dest).map_err(Error::io(format!(
+            "Failed to copy {} to {}",
+            source.display(),
+            dest.display()
+        )))?;
+    }
+    Ok(())
+}
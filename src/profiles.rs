@@ -0,0 +1,92 @@
+//! Generates a page comparing the builds of each cargo profile side by side, for `--profiles`.
+//!
+//! Each profile is built into its own directory next to this page, so it is also served on its own under `/<profile>/`,
+//! which makes it quick to switch between them when something only breaks with optimizations.
+
+use crate::server::escape_html;
+use crate::{report, BuildOutput, Error};
+use std::path::Path;
+
+/// The profiles that can be built, since only `--release` is passed on to cargo.
+const PROFILES: &[&str] = &["debug", "release"];
+
+/// Parses the comma separated list of profiles given to `--profiles`.
+pub(crate) fn parse(list: &str) -> Result<Vec<&'static str>, String> {
+    let mut profiles = vec![];
+    for name in list.split(',').map(|x| x.trim()) {
+        let profile = PROFILES.iter().find(|x| **x == name).ok_or_else(|| {
+            format!(
+                "Invalid profile {:?} in --profiles, expected 'debug' or 'release'",
+                name
+            )
+        })?;
+        if profiles.contains(profile) {
+            return Err(format!(
+                "The profile {:?} is given to --profiles twice",
+                name
+            ));
+        }
+        profiles.push(*profile);
+    }
+    Ok(profiles)
+}
+
+/// Write an index.html to `dir` comparing the `outputs` built into its subdirectories, and showing each of them in an iframe.
+pub(crate) fn write(dir: &Path, name: &str, outputs: &[BuildOutput]) -> Result<(), Error> {
+    let size = |path: &Path| match report::file_size(path) {
+        Some(size) => report::format_size(size),
+        None => "-".to_string(),
+    };
+    let mut rows = vec![(
+        "Profile",
+        outputs
+            .iter()
+            .map(|x| format!("<a href=\"./{0}/\">{0}</a>", x.profile()))
+            .collect::<Vec<_>>(),
+    )];
+    rows.push((
+        "wasm",
+        outputs.iter().map(|x| size(x.wasm_path())).collect(),
+    ));
+    rows.push(("js", outputs.iter().map(|x| size(x.js_path())).collect()));
+    rows.push((
+        "Build time",
+        outputs
+            .iter()
+            .map(|x| format!("{:.1?}", x.duration()))
+            .collect(),
+    ));
+    let mut table = String::new();
+    for (i, (heading, cells)) in rows.iter().enumerate() {
+        let cell = if i == 0 { "th" } else { "td" };
+        table.push_str(&format!("    <tr><th>{}</th>", heading));
+        for value in cells {
+            table.push_str(&format!("<{0}>{1}</{0}>", cell, value));
+        }
+        table.push_str("</tr>\n");
+    }
+
+    let mut builds = String::new();
+    for output in outputs {
+        builds.push_str(&format!(
+            "    <div class=\"build\">\n      <a href=\"./{0}/\">{0}</a>\n      <iframe src=\"./{0}/\" title=\"{0}\" allowfullscreen></iframe>\n    </div>\n",
+            output.profile()
+        ));
+    }
+
+    let title = format!(
+        "{} {}",
+        escape_html(name),
+        outputs
+            .iter()
+            .map(|x| x.profile())
+            .collect::<Vec<_>>()
+            .join(" vs ")
+    );
+    let html = include_str!("profiles.template.html")
+        .replace("{{title}}", &title)
+        .replace("{{table}}", table.trim_end())
+        .replace("{{builds}}", builds.trim_end());
+    let path = dir.join("index.html");
+    std::fs::write(&path, html).map_err(Error::io(format!("Failed to write {}", path.display())))
+}
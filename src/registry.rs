@@ -0,0 +1,95 @@
+//! Keeps track of the dev servers running in a workspace, so that starting another one can point at them
+//! instead of leaving the user to work out why a port is taken.
+//!
+//! Each running dev server writes a small JSON file named after its process id, which it removes on shutdown.
+//! Files left behind by a dev server that was killed are removed once it can no longer be connected to.
+
+use serde_json::{json, Value};
+use std::net::{SocketAddr, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Counts the dev servers registered by this process, which can run several via `RunWasm::serve_in_background`.
+static REGISTERED: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) struct RunningServer {
+    pub pid: u32,
+    pub name: String,
+    pub url: String,
+    /// None when listening on a unix socket.
+    pub port: Option<u16>,
+}
+
+/// Removes the servers entry from the registry when dropped.
+pub(crate) struct Registration {
+    path: PathBuf,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+/// Records that this process is serving `name` on `url`, reachable at `address` which is either a socket address or `unix:PATH`.
+///
+/// Failing to register only makes the reports of other dev servers less helpful, so errors are ignored.
+pub(crate) fn register(dir: &Path, name: &str, url: &str, address: &str) -> Option<Registration> {
+    std::fs::create_dir_all(dir).ok()?;
+    let pid = std::process::id();
+    let n = REGISTERED.fetch_add(1, Ordering::Relaxed);
+    let path = dir.join(format!("{}-{}.json", pid, n));
+    let entry = json!({
+        "pid": pid,
+        "name": name,
+        "url": url,
+        "address": address,
+    });
+    std::fs::write(&path, entry.to_string()).ok()?;
+    Some(Registration { path })
+}
+
+/// The other dev servers that are currently running.
+pub(crate) fn running(dir: &Path) -> Vec<RunningServer> {
+    let mut servers = vec![];
+    for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let entry: Value = match std::fs::read(&path)
+            .ok()
+            .and_then(|x| serde_json::from_slice(&x).ok())
+        {
+            Some(entry) => entry,
+            None => continue,
+        };
+        let pid = entry["pid"].as_u64().unwrap_or(0) as u32;
+        let address = entry["address"].as_str().unwrap_or_default();
+        if pid == std::process::id() {
+            continue;
+        }
+        if !is_reachable(address) {
+            std::fs::remove_file(&path).ok();
+            continue;
+        }
+        servers.push(RunningServer {
+            pid,
+            name: entry["name"].as_str().unwrap_or_default().to_string(),
+            url: entry["url"].as_str().unwrap_or_default().to_string(),
+            port: address.parse::<SocketAddr>().ok().map(|x| x.port()),
+        });
+    }
+    servers
+}
+
+fn is_reachable(address: &str) -> bool {
+    #[cfg(unix)]
+    if let Some(path) = address.strip_prefix("unix:") {
+        return UnixStream::connect(path).is_ok();
+    }
+    match address.parse::<SocketAddr>() {
+        Ok(addr) => TcpStream::connect_timeout(&addr, Duration::from_millis(200)).is_ok(),
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,150 @@
+//! A summary of how long a build took and how large its output is, for tracking them across commits.
+
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How long each phase of a build took and the sizes of the generated files, from [`crate::BuildOutput::report`].
+///
+/// Can also be written to a file as JSON after every build via [`crate::RunWasm::report`] or `--report <PATH>`.
+#[derive(Clone, Debug)]
+pub struct BuildReport {
+    pub(crate) name: String,
+    pub(crate) profile: &'static str,
+    pub(crate) features: Option<String>,
+    pub(crate) cargo_args: Vec<String>,
+    pub(crate) phases: Vec<(String, Duration)>,
+    pub(crate) artifacts: Vec<ArtifactSize>,
+    pub(crate) duration: Duration,
+}
+
+impl BuildReport {
+    /// The name of the package or example that was built.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The cargo profile that was built, `debug` or `release`.
+    pub fn profile(&self) -> &str {
+        self.profile
+    }
+
+    /// The features that were enabled, comma separated.
+    pub fn features(&self) -> Option<&str> {
+        self.features.as_deref()
+    }
+
+    /// The args cargo was run with, which includes every option that affects the wasm.
+    pub fn cargo_args(&self) -> &[String] {
+        &self.cargo_args
+    }
+
+    /// How long each phase took in the order they ran: `before_build`, each [`crate::BuildStep`] by its name and `after_build`.
+    ///
+    /// While the [`crate::RunWasm::after_build`] hooks run the `after_build` phase is not included yet.
+    pub fn phases(&self) -> &[(String, Duration)] {
+        &self.phases
+    }
+
+    /// How long the phase `name` took, e.g. `cargo` or `bindgen`.
+    pub fn phase(&self, name: &str) -> Option<Duration> {
+        self.phases
+            .iter()
+            .find(|(x, _)| x == name)
+            .map(|(_, duration)| *duration)
+    }
+
+    /// The sizes of the generated files.
+    pub fn artifacts(&self) -> &[ArtifactSize] {
+        &self.artifacts
+    }
+
+    /// How long the whole build took.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// The report as pretty printed JSON, which is what is written by [`crate::RunWasm::report`].
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.to_value()).unwrap()
+    }
+
+    pub(crate) fn to_value(&self) -> Value {
+        let phases: Vec<_> = self
+            .phases
+            .iter()
+            .map(
+                |(name, duration)| json!({ "name": name, "duration_secs": duration.as_secs_f64() }),
+            )
+            .collect();
+        let artifacts: Vec<_> = self
+            .artifacts
+            .iter()
+            .map(|artifact| {
+                json!({
+                    "file": artifact.path.file_name().map(|x| x.to_string_lossy()),
+                    "generated_size": artifact.generated_size,
+                    "size": artifact.size,
+                })
+            })
+            .collect();
+        json!({
+            "name": self.name,
+            "profile": self.profile,
+            "features": self.features,
+            "cargo_args": self.cargo_args,
+            "duration_secs": self.duration.as_secs_f64(),
+            "phases": phases,
+            "artifacts": artifacts,
+        })
+    }
+}
+
+/// The size of a generated file, both as generated and after any post-processing.
+#[derive(Clone, Debug)]
+pub struct ArtifactSize {
+    pub(crate) path: PathBuf,
+    pub(crate) generated_size: Option<u64>,
+    pub(crate) size: Option<u64>,
+}
+
+impl ArtifactSize {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The size in bytes once the step that generated the file finished,
+    /// before later steps and the [`crate::RunWasm::after_build`] hooks had a chance to e.g. optimize it.
+    pub fn generated_size(&self) -> Option<u64> {
+        self.generated_size
+    }
+
+    /// The size in bytes at the end of the build, None if the file was not generated.
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+}
+
+pub(crate) fn file_size(path: &Path) -> Option<u64> {
+    std::fs::metadata(path).map(|x| x.len()).ok()
+}
+
+/// The size of the file at `path` if it was written since `since`, so that the output of a previous build is not mistaken for this one.
+pub(crate) fn file_size_since(path: &Path, since: SystemTime) -> Option<u64> {
+    let metadata = std::fs::metadata(path).ok()?;
+    match metadata.modified() {
+        Ok(modified) if modified >= since => Some(metadata.len()),
+        _ => None,
+    }
+}
+
+/// `bytes` in the largest unit it is at least one of, e.g. `1.5 MiB`.
+pub(crate) fn format_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
@@ -0,0 +1,1312 @@
+//! A tiny static file server used to serve the generated wasm, js and index.html.
+//!
+//! We used to use devserver_lib for this, but it gives us no control over the responses it sends.
+//!
+//! This is intentionally minimal: one thread per connection, one request per connection.
+//!
+//! Without the `server` feature only the types used to configure the dev server are compiled, along with what they happen to use.
+#![cfg_attr(not(feature = "server"), allow(dead_code, unused_imports))]
+
+use crate::{color, Error, Verbosity};
+use access_log::AccessLog;
+use compression::CompressionCache;
+use dashboard::Dashboard;
+use events::Events;
+use proxy::Proxy;
+use reload::Reloader;
+pub(crate) use socket::Listener;
+use socket::{ConnectAddress, Connection};
+use status::BuildStatus;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use throttle::Throttle;
+
+pub(crate) mod access_log;
+mod auth;
+mod cache;
+mod compression;
+mod conditional;
+mod console;
+mod coverage;
+pub(crate) mod dashboard;
+pub(crate) mod events;
+mod headless;
+mod listing;
+mod not_found;
+mod profile;
+pub(crate) mod proxy;
+mod range;
+pub(crate) mod reload;
+mod socket;
+pub(crate) mod status;
+pub(crate) mod throttle;
+mod websocket;
+
+pub(crate) use not_found::escape_html;
+
+/// Can answer a request instead of the dev server, see [`crate::RunWasm::on_request`].
+pub(crate) type RequestHook = Box<dyn Fn(&Request) -> Option<Response> + Send + Sync>;
+/// Can modify a response before it is sent, see [`crate::RunWasm::on_response`].
+pub(crate) type ResponseHook = Box<dyn Fn(&Request, &mut Response) + Send + Sync>;
+
+/// An extra header attached to responses, optionally only for request paths matching a glob.
+#[derive(Clone, Debug)]
+pub(crate) struct ExtraHeader {
+    pub path_glob: Option<String>,
+    pub name: String,
+    pub value: String,
+}
+
+impl ExtraHeader {
+    /// Parses a header in the `Name: value` form used on the command line.
+    pub fn parse(header: &str) -> Result<Self, String> {
+        match header.split_once(':') {
+            Some((name, value))
+                if is_valid_header_name(name.trim()) && !value.contains(&['\r', '\n'][..]) =>
+            {
+                Ok(ExtraHeader {
+                    path_glob: None,
+                    name: name.trim().to_string(),
+                    value: value.trim().to_string(),
+                })
+            }
+            _ => Err(format!(
+                "Invalid header {:?}, expected the form \"Name: value\"",
+                header
+            )),
+        }
+    }
+
+    fn applies_to(&self, path: &str) -> bool {
+        match &self.path_glob {
+            Some(glob) => glob_matches(glob, path),
+            None => true,
+        }
+    }
+
+    /// Builds a `Permissions-Policy` header that allows or denies each feature, e.g. `gamepad=*, fullscreen=()`.
+    ///
+    /// Allowed features are allowed for every origin, so that they also work when the page is embedded in a cross origin iframe.
+    /// When a feature is given more than once the last one wins.
+    pub fn permissions_policy(permissions: &[(String, bool)]) -> Self {
+        let mut directives: Vec<(&str, bool)> = vec![];
+        for (feature, allowed) in permissions {
+            directives.retain(|(x, _)| x != feature);
+            directives.push((feature, *allowed));
+        }
+        let value: Vec<String> = directives
+            .iter()
+            .map(|(feature, allowed)| format!("{}={}", feature, if *allowed { "*" } else { "()" }))
+            .collect();
+        ExtraHeader {
+            path_glob: None,
+            name: "Permissions-Policy".to_string(),
+            value: value.join(", "),
+        }
+    }
+}
+
+/// Checks that `feature` is a valid Permissions-Policy feature name such as `gamepad` or `xr-spatial-tracking`.
+pub(crate) fn parse_permission_feature(feature: &str) -> Result<String, String> {
+    if !feature.is_empty()
+        && feature
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+    {
+        Ok(feature.to_string())
+    } else {
+        Err(format!(
+            "Invalid permissions policy feature {:?}, expected a name such as \"gamepad\" or \"clipboard-read\"",
+            feature
+        ))
+    }
+}
+
+/// A directory served at a url prefix directly from its original location, rather than being copied into the output.
+#[derive(Clone, Debug)]
+pub(crate) struct Mount {
+    prefix: String,
+    pub dir: PathBuf,
+}
+
+impl Mount {
+    /// Parses a mount in the `/prefix=dir` form used on the command line.
+    pub fn parse(mount: &str) -> Result<Self, String> {
+        match mount.split_once('=') {
+            Some((prefix, dir)) => Mount::new(prefix, Path::new(dir)),
+            None => Err(format!(
+                "Invalid mount {:?}, expected the form \"/prefix=dir\"",
+                mount
+            )),
+        }
+    }
+
+    pub fn new(prefix: &str, dir: &Path) -> Result<Self, String> {
+        if !prefix.starts_with('/') {
+            return Err(format!(
+                "Invalid mount prefix {:?}, it must start with a `/`",
+                prefix
+            ));
+        }
+        Ok(Mount {
+            prefix: prefix.trim_end_matches('/').to_string(),
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    /// Returns the part of `path` within the mount, or None if `path` is not below the prefix.
+    fn strip<'a>(&self, path: &'a str) -> Option<&'a str> {
+        match path.strip_prefix(&self.prefix) {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => Some(rest),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a content type for an extension in the `EXT=TYPE` form used on the command line, e.g. `ktx2=image/ktx2`.
+pub(crate) fn parse_mime_type(mime_type: &str) -> Result<(String, String), String> {
+    match mime_type.split_once('=') {
+        Some((extension, content_type)) => new_mime_type(extension, content_type),
+        None => Err(format!(
+            "Invalid mime type {:?}, expected the form \"EXT=TYPE\" e.g. \"ktx2=image/ktx2\"",
+            mime_type
+        )),
+    }
+}
+
+pub(crate) fn new_mime_type(
+    extension: &str,
+    content_type: &str,
+) -> Result<(String, String), String> {
+    let extension = extension.trim().trim_start_matches('.');
+    let content_type = content_type.trim();
+    if extension.is_empty() || extension.contains(&['.', '/', '\\'][..]) {
+        return Err(format!(
+            "Invalid mime type extension {:?}, expected e.g. \"ktx2\"",
+            extension
+        ));
+    }
+    if !content_type.contains('/') || content_type.contains(&['\r', '\n'][..]) {
+        return Err(format!(
+            "Invalid content type {:?} for the extension {:?}, expected e.g. \"image/ktx2\"",
+            content_type, extension
+        ));
+    }
+    Ok((extension.to_string(), content_type.to_string()))
+}
+
+pub(crate) fn is_valid_header_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+pub(crate) struct ServerConfig {
+    pub headers: Vec<ExtraHeader>,
+    /// Origins allowed to make cross origin requests, `*` allows any origin.
+    pub cors_origins: Vec<String>,
+    pub proxies: Vec<Proxy>,
+    pub mounts: Vec<Mount>,
+    pub request_hooks: Vec<RequestHook>,
+    pub response_hooks: Vec<ResponseHook>,
+    /// When set, served html pages reload whenever the reloader is triggered.
+    pub reloader: Option<Arc<Reloader>>,
+    /// Serve the root index.html for missing paths, so that client side routers can handle them.
+    pub spa: bool,
+    /// Each directory in the root is a separate package with its own index.html.
+    pub serve_all: bool,
+    /// Content types for file extensions, overriding the built in ones, later entries take priority.
+    pub mime_types: Vec<(String, String)>,
+    /// Render an index of directories that dont contain an index.html.
+    pub directory_listing: bool,
+    /// Compress responses with gzip or brotli when the client supports it.
+    pub compression: bool,
+    pub verbosity: Verbosity,
+    /// Forward console output and uncaught errors from served pages to the terminal.
+    pub console: bool,
+    /// Record console output, uncaught errors and when the wasm is loaded in served pages, for the headless browser test.
+    pub headless_test: bool,
+    /// Mark when the wasm is fetched, instantiated and first drawn in served pages, and print how long each took.
+    pub profile_startup: bool,
+    /// Collect the coverage of the wasm from served pages into this directory.
+    pub coverage_dir: Option<PathBuf>,
+    /// The `Cache-Control` header sent for served files, unless overridden by an extra header.
+    pub cache_control: String,
+    /// Tell the browser to cache files with a content hash in their name forever.
+    pub immutable: bool,
+    /// Require requests to authenticate with these `user:password` credentials.
+    pub auth: Option<String>,
+    /// Reported by the status endpoint.
+    pub build: BuildStatus,
+    /// Streamed to clients of the events endpoint.
+    pub events: Arc<Events>,
+    /// When the dev server started, for reporting its uptime.
+    pub started: Instant,
+    /// New connections are not accepted while this many are open.
+    pub max_connections: Option<usize>,
+    /// How long to wait for the request on a new connection, or for the rest of it, before closing the connection.
+    pub read_timeout: Duration,
+    /// Simulate a slow network by delaying and rate limiting responses.
+    pub throttle: Option<Throttle>,
+    /// Requests are logged to this file.
+    pub access_log: Option<AccessLog>,
+    /// When set the terminal shows a dashboard, which log lines are shown on instead of being printed.
+    pub dashboard: Option<Dashboard>,
+}
+
+impl ServerConfig {
+    /// Print a line, or show it on the dashboard if there is one.
+    fn log(&self, line: String) {
+        match &self.dashboard {
+            Some(dashboard) => dashboard.log(line),
+            None => println!("{}", line),
+        }
+    }
+}
+
+/// Listen on `host:port`.
+/// If the port is already in use and `strict_port` is false, the following ports are tried instead.
+/// `reuse_address` overrides the platforms default for `SO_REUSEADDR` when set.
+#[cfg(feature = "server")]
+pub(crate) fn bind(
+    host: &str,
+    port: u16,
+    strict_port: bool,
+    reuse_address: Option<bool>,
+) -> Result<TcpListener, Error> {
+    // Give up eventually, if this many ports are in use something else is probably going wrong.
+    let last_port = port.saturating_add(100);
+    let mut try_port = port;
+    loop {
+        match socket::bind_tcp(host, try_port, reuse_address) {
+            Ok(listener) => return Ok(listener),
+            Err(err) if err.kind() == io::ErrorKind::AddrInUse => {
+                if strict_port {
+                    return Err(Error::PortInUse(format!(
+                        "Port {} is already in use on {}, use --port to pick another port or remove --strict-port to automatically use the next free port",
+                        port, host
+                    )));
+                }
+                if try_port == last_port {
+                    return Err(Error::PortInUse(format!(
+                        "Ports {} to {} are all already in use on {}, use --port to pick another port",
+                        port, last_port, host
+                    )));
+                }
+                try_port += 1;
+            }
+            Err(err) => {
+                return Err(Error::Io {
+                    context: format!("Failed to listen on {}:{}", host, try_port),
+                    source: err,
+                })
+            }
+        }
+    }
+}
+
+/// Listen on a unix domain socket at `path`, replacing any stale socket left behind by a previous run.
+#[cfg(unix)]
+pub(crate) fn bind_unix(path: &Path) -> Result<Listener, Error> {
+    use std::os::unix::fs::FileTypeExt;
+
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(Error::InvalidArgs(format!(
+                "Cannot listen on unix:{}, a file that is not a socket already exists there",
+                path.display()
+            )));
+        }
+        if UnixStream::connect(path).is_ok() {
+            return Err(Error::PortInUse(format!(
+                "Cannot listen on unix:{}, something is already listening on it",
+                path.display()
+            )));
+        }
+        std::fs::remove_file(path).ok();
+    }
+    UnixListener::bind(path)
+        .map(|listener| Listener::Unix(listener, path.to_path_buf()))
+        .map_err(Error::io(format!(
+            "Failed to listen on unix:{}",
+            path.display()
+        )))
+}
+
+/// Allows stopping [`serve`] from another thread, e.g. a ctrl-c handler.
+pub(crate) struct Shutdown {
+    requested: AtomicBool,
+    /// Connecting to this address wakes up the thread blocked waiting for new connections.
+    wake_address: ConnectAddress,
+}
+
+impl Shutdown {
+    pub fn new(listener: &Listener) -> Self {
+        Shutdown {
+            requested: AtomicBool::new(false),
+            wake_address: listener.connect_address(),
+        }
+    }
+
+    pub fn trigger(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+        self.wake_address.connect().ok();
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+}
+
+/// Serve the contents of `root` to connections from `listener` until `shutdown` is triggered.
+///
+/// Before returning, requests that are in progress are given some time to finish.
+/// Long lived connections such as reload event streams are not waited on.
+/// A unix domain socket is removed once the server stops.
+#[cfg(feature = "server")]
+pub(crate) fn serve(listener: Listener, root: &Path, config: ServerConfig, shutdown: &Shutdown) {
+    let root = Arc::new(root.to_path_buf());
+    let config = Arc::new(config);
+    let compression_cache = Arc::new(CompressionCache::default());
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let connections = Arc::new(ConnectionCount::default());
+    if config.dashboard.is_some() {
+        Dashboard::spawn(config.clone());
+    }
+    loop {
+        if let Some(max) = config.max_connections {
+            // Connections that cant be accepted yet wait in the OS backlog.
+            connections.wait_below(max, shutdown);
+        }
+        let stream = match listener.accept() {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+        if shutdown.is_requested() {
+            break;
+        }
+        let root = root.clone();
+        let config = config.clone();
+        let compression_cache = compression_cache.clone();
+        let in_flight = in_flight.clone();
+        let connection = ConnectionGuard::new(connections.clone());
+        thread::spawn(move || {
+            let _connection = connection;
+            // Errors here are just clients going away mid request, nothing useful to report.
+            handle_client(stream, &root, &config, &compression_cache, &in_flight).ok();
+        });
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while in_flight.load(Ordering::SeqCst) > 0 && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(10));
+    }
+    if let Some(dashboard) = &config.dashboard {
+        dashboard.stop();
+    }
+    #[cfg(unix)]
+    if let Listener::Unix(_, path) = &listener {
+        std::fs::remove_file(path).ok();
+    }
+}
+
+/// Counts the open connections so that they can be limited.
+#[derive(Default)]
+struct ConnectionCount {
+    count: Mutex<usize>,
+    closed: Condvar,
+}
+
+impl ConnectionCount {
+    /// Blocks until fewer than `max` connections are open, or shutdown is requested.
+    fn wait_below(&self, max: usize, shutdown: &Shutdown) {
+        let mut count = self.count.lock().unwrap();
+        while *count >= max && !shutdown.is_requested() {
+            count = self
+                .closed
+                .wait_timeout(count, Duration::from_millis(100))
+                .unwrap()
+                .0;
+        }
+    }
+}
+
+/// Counts a connection as open for as long as it is alive.
+struct ConnectionGuard(Arc<ConnectionCount>);
+
+impl ConnectionGuard {
+    fn new(connections: Arc<ConnectionCount>) -> Self {
+        *connections.count.lock().unwrap() += 1;
+        ConnectionGuard(connections)
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        *self.0.count.lock().unwrap() -= 1;
+        self.0.closed.notify_one();
+    }
+}
+
+/// Counts a request as in flight for as long as it is alive.
+struct InFlightGuard<'a>(&'a AtomicUsize);
+
+impl<'a> InFlightGuard<'a> {
+    fn new(in_flight: &'a AtomicUsize) -> Self {
+        in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard(in_flight)
+    }
+}
+
+impl<'a> Drop for InFlightGuard<'a> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(feature = "server")]
+fn handle_client(
+    mut stream: Connection,
+    root: &Path,
+    config: &ServerConfig,
+    compression_cache: &CompressionCache,
+    in_flight: &AtomicUsize,
+) -> io::Result<()> {
+    let _connection = config
+        .dashboard
+        .as_ref()
+        .map(|dashboard| dashboard.connected(stream.peer_addr()));
+    // The reader is kept for the websocket of the console, since it may have buffered the first messages.
+    let mut reader = BufReader::new(stream.try_clone()?);
+    stream.set_read_timeout(Some(config.read_timeout))?;
+    let mut request = match Request::read(&mut reader)? {
+        Some(Ok(request)) => request,
+        Some(Err(status)) => return Response::new(status).write(&mut stream, false),
+        None => return Ok(()),
+    };
+    handle_request(
+        &mut request,
+        &mut reader,
+        &mut stream,
+        root,
+        config,
+        compression_cache,
+        in_flight,
+    )
+}
+
+#[cfg(feature = "server")]
+fn handle_request(
+    request: &mut Request,
+    reader: &mut BufReader<Connection>,
+    stream: &mut Connection,
+    root: &Path,
+    config: &ServerConfig,
+    compression_cache: &CompressionCache,
+    in_flight: &AtomicUsize,
+) -> io::Result<()> {
+    let start = Instant::now();
+    // Preflight requests never include credentials, so they have to be answered without them.
+    let preflight = request.method == "OPTIONS" && !config.cors_origins.is_empty();
+    let authorized = match &config.auth {
+        Some(credentials) => preflight || auth::is_authorized(request, credentials),
+        None => true,
+    };
+    let proxy = config
+        .proxies
+        .iter()
+        .find(|x| x.matches(&request.path))
+        .filter(|_| authorized);
+    // Read only once authorized, so that a client without credentials cant make the dev server hold a large body in memory.
+    let body_error = if !authorized {
+        None
+    } else if let Some(response) = proxy.and_then(|_| Proxy::unsupported(request)) {
+        Some(response)
+    } else {
+        let uploads = proxy.is_some()
+            || (config.profile_startup && request.path == profile::PROFILE_PATH)
+            || (config.coverage_dir.is_some() && request.path == coverage::COVERAGE_PATH);
+        let limit = if uploads { MAX_UPLOAD_BODY } else { MAX_BODY };
+        request.read_body(reader, limit)?.err().map(Response::new)
+    };
+    let request = &*request;
+    let accepted = authorized && body_error.is_none();
+    let hook_response = if accepted {
+        config.request_hooks.iter().find_map(|hook| hook(request))
+    } else {
+        None
+    };
+    if accepted && hook_response.is_none() {
+        if let Some(proxy) = proxy {
+            proxy.forward(request, stream, config.verbosity, config.auth.is_some())?;
+            tracing::debug!(
+                method = %request.method,
+                target = %request.target,
+                proxied_to = %proxy.target(),
+                duration = ?start.elapsed(),
+                "request"
+            );
+            if let Some(dashboard) = &config.dashboard {
+                dashboard.count_request();
+            }
+            if config.verbosity >= Verbosity::Verbose || config.dashboard.is_some() {
+                config.log(format!(
+                    "{} {} -> proxied to {} ({:.1?})",
+                    request.method,
+                    request.target,
+                    proxy.target(),
+                    start.elapsed()
+                ));
+            }
+            return Ok(());
+        }
+        if config.console && request.path == console::CONSOLE_PATH {
+            // Pages can go a long time without logging anything.
+            stream.set_read_timeout(None)?;
+            return console::receive_messages(request, reader, stream, config);
+        }
+        if let Some(reloader) = &config.reloader {
+            if request.path == reload::RELOAD_PATH {
+                return reloader.stream_events(request, stream);
+            }
+        }
+        if request.path == events::EVENTS_PATH {
+            return config.events.stream_events(request, stream);
+        }
+    }
+
+    let _in_flight = InFlightGuard::new(in_flight);
+    let mut response = if let Some(response) = body_error {
+        response
+    } else if !authorized {
+        auth::unauthorized()
+    } else if let Some(response) = hook_response {
+        response
+    } else if preflight {
+        cors_preflight(request)
+    } else if request.path == status::STATUS_PATH {
+        status::respond(&config.build, config.started)
+    } else if config.profile_startup
+        && request.method == "POST"
+        && request.path == profile::PROFILE_PATH
+    {
+        profile::receive(request, config)
+    } else if config.coverage_dir.is_some()
+        && request.method == "POST"
+        && request.path == coverage::COVERAGE_PATH
+    {
+        coverage::receive(request, config)
+    } else {
+        respond(request, root, config)
+    };
+    add_cors_headers(request, config, &mut response);
+    for header in &config.headers {
+        if header.applies_to(&request.path) {
+            response.header(&header.name, &header.value);
+        }
+    }
+    cache::add_header(
+        &request.path,
+        &config.cache_control,
+        config.immutable,
+        &mut response,
+    );
+    for hook in &config.response_hooks {
+        hook(request, &mut response);
+    }
+    conditional::respond(request, &mut response);
+    if config.compression {
+        compression_cache.compress(request, &mut response);
+    }
+    let head_only = request.method == "HEAD";
+    let result = match &config.throttle {
+        Some(throttle) => {
+            thread::sleep(throttle.latency);
+            response.write(&mut throttle.writer(stream), head_only)
+        }
+        None => response.write(stream, head_only),
+    };
+    tracing::debug!(
+        method = %request.method,
+        target = %request.target,
+        status = response.status,
+        bytes = response.body.len(),
+        duration = ?start.elapsed(),
+        "request"
+    );
+    if let Some(dashboard) = &config.dashboard {
+        dashboard.count_request();
+    }
+    // The dashboard only shows recent lines, so it can show every request without drowning out anything else.
+    if config.verbosity >= Verbosity::Verbose || config.dashboard.is_some() {
+        config.log(format!(
+            "{} {} -> {} ({} bytes, {:.1?})",
+            request.method,
+            request.target,
+            color::paint(
+                color::status_style(response.status),
+                &response.status.to_string()
+            ),
+            response.body.len(),
+            start.elapsed()
+        ));
+    }
+    if let Some(access_log) = &config.access_log {
+        let bytes = if head_only { 0 } else { response.body.len() };
+        access_log.log(
+            &stream.peer_addr(),
+            request,
+            &response,
+            bytes,
+            start.elapsed(),
+        );
+    }
+    result
+}
+
+fn respond(request: &Request, root: &Path, config: &ServerConfig) -> Response {
+    if request.method != "GET" && request.method != "HEAD" {
+        return Response::new(405);
+    }
+
+    let mount = config
+        .mounts
+        .iter()
+        .find_map(|mount| Some((mount.dir.as_path(), mount.strip(&request.path)?)));
+    let resolved = match mount {
+        Some((dir, path)) => resolve_path(dir, path),
+        None => resolve_path(root, &request.path),
+    };
+    let mut file_path = match resolved {
+        Some(file_path) => file_path,
+        None => return Response::new(400),
+    };
+    // Like production static hosts, redirect to the canonical url of a directory so that relative urls in its index.html resolve the same way.
+    if !request.path.ends_with('/') && file_path.is_dir() {
+        let mut location = format!("{}/", request.path);
+        if let Some(query) = request.query() {
+            location.push('?');
+            location.push_str(query);
+        }
+        let mut response = Response::new(301);
+        response.header("Location", &location);
+        return response;
+    }
+    // Paths with an extension are probably meant to be a real file, so still 404 on them to make debugging missing assets easier.
+    let spa_fallback =
+        config.spa && mount.is_none() && !file_path.is_file() && file_path.extension().is_none();
+    // The url path of the directory containing the index.html to fall back to.
+    let mut spa_base = "/".to_string();
+    if spa_fallback {
+        if config.serve_all {
+            if let Some(package) = request.path.split('/').find(|x| !x.is_empty()) {
+                spa_base = format!("/{}/", package);
+            }
+        }
+        file_path = match resolve_path(root, &spa_base) {
+            Some(file_path) => file_path,
+            None => return Response::new(400),
+        };
+    }
+    if config.directory_listing && request.path.ends_with('/') && !file_path.is_file() {
+        if let Some(dir) = file_path.parent().filter(|x| x.is_dir()) {
+            return listing::respond(&request.path, dir);
+        }
+    }
+    let content_type = content_type(&file_path, &config.mime_types);
+    // Range requests for html are not supported since we might modify the contents
+    let is_html = content_type.starts_with("text/html");
+    if !is_html {
+        if let Some(range) = request.header("Range") {
+            if let Some(response) = range::respond(range, &file_path, content_type) {
+                return response;
+            }
+        }
+    }
+    match std::fs::read(&file_path) {
+        Ok(mut contents) => {
+            if spa_fallback {
+                // The page is being served from a nested path, so relative urls need to be resolved from the root instead.
+                let base = format!("\n  <base href=\"{}\" />", spa_base);
+                insert_after_head(&mut contents, base.as_bytes());
+            }
+            if config.reloader.is_some() && is_html {
+                reload::inject_script(&mut contents);
+            }
+            if config.console && is_html {
+                console::inject_script(&mut contents);
+            }
+            if config.headless_test && is_html {
+                headless::inject_script(&mut contents);
+            }
+            if config.profile_startup && is_html {
+                profile::inject_script(&mut contents);
+            }
+            if config.coverage_dir.is_some() && is_html {
+                coverage::inject_script(&mut contents, &config.build.name);
+            }
+            let mut response = Response::new(200);
+            response.header("Content-Type", content_type);
+            if !is_html {
+                response.header("Accept-Ranges", "bytes");
+            }
+            if let Ok(modified) = std::fs::metadata(&file_path).and_then(|x| x.modified()) {
+                response.header("Last-Modified", &conditional::http_date(modified));
+            }
+            response.body = contents;
+            response
+        }
+        Err(_) => {
+            if config.verbosity >= Verbosity::Normal {
+                config.log(format!("Could not find file: {}", file_path.display()));
+            }
+            not_found::respond(request, root)
+        }
+    }
+}
+
+fn cors_preflight(request: &Request) -> Response {
+    let mut response = Response::new(204);
+    response.header("Access-Control-Allow-Methods", "GET, HEAD, OPTIONS");
+    if let Some(headers) = request.header("Access-Control-Request-Headers") {
+        response.header("Access-Control-Allow-Headers", headers);
+    }
+    response.header("Access-Control-Max-Age", "86400");
+    response
+}
+
+fn add_cors_headers(request: &Request, config: &ServerConfig, response: &mut Response) {
+    if config.cors_origins.iter().any(|x| x == "*") {
+        response.header("Access-Control-Allow-Origin", "*");
+    } else if !config.cors_origins.is_empty() {
+        // Only a single origin can be returned, so echo back the requesting origin if its allowed.
+        if let Some(origin) = request.header("Origin") {
+            if config.cors_origins.iter().any(|x| x == origin) {
+                response.header("Access-Control-Allow-Origin", origin);
+            }
+        }
+        response.header("Vary", "Origin");
+    }
+}
+
+pub(crate) fn insert_after_head(html: &mut Vec<u8>, insert: &[u8]) {
+    let head = b"<head>";
+    if let Some(i) = html
+        .windows(head.len())
+        .position(|window| window.eq_ignore_ascii_case(head))
+    {
+        let position = i + head.len();
+        html.splice(position..position, insert.iter().copied());
+    }
+}
+
+/// Map a url path onto a file within `root`, returning None if the path tries to escape `root`.
+fn resolve_path(root: &Path, url_path: &str) -> Option<PathBuf> {
+    let decoded = percent_decode(url_path)?;
+    let mut path = root.to_path_buf();
+    for segment in decoded.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => return None,
+            segment if segment.contains('\\') => return None,
+            // Pushing a drive or root, e.g. `C:` on windows, would replace `root` entirely.
+            segment if !is_normal_segment(segment) => return None,
+            segment => path.push(segment),
+        }
+    }
+    if decoded.ends_with('/') {
+        path.push("index.html");
+    }
+    Some(path)
+}
+
+/// Whether `segment` is a single file or directory name, rather than something that changes which directory is being resolved in.
+fn is_normal_segment(segment: &str) -> bool {
+    let mut components = Path::new(segment).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    )
+}
+
+fn percent_decode(input: &str) -> Option<String> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = input.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Matches `*` against any sequence of characters (including `/`) and `?` against any single character.
+pub(crate) fn glob_matches(glob: &str, input: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let input: Vec<char> = input.chars().collect();
+    let (mut g, mut i) = (0, 0);
+    let mut backtrack = None;
+    while i < input.len() {
+        if g < glob.len() && (glob[g] == '?' || glob[g] == input[i]) {
+            g += 1;
+            i += 1;
+        } else if g < glob.len() && glob[g] == '*' {
+            backtrack = Some((g, i));
+            g += 1;
+        } else if let Some((star_g, star_i)) = backtrack {
+            g = star_g + 1;
+            i = star_i + 1;
+            backtrack = Some((star_g, star_i + 1));
+        } else {
+            return false;
+        }
+    }
+    glob[g..].iter().all(|c| *c == '*')
+}
+
+/// The headers the dev server sends with the file at `path` besides those describing the body,
+/// so that the configs written for static hosts match it, see [`crate::deploy`].
+pub(crate) fn file_headers(
+    path: &str,
+    headers: &[ExtraHeader],
+    mime_types: &[(String, String)],
+    cache_control: &str,
+    immutable: bool,
+) -> Vec<(String, String)> {
+    let mut response = Response::new(200);
+    response.header("Content-Type", content_type(Path::new(path), mime_types));
+    for header in headers {
+        if header.applies_to(path) {
+            response.header(&header.name, &header.value);
+        }
+    }
+    cache::add_header(path, cache_control, immutable, &mut response);
+    response.headers
+}
+
+fn content_type<'a>(path: &Path, mime_types: &'a [(String, String)]) -> &'a str {
+    let extension = path.extension().and_then(|x| x.to_str());
+    if let Some(extension) = extension {
+        if let Some((_, mime_type)) = mime_types
+            .iter()
+            .rev()
+            .find(|(x, _)| x.eq_ignore_ascii_case(extension))
+        {
+            return mime_type;
+        }
+    }
+    match extension {
+        Some("html") | Some("htm") => "text/html; charset=utf-8",
+        Some("js") | Some("mjs") => "text/javascript; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("json") | Some("map") => "application/json",
+        Some("wasm") => "application/wasm",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("xml") => "application/xml",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("ico") => "image/x-icon",
+        Some("webp") => "image/webp",
+        Some("avif") => "image/avif",
+        Some("mp3") => "audio/mpeg",
+        Some("ogg") => "audio/ogg",
+        Some("wav") => "audio/wav",
+        Some("mp4") => "video/mp4",
+        Some("webm") => "video/webm",
+        Some("ttf") => "font/ttf",
+        Some("otf") => "font/otf",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("glb") => "model/gltf-binary",
+        Some("gltf") => "model/gltf+json",
+        Some("ktx2") => "image/ktx2",
+        Some("wgsl") => "text/wgsl; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+/// A request received by the dev server, passed to the hooks registered with [`crate::RunWasm::on_request`].
+pub struct Request {
+    method: String,
+    version: String,
+    /// The request path and query string exactly as sent by the client.
+    target: String,
+    /// The request path with any query string removed, still percent encoded.
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+/// The largest request line and headers accepted, together, browsers send a few KiB at most.
+const MAX_HEAD: u64 = 64 * 1024;
+
+/// The largest request body accepted by default, requests to the dev server itself only ever carry small ones.
+const MAX_BODY: usize = 1024 * 1024;
+
+/// The largest request body accepted for the routes that take uploads: proxied requests and the profile and coverage reports of pages.
+const MAX_UPLOAD_BODY: usize = 256 * 1024 * 1024;
+
+impl Request {
+    /// Reads the request line and headers, the body is left to [`Request::read_body`] so that it is only read once the request is authorized.
+    ///
+    /// Returns None if the client closed the connection without sending a request,
+    /// or 431 to reject the request with when the request line and headers are larger than [`MAX_HEAD`].
+    fn read(reader: &mut impl BufRead) -> io::Result<Option<Result<Request, u16>>> {
+        let mut reader = reader.take(MAX_HEAD);
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line)? == 0 {
+            return Ok(None);
+        }
+
+        let mut headers = vec![];
+        loop {
+            // Only a line cut short by the limit can be missing its line ending once the limit is used up.
+            if reader.limit() == 0 {
+                return Ok(Some(Err(431)));
+            }
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+            }
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let target = parts.next().unwrap_or_default().to_string();
+        let version = parts.next().unwrap_or_default().to_string();
+        let path = match target.split_once('?') {
+            Some((path, _query)) => path,
+            None => &target,
+        }
+        .to_string();
+        Ok(Some(Ok(Request {
+            method,
+            version,
+            target,
+            path,
+            headers,
+            body: vec![],
+        })))
+    }
+
+    /// The length of the body from the `Content-Length` header, or the status to reject the request with when it cant be read:
+    /// 400 for an invalid `Content-Length` and 411 for a `Transfer-Encoding` such as chunked, which isnt supported.
+    fn body_length(&self) -> Result<usize, u16> {
+        if self.header("Transfer-Encoding").is_some() {
+            return Err(411);
+        }
+        let mut lengths = self
+            .headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("Content-Length"))
+            .map(|(_, value)| value.parse::<usize>());
+        match (lengths.next(), lengths.next()) {
+            (None, _) => Ok(0),
+            (Some(Ok(length)), None) => Ok(length),
+            // Several lengths could each be what a proxy in between went by, so the request is ambiguous.
+            _ => Err(400),
+        }
+    }
+
+    /// Reads the body of at most `limit` bytes, or returns the status to reject the request with, see [`Request::body_length`].
+    /// 413 when the body is larger than `limit`, in which case it is left unread.
+    fn read_body(&mut self, reader: &mut impl Read, limit: usize) -> io::Result<Result<(), u16>> {
+        let length = match self.body_length() {
+            Ok(length) if length > limit => return Ok(Err(413)),
+            Ok(length) => length,
+            Err(status) => return Ok(Err(status)),
+        };
+        self.body = vec![0; length];
+        reader.read_exact(&mut self.body)?;
+        Ok(Ok(()))
+    }
+
+    /// The request method, e.g. `GET`.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// The request path without the query string, still percent encoded.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The query string without the leading `?`, if there is one.
+    pub fn query(&self) -> Option<&str> {
+        self.target.split_once('?').map(|(_, query)| query)
+    }
+
+    /// Every header as sent by the client, in order.
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Returns the value of the first header named `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(x, _)| x.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A response sent by the dev server, which can be created or modified by the hooks registered with
+/// [`crate::RunWasm::on_request`] and [`crate::RunWasm::on_response`].
+///
+/// The `Content-Length` and `Connection` headers are added when it is sent.
+pub struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16) -> Self {
+        Response {
+            status,
+            headers: vec![],
+            body: vec![],
+        }
+    }
+
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    pub fn set_status(&mut self, status: u16) {
+        self.status = status;
+    }
+
+    /// Add a header, panics if the name is invalid or the value contains a newline.
+    pub fn header(&mut self, name: &str, value: &str) {
+        if !is_valid_header_name(name) {
+            panic!("{:?} is not a valid header name", name);
+        }
+        if value.contains(&['\r', '\n'][..]) {
+            panic!("The value of the header {:?} contains a newline", name);
+        }
+        self.headers.push((name.to_string(), value.to_string()));
+    }
+
+    /// Remove every header named `name`, ignoring case.
+    pub fn remove_header(&mut self, name: &str) {
+        self.headers.retain(|(x, _)| !x.eq_ignore_ascii_case(name));
+    }
+
+    /// Every header in the order they will be sent.
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    pub fn set_body<B: Into<Vec<u8>>>(&mut self, body: B) {
+        self.body = body.into();
+    }
+
+    fn has_header(&self, name: &str) -> bool {
+        self.headers
+            .iter()
+            .any(|(x, _)| x.eq_ignore_ascii_case(name))
+    }
+
+    fn content_type(&self) -> Option<&str> {
+        self.header_value("Content-Type")
+    }
+
+    /// Returns the value of the first header named `name`, ignoring case.
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(x, _)| x.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn write(&self, stream: &mut impl Write, head_only: bool) -> io::Result<()> {
+        let mut head = format!(
+            "HTTP/1.1 {} {}\r\n",
+            self.status,
+            reason_phrase(self.status)
+        );
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        // A 304 has no body, but its Content-Length would describe the body of the full response.
+        if self.status != 304 {
+            head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        }
+        head.push_str("Connection: close\r\n\r\n");
+        stream.write_all(head.as_bytes())?;
+        if !head_only {
+            stream.write_all(&self.body)?;
+        }
+        stream.flush()
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        206 => "Partial Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        304 => "Not Modified",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Content Too Large",
+        431 => "Request Header Fields Too Large",
+        416 => "Range Not Satisfiable",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        // The reason phrase is purely informational, clients must not rely on it.
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glob_matches, resolve_path, Request, MAX_BODY, MAX_HEAD};
+    use std::io::{Cursor, Read};
+    use std::path::Path;
+
+    fn parse(raw: &str) -> Request {
+        Request::read(&mut Cursor::new(raw.as_bytes()))
+            .unwrap()
+            .unwrap()
+            .ok()
+            .unwrap()
+    }
+
+    #[test]
+    fn parses_requests() {
+        let request = parse(
+            "GET /app/index.html?debug=1 HTTP/1.1\r\nHost: localhost\r\nconnection: Close\r\n\r\n",
+        );
+        assert_eq!(request.method(), "GET");
+        assert_eq!(request.path(), "/app/index.html");
+        assert_eq!(request.query(), Some("debug=1"));
+        assert_eq!(request.header("HOST"), Some("localhost"));
+        assert!(Request::read(&mut Cursor::new(b"")).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_large_heads() {
+        let status = |raw: String| Request::read(&mut Cursor::new(raw)).unwrap().unwrap().err();
+        let header = format!("X-Padding: {}\r\n", "a".repeat(1000));
+        let fits = format!("GET / HTTP/1.1\r\n{}\r\n", header.repeat(60));
+        assert_eq!(status(fits), None);
+        let too_many = format!("GET / HTTP/1.1\r\n{}\r\n", header.repeat(70));
+        assert_eq!(status(too_many), Some(431));
+        let too_long = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_HEAD as usize));
+        assert_eq!(status(too_long), Some(431));
+    }
+
+    #[test]
+    fn reads_the_body_and_leaves_the_next_request() {
+        let mut reader = Cursor::new(&b"POST /x HTTP/1.1\r\nContent-Length: 5\r\n\r\nhelloGET"[..]);
+        let mut request = Request::read(&mut reader).unwrap().unwrap().ok().unwrap();
+        assert_eq!(request.read_body(&mut reader, MAX_BODY).unwrap(), Ok(()));
+        assert_eq!(request.body(), b"hello");
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "GET");
+    }
+
+    #[test]
+    fn rejects_bodies_that_cant_be_read() {
+        let status = |headers: &str, limit| {
+            let raw = format!("POST / HTTP/1.1\r\n{}\r\n", headers);
+            let mut reader = Cursor::new(raw.into_bytes());
+            let mut request = Request::read(&mut reader).unwrap().unwrap().ok().unwrap();
+            request.read_body(&mut reader, limit).unwrap().err()
+        };
+        assert_eq!(status("Content-Length: abc\r\n", MAX_BODY), Some(400));
+        assert_eq!(status("Content-Length: -1\r\n", MAX_BODY), Some(400));
+        assert_eq!(
+            status("Content-Length: 1\r\nContent-Length: 2\r\n", MAX_BODY),
+            Some(400)
+        );
+        assert_eq!(
+            status("Transfer-Encoding: chunked\r\n", MAX_BODY),
+            Some(411)
+        );
+        assert_eq!(status("Content-Length: 10\r\n", 5), Some(413));
+        assert_eq!(status("", MAX_BODY), None);
+    }
+
+    #[test]
+    fn resolves_paths_within_the_root() {
+        let root = Path::new("site");
+        assert_eq!(
+            resolve_path(root, "/app/app%20bg.wasm"),
+            Some(root.join("app").join("app bg.wasm"))
+        );
+        assert_eq!(
+            resolve_path(root, "/app/./"),
+            Some(root.join("app").join("index.html"))
+        );
+        assert_eq!(resolve_path(root, "/../secret"), None);
+        assert_eq!(resolve_path(root, "/app/%2e%2e/%2e%2e/secret"), None);
+        assert_eq!(resolve_path(root, "/app/..%5c..%5csecret"), None);
+        assert_eq!(resolve_path(root, "/app/%zz"), None);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn drives_cant_replace_the_root() {
+        let root = Path::new("site");
+        assert_eq!(resolve_path(root, "/C:/Windows/win.ini"), None);
+        assert_eq!(resolve_path(root, "/app/C:secret"), None);
+    }
+
+    #[test]
+    fn matches_globs() {
+        assert!(glob_matches("*.wasm", "/app/app_bg.wasm"));
+        assert!(glob_matches("/assets/*", "/assets/textures/grass.png"));
+        assert!(glob_matches("/levels/level-?.json", "/levels/level-1.json"));
+        assert!(glob_matches("/*", "/"));
+        assert!(!glob_matches("*.wasm", "/app/app.js"));
+        assert!(!glob_matches("/assets/*", "/app/assets/grass.png"));
+        assert!(!glob_matches(
+            "/levels/level-?.json",
+            "/levels/level-10.json"
+        ));
+    }
+}
@@ -0,0 +1,77 @@
+//! Appends a line per request to a file, so that long running dev servers have a record of who visited.
+
+use super::conditional::http_date;
+use super::{Request, Response};
+use crate::AccessLogFormat;
+use serde_json::json;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub(crate) struct AccessLog {
+    file: Mutex<File>,
+    format: AccessLogFormat,
+}
+
+impl AccessLog {
+    /// Opens `path` for appending, creating it if needed.
+    pub fn open(path: &Path, format: AccessLogFormat) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(AccessLog {
+            file: Mutex::new(file),
+            format,
+        })
+    }
+
+    pub(super) fn log(
+        &self,
+        remote: &str,
+        request: &Request,
+        response: &Response,
+        bytes: usize,
+        duration: Duration,
+    ) {
+        let now = SystemTime::now();
+        let line = match self.format {
+            AccessLogFormat::Common => format!(
+                "{} - - [{}] \"{} {} {}\" {} {}\n",
+                remote,
+                common_log_date(now),
+                request.method,
+                // Quotes are escaped so the request can be told apart from the rest of the line.
+                request.target.replace('\\', "\\\\").replace('"', "\\\""),
+                request.version,
+                response.status,
+                bytes
+            ),
+            AccessLogFormat::Json => {
+                let entry = json!({
+                    "time": now.duration_since(UNIX_EPOCH).map(|x| x.as_secs_f64()).unwrap_or(0.0),
+                    "remote": remote,
+                    "method": request.method,
+                    "target": request.target,
+                    "status": response.status,
+                    "bytes": bytes,
+                    "duration_ms": duration.as_secs_f64() * 1000.0,
+                    "referer": request.header("Referer"),
+                    "user_agent": request.header("User-Agent"),
+                });
+                format!("{}\n", entry)
+            }
+        };
+        // Each line is written in one call so that lines from concurrent requests dont interleave.
+        if let Err(err) = self.file.lock().unwrap().write_all(line.as_bytes()) {
+            println!("Failed to write to the access log: {}", err);
+        }
+    }
+}
+
+/// Formats `time` like `06/Nov/1994:08:49:37 +0000`.
+fn common_log_date(time: SystemTime) -> String {
+    // Rearranged from the HTTP date `Sun, 06 Nov 1994 08:49:37 GMT`
+    let date = http_date(time);
+    let parts: Vec<&str> = date.split(' ').collect();
+    format!("{}/{}/{}:{} +0000", parts[1], parts[2], parts[3], parts[4])
+}
@@ -0,0 +1,29 @@
+//! HTTP basic authentication, so that a dev server exposed to the LAN or a tunnel is not readable by everyone.
+//!
+//! The credentials are sent unencrypted, so this only keeps out casual visitors rather than anyone able to watch the traffic.
+
+use super::websocket::base64;
+use super::{Request, Response};
+
+/// Returns true if the request includes the `user:password` `credentials`.
+pub(super) fn is_authorized(request: &Request, credentials: &str) -> bool {
+    match request.header("Authorization") {
+        Some(authorization) => match authorization.split_once(' ') {
+            Some((scheme, encoded)) if scheme.eq_ignore_ascii_case("Basic") => {
+                encoded.trim() == base64(credentials.as_bytes())
+            }
+            _ => false,
+        },
+        None => false,
+    }
+}
+
+/// Asks the browser to prompt for credentials.
+pub(super) fn unauthorized() -> Response {
+    let mut response = Response::new(401);
+    response.header(
+        "WWW-Authenticate",
+        "Basic realm=\"cargo-run-wasm\", charset=\"UTF-8\"",
+    );
+    response
+}
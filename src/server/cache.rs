@@ -0,0 +1,70 @@
+//! Picks the `Cache-Control` header sent with served files.
+//!
+//! By default everything is revalidated on every request, since a stale cached wasm after a rebuild is very confusing.
+//! Files with a content hash in their name can never go stale, so the browser is told to cache them forever.
+
+use super::Response;
+
+/// Sent for content hashed files unless disabled.
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// The length of the hashes `--dist` puts in file names.
+const HASH_LEN: usize = 16;
+
+pub(super) fn add_header(
+    path: &str,
+    cache_control: &str,
+    immutable: bool,
+    response: &mut Response,
+) {
+    if response.has_header("Cache-Control") {
+        return;
+    }
+    let file_name = path.rsplit('/').next().unwrap_or_default();
+    if immutable && response.status == 200 && is_content_hashed(file_name) {
+        response.header("Cache-Control", IMMUTABLE);
+    } else {
+        response.header("Cache-Control", cache_control);
+    }
+}
+
+/// Returns true if the file name contains a hash as written by `--dist` and `--trunk-dist`, e.g. `app-3f2a9c1b0d4e5f67.js`
+/// or `app-3f2a9c1b0d4e5f67_bg.wasm`, or a longer one such as those of bundlers.
+///
+/// A hash is a `-`, `.` or `_` separated part of at least 16 hex characters, the length of [`crate::dist::hash`].
+/// Shorter parts are far more often dates or build numbers, e.g. `data-20240115.json`, which change contents under the same name.
+fn is_content_hashed(file_name: &str) -> bool {
+    let mut parts = file_name.split(&['-', '.', '_'][..]);
+    // The first part is the name itself rather than a hash.
+    parts.next();
+    parts.any(|part| part.len() >= HASH_LEN && part.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_content_hashed;
+
+    #[test]
+    fn dist_names_are_hashed() {
+        assert!(is_content_hashed("app-3f2a9c1b0d4e5f67.js"));
+        assert!(is_content_hashed("app_bg-3f2a9c1b0d4e5f67.wasm"));
+        assert!(is_content_hashed("app-3f2a9c1b0d4e5f67_bg.wasm"));
+        assert!(is_content_hashed("main.0a1b2c3d4e5f6a7b8c9d.css"));
+    }
+
+    #[test]
+    fn dates_and_build_numbers_are_not_hashed() {
+        assert!(!is_content_hashed("data-20240115.json"));
+        assert!(!is_content_hashed("level.12345678.bin"));
+        assert!(!is_content_hashed("save_2024-01-15.json"));
+        assert!(!is_content_hashed("app-3f2a9c1b.js"));
+    }
+
+    #[test]
+    fn plain_names_are_not_hashed() {
+        assert!(!is_content_hashed("app.js"));
+        assert!(!is_content_hashed("app_bg.wasm"));
+        assert!(!is_content_hashed("3f2a9c1b0d4e5f67.js"));
+        assert!(!is_content_hashed("facade-decadedeadbeefa.png"));
+    }
+}
@@ -0,0 +1,150 @@
+//! Compresses responses according to the encodings accepted by the client.
+
+use super::{Request, Response};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+#[cfg(feature = "server")]
+use std::io::Write;
+use std::sync::Mutex;
+
+/// Responses smaller than this are not worth compressing.
+const MIN_SIZE: usize = 1024;
+
+/// Limits the memory used by the cache, it is cleared when it reaches this many entries.
+const MAX_CACHED: usize = 64;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    #[cfg(feature = "server")]
+    fn compress(self, data: &[u8]) -> Vec<u8> {
+        // Compression levels are chosen to keep compressing multi-MB debug wasm fast,
+        // the files only need to be small enough to not bottleneck on the network.
+        match self {
+            Encoding::Brotli => {
+                let mut output = vec![];
+                {
+                    let mut writer = brotli::CompressorWriter::new(&mut output, 4096, 5, 22);
+                    writer.write_all(data).unwrap();
+                }
+                output
+            }
+            Encoding::Gzip => {
+                let mut writer = flate2::write::GzEncoder::new(vec![], flate2::Compression::new(6));
+                writer.write_all(data).unwrap();
+                writer.finish().unwrap()
+            }
+        }
+    }
+}
+
+/// Caches compressed responses keyed by the hash of their uncompressed contents,
+/// so that unchanged files are only compressed once.
+#[derive(Default)]
+pub(crate) struct CompressionCache {
+    cache: Mutex<HashMap<(u64, Encoding), Vec<u8>>>,
+}
+
+impl CompressionCache {
+    /// Replace the body of the response with a compressed version if the client supports it.
+    #[cfg(feature = "server")]
+    pub(super) fn compress(&self, request: &Request, response: &mut Response) {
+        if response.status != 200
+            || response.body.len() < MIN_SIZE
+            || !matches!(response.content_type(), Some(x) if is_compressible(x))
+            || response.has_header("Content-Encoding")
+        {
+            return;
+        }
+        response.header("Vary", "Accept-Encoding");
+
+        let encoding = match request.header("Accept-Encoding").and_then(choose_encoding) {
+            Some(encoding) => encoding,
+            None => return,
+        };
+        let key = (content_hash(&response.body), encoding);
+        let cached = self.cache.lock().unwrap().get(&key).cloned();
+        response.body = match cached {
+            Some(compressed) => compressed,
+            None => {
+                // Compress without holding the lock so that other requests are not blocked.
+                let compressed = encoding.compress(&response.body);
+                let mut cache = self.cache.lock().unwrap();
+                if cache.len() >= MAX_CACHED {
+                    cache.clear();
+                }
+                cache.insert(key, compressed.clone());
+                compressed
+            }
+        };
+        response.header("Content-Encoding", encoding.name());
+    }
+}
+
+pub(crate) fn content_hash(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn is_compressible(content_type: &str) -> bool {
+    content_type.starts_with("text/")
+        || content_type.starts_with("application/wasm")
+        || content_type.starts_with("application/json")
+        || content_type.starts_with("application/xml")
+        || content_type.starts_with("image/svg+xml")
+        || content_type.starts_with("model/gltf")
+}
+
+/// Pick the best encoding from an `Accept-Encoding` header.
+fn choose_encoding(accept_encoding: &str) -> Option<Encoding> {
+    let accepted = |name: &str| {
+        accept_encoding.split(',').any(|x| {
+            let mut parts = x.split(';');
+            let coding = parts.next().unwrap_or_default().trim();
+            let rejected = parts.any(|param| {
+                matches!(param.trim().split_once('='), Some(("q", q)) if q.trim().parse() == Ok(0.0))
+            });
+            coding.eq_ignore_ascii_case(name) && !rejected
+        })
+    };
+    if accepted("br") {
+        Some(Encoding::Brotli)
+    } else if accepted("gzip") {
+        Some(Encoding::Gzip)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{choose_encoding, Encoding};
+
+    #[test]
+    fn prefers_brotli() {
+        assert!(choose_encoding("gzip, deflate, br") == Some(Encoding::Brotli));
+        assert!(choose_encoding("gzip") == Some(Encoding::Gzip));
+        assert!(choose_encoding("GZIP") == Some(Encoding::Gzip));
+        assert!(choose_encoding("identity").is_none());
+    }
+
+    #[test]
+    fn zero_q_values_reject_an_encoding() {
+        assert!(choose_encoding("br;q=0, gzip") == Some(Encoding::Gzip));
+        assert!(choose_encoding("br; q=0.0, gzip;q=0").is_none());
+        assert!(choose_encoding("br;q=0.5, gzip;q=1") == Some(Encoding::Brotli));
+    }
+}
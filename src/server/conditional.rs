@@ -0,0 +1,83 @@
+//! Answers conditional requests with `304 Not Modified`, so that reloading a page with an unchanged multi-MB wasm is instant.
+
+use super::compression::content_hash;
+use super::{Request, Response};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Adds an `ETag` to a successful response and replaces it with a 304 if the client already has the same contents.
+///
+/// The ETag is weak since the same contents may be sent with different compression.
+pub(super) fn respond(request: &Request, response: &mut Response) {
+    if response.status != 200 || response.has_header("ETag") {
+        return;
+    }
+    let etag = format!("W/\"{:016x}\"", content_hash(&response.body));
+    let not_modified = match request.header("If-None-Match") {
+        Some(if_none_match) => if_none_match
+            .split(',')
+            .any(|x| x.trim() == "*" || x.trim().trim_start_matches("W/") == &etag[2..]),
+        // If-Modified-Since must be ignored when If-None-Match is present.
+        // Browsers send back the exact Last-Modified value, so comparing the strings is enough.
+        None => matches!(
+            (request.header("If-Modified-Since"), response.header_value("Last-Modified")),
+            (Some(since), Some(modified)) if since == modified
+        ),
+    };
+    response.header("ETag", &etag);
+    if not_modified {
+        response.status = 304;
+        response.body.clear();
+    }
+}
+
+/// Formats `time` as an HTTP date, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+pub(super) fn http_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0);
+    let days = secs / 86400;
+    let (hour, minute, second) = (secs % 86400 / 3600, secs % 3600 / 60, secs % 60);
+    // 1970-01-01 was a Thursday
+    let weekday = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"][(days % 7) as usize];
+
+    // Convert days since the epoch into a civil date, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z / 146097;
+    let day_of_era = z % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    let month = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ][(month - 1) as usize];
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        weekday, day, month, year, hour, minute, second
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::http_date;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn formats_http_dates() {
+        // The example from RFC 9110.
+        assert_eq!(
+            http_date(UNIX_EPOCH + Duration::from_secs(784111777)),
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        );
+        assert_eq!(http_date(UNIX_EPOCH), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(
+            http_date(UNIX_EPOCH + Duration::from_secs(951782400)),
+            "Tue, 29 Feb 2000 00:00:00 GMT"
+        );
+    }
+}
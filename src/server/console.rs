@@ -0,0 +1,80 @@
+//! Forwards console output and uncaught errors from the browser to the terminal.
+
+use super::{websocket, Connection, Request, ServerConfig};
+use crate::color;
+use serde_json::Value;
+use std::io::{self, BufReader};
+
+pub(crate) const CONSOLE_PATH: &str = "/__run_wasm/console";
+
+/// Injected into the head of served html pages, so that console methods are wrapped before any other script runs.
+const CONSOLE_SCRIPT: &str = r#"
+  <script>
+    (() => {
+      const protocol = location.protocol === "https:" ? "wss:" : "ws:";
+      const socket = new WebSocket(`${protocol}//${location.host}/__run_wasm/console`);
+      const queue = [];
+      const format = (arg) => {
+        if (typeof arg === "string") return arg;
+        if (arg instanceof Error) return arg.stack || String(arg);
+        try { return JSON.stringify(arg); } catch (e) { return String(arg); }
+      };
+      const send = (level, args) => {
+        const message = JSON.stringify({ level, text: args.map(format).join(" ") });
+        if (socket.readyState === WebSocket.OPEN) socket.send(message);
+        else if (socket.readyState === WebSocket.CONNECTING) queue.push(message);
+      };
+      socket.addEventListener("open", () => queue.splice(0).forEach((message) => socket.send(message)));
+      for (const level of ["debug", "log", "info", "warn", "error"]) {
+        const original = console[level];
+        console[level] = (...args) => {
+          send(level, args);
+          original.apply(console, args);
+        };
+      }
+      window.addEventListener("error", (event) => send("error", [`Uncaught ${event.error ? format(event.error) : event.message}`]));
+      window.addEventListener("unhandledrejection", (event) => send("error", ["Uncaught (in promise)", event.reason]));
+    })();
+  </script>"#;
+
+pub(crate) fn inject_script(html: &mut Vec<u8>) {
+    super::insert_after_head(html, CONSOLE_SCRIPT.as_bytes());
+}
+
+/// Print each console message sent by the page until it disconnects, or show it on the dashboard.
+#[cfg(feature = "server")]
+pub(super) fn receive_messages(
+    request: &Request,
+    reader: &mut BufReader<Connection>,
+    stream: &mut Connection,
+    config: &ServerConfig,
+) -> io::Result<()> {
+    if !websocket::accept(request, stream)? {
+        return super::Response::new(400).write(stream, false);
+    }
+    while let Some(message) = websocket::read_message(reader, stream)? {
+        let message: Value = match serde_json::from_str(&message) {
+            Ok(message) => message,
+            Err(_) => continue,
+        };
+        let level = message["level"].as_str().unwrap_or("log");
+        let text = message["text"].as_str().unwrap_or_default();
+        print_message(level, text, config);
+    }
+    Ok(())
+}
+
+fn print_message(level: &str, text: &str, config: &ServerConfig) {
+    let style = match level {
+        "error" => color::RED,
+        "warn" => color::YELLOW,
+        "debug" => color::DIM,
+        _ => "",
+    };
+    for line in text.lines() {
+        config.log(color::paint(
+            style,
+            &format!("[console.{}] {}", level, line),
+        ));
+    }
+}
@@ -0,0 +1,79 @@
+//! Collects the coverage of the wasm from the browser, for `--coverage`.
+//!
+//! The wasm is built with `-Cinstrument-coverage` and the app exports a `run_wasm_coverage` function that returns the counters captured by minicov,
+//! which the injected script calls and posts back, so that code that only runs in the browser can be covered too.
+//! Each page load is written to its own `.profraw` file, which `llvm-profdata merge` combines.
+
+use super::{Request, Response, ServerConfig};
+use serde_json::json;
+
+pub(crate) const COVERAGE_PATH: &str = "/__run_wasm/coverage";
+
+/// Injected into the head of served html pages, with `{{name}}` replaced by the name of the package or example.
+///
+/// The counters only ever grow while the page is open, so each post replaces the last one of the page.
+/// They are posted every few seconds since the last post before the page is closed may be too large to be sent.
+const COVERAGE_SCRIPT: &str = r#"
+  <script type="module">
+    const page = Date.now().toString(36) + Math.random().toString(36).slice(2, 8);
+    let module;
+    const send = (closing) => {
+      let coverage;
+      try {
+        coverage = module && module.run_wasm_coverage && module.run_wasm_coverage();
+      } catch (e) {
+        return;
+      }
+      if (!coverage) return;
+      // Browsers limit the requests that outlive the page to 64KiB.
+      fetch(`/__run_wasm/coverage?page=${page}`, { method: "POST", body: coverage, keepalive: closing && coverage.length < 65536 }).catch(() => {});
+    };
+    window.addEventListener("load", async () => {
+      try {
+        module = await import("./{{name}}.js");
+      } catch (e) {
+        return;
+      }
+      setInterval(() => send(false), 5000);
+    });
+    window.addEventListener("pagehide", () => send(true));
+    window.runWasmCoverage = () => send(false);
+  </script>"#;
+
+pub(crate) fn inject_script(html: &mut Vec<u8>, name: &str) {
+    let script = COVERAGE_SCRIPT.replace("{{name}}", name);
+    super::insert_after_head(html, script.as_bytes());
+}
+
+/// Writes the coverage posted by a page to `<page>.profraw` in the coverage directory, and emits a `coverage` event.
+pub(super) fn receive(request: &Request, config: &ServerConfig) -> Response {
+    let dir = match &config.coverage_dir {
+        Some(dir) => dir,
+        None => return Response::new(404),
+    };
+    let page: String = request
+        .query()
+        .and_then(|query| query.split('&').find_map(|x| x.strip_prefix("page=")))
+        .unwrap_or_default()
+        .chars()
+        .filter(|x| x.is_ascii_alphanumeric())
+        .collect();
+    if page.is_empty() || request.body.is_empty() {
+        return Response::new(400);
+    }
+    let path = dir.join(format!("{}.profraw", page));
+    let result = std::fs::create_dir_all(dir).and_then(|()| std::fs::write(&path, &request.body));
+    if let Err(err) = result {
+        config.log(format!(
+            "Failed to write the coverage to {}: {}",
+            path.display(),
+            err
+        ));
+        return Response::new(500);
+    }
+    config.events.emit(
+        "coverage",
+        json!({ "path": path.display().to_string(), "size": request.body.len() }),
+    );
+    Response::new(204)
+}
@@ -0,0 +1,160 @@
+//! A screen summarizing the build and dev server that is redrawn in place, for long running sessions where log lines would scroll away.
+//!
+//! Only ANSI escape codes are used, which every terminal we support understands.
+
+use super::ServerConfig;
+use crate::report;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// How many of the most recent log lines are shown.
+const MAX_LINES: usize = 15;
+
+pub(crate) struct Dashboard {
+    url: String,
+    lines: Mutex<VecDeque<String>>,
+    /// The number of open connections from each client address.
+    connections: Mutex<HashMap<String, usize>>,
+    requests: AtomicU64,
+    stopped: AtomicBool,
+}
+
+impl Dashboard {
+    pub fn new(url: &str) -> Self {
+        Dashboard {
+            url: url.to_string(),
+            lines: Mutex::new(VecDeque::new()),
+            connections: Mutex::new(HashMap::new()),
+            requests: AtomicU64::new(0),
+            stopped: AtomicBool::new(false),
+        }
+    }
+
+    /// Add a line to the recent activity shown on the dashboard.
+    pub(super) fn log(&self, line: String) {
+        let mut lines = self.lines.lock().unwrap();
+        lines.push_back(line);
+        if lines.len() > MAX_LINES {
+            lines.pop_front();
+        }
+    }
+
+    pub(super) fn count_request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a connection from `address` as open for as long as the returned guard is alive.
+    pub(super) fn connected(&self, address: String) -> ConnectionGuard<'_> {
+        *self
+            .connections
+            .lock()
+            .unwrap()
+            .entry(address.clone())
+            .or_insert(0) += 1;
+        ConnectionGuard {
+            dashboard: self,
+            address,
+        }
+    }
+
+    /// Redraws the dashboard every second on another thread, until [`Dashboard::stop`] is called.
+    pub(super) fn spawn(config: Arc<ServerConfig>) {
+        thread::spawn(move || {
+            let dashboard = config.dashboard.as_ref().unwrap();
+            while !dashboard.stopped.load(Ordering::SeqCst) {
+                // Move to the top left and clear the screen, then draw over it.
+                print!("\x1b[H\x1b[2J{}", dashboard.render(&config));
+                io::Write::flush(&mut io::stdout()).ok();
+                thread::sleep(Duration::from_secs(1));
+            }
+        });
+    }
+
+    pub(super) fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+
+    fn render(&self, config: &ServerConfig) -> String {
+        let build = &config.build;
+        let mut screen = String::new();
+        writeln!(
+            screen,
+            "\x1b[1mcargo run-wasm\x1b[0m serving `{}` on {}\n",
+            build.name, self.url
+        )
+        .unwrap();
+        writeln!(
+            screen,
+            "Build:        {} build succeeded in {:.1?}",
+            build.profile, build.duration
+        )
+        .unwrap();
+        for artifact in &build.artifacts {
+            let size = std::fs::metadata(artifact).map(|x| x.len()).unwrap_or(0);
+            writeln!(
+                screen,
+                "              {:<30} {:>10}",
+                artifact
+                    .file_name()
+                    .map(|x| x.to_string_lossy())
+                    .unwrap_or_default(),
+                report::format_size(size)
+            )
+            .unwrap();
+        }
+        let (open, clients) = {
+            let connections = self.connections.lock().unwrap();
+            (connections.values().sum::<usize>(), connections.len())
+        };
+        writeln!(
+            screen,
+            "Connections:  {} open from {} client{}",
+            open,
+            clients,
+            if clients == 1 { "" } else { "s" }
+        )
+        .unwrap();
+        writeln!(
+            screen,
+            "Requests:     {}",
+            self.requests.load(Ordering::Relaxed)
+        )
+        .unwrap();
+        let uptime = config.started.elapsed().as_secs();
+        writeln!(
+            screen,
+            "Uptime:       {}h {:02}m {:02}s\n",
+            uptime / 3600,
+            uptime % 3600 / 60,
+            uptime % 60
+        )
+        .unwrap();
+        writeln!(screen, "Recent activity:").unwrap();
+        for line in self.lines.lock().unwrap().iter() {
+            writeln!(screen, "  {}", line).unwrap();
+        }
+        screen
+    }
+}
+
+pub(super) struct ConnectionGuard<'a> {
+    dashboard: &'a Dashboard,
+    address: String,
+}
+
+impl<'a> Drop for ConnectionGuard<'a> {
+    fn drop(&mut self) {
+        let mut connections = self.dashboard.connections.lock().unwrap();
+        if let Some(count) = connections.get_mut(&self.address) {
+            *count -= 1;
+            if *count == 0 {
+                connections.remove(&self.address);
+            }
+        }
+    }
+}
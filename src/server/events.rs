@@ -0,0 +1,133 @@
+//! Streams structured events about the dev loop via server-sent events,
+//! so that editors and dashboards can integrate with it without scraping stdout.
+//!
+//! Events that happened before a client connected, such as the initial build, are replayed to it first.
+
+use super::{Connection, Request};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+pub(crate) const EVENTS_PATH: &str = "/__run_wasm/events";
+
+/// Limits the memory used by the history, the oldest events are forgotten past this.
+const MAX_HISTORY: usize = 1000;
+
+pub(crate) struct Events {
+    log: Mutex<EventLog>,
+    changed: Condvar,
+    /// Also print every event to stdout as a JSON line, for `--message-format json`.
+    print: bool,
+}
+
+#[derive(Default)]
+struct EventLog {
+    next_id: u64,
+    history: VecDeque<Event>,
+}
+
+struct Event {
+    id: u64,
+    name: &'static str,
+    data: String,
+}
+
+impl Events {
+    pub fn new(print: bool) -> Self {
+        Events {
+            log: Mutex::default(),
+            changed: Condvar::new(),
+            print,
+        }
+    }
+
+    /// Whether events are printed as JSON lines, in which case the human readable output they duplicate is left out.
+    pub fn is_printing(&self) -> bool {
+        self.print
+    }
+
+    /// Send an event named `name`, e.g. `build-started`, to every connected client.
+    pub fn emit(&self, name: &'static str, data: Value) {
+        if self.print {
+            print_json(name, &data);
+        }
+        let mut log = self.log.lock().unwrap();
+        let id = log.next_id;
+        log.next_id += 1;
+        if log.history.len() == MAX_HISTORY {
+            log.history.pop_front();
+        }
+        log.history.push_back(Event {
+            id,
+            name,
+            data: data.to_string(),
+        });
+        self.changed.notify_all();
+    }
+
+    /// Holds the connection open, sending every event emitted.
+    /// Returns once the client disconnects.
+    pub(super) fn stream_events(
+        &self,
+        request: &Request,
+        stream: &mut Connection,
+    ) -> io::Result<()> {
+        stream.write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\r\n",
+        )?;
+        stream.flush()?;
+
+        // A reconnecting EventSource tells us the last event it saw, so that it only receives the ones it missed.
+        let mut next_id = request
+            .header("Last-Event-ID")
+            .and_then(|x| x.parse::<u64>().ok())
+            .map_or(0, |x| x + 1);
+        let mut log = self.log.lock().unwrap();
+        loop {
+            let mut message = String::new();
+            for event in log.history.iter().filter(|x| x.id >= next_id) {
+                message.push_str(&format!(
+                    "id: {}\nevent: {}\ndata: {}\n\n",
+                    event.id, event.name, event.data
+                ));
+            }
+            next_id = log.next_id;
+            if message.is_empty() {
+                // Wake up every so often to send a keepalive, otherwise we would never notice the client going away.
+                log = self
+                    .changed
+                    .wait_timeout(log, Duration::from_secs(15))
+                    .unwrap()
+                    .0;
+                if log.next_id != next_id {
+                    continue;
+                }
+                message.push_str(": keepalive\n\n");
+            }
+            // Dont hold the lock while blocked on the network.
+            drop(log);
+            stream.write_all(message.as_bytes())?;
+            stream.flush()?;
+            log = self.log.lock().unwrap();
+        }
+    }
+}
+
+/// Prints an event as a JSON object with a `reason` field, in the style of `cargo build --message-format json`.
+fn print_json(name: &str, data: &Value) {
+    let line = match data {
+        // Ours covers running wasm-bindgen as well, so cargo's would only be confusing.
+        _ if name == "cargo-output" && data["reason"] == "build-finished" => return,
+        // Cargo's own messages already have a reason, e.g. `compiler-artifact`, so they are passed through as is.
+        _ if name == "cargo-output" => data.clone(),
+        Value::Object(fields) => {
+            let mut fields = fields.clone();
+            fields.insert("reason".to_string(), Value::from(name));
+            Value::Object(fields)
+        }
+        data => json!({ "reason": name, "data": data }),
+    };
+    println!("{}", line);
+}
@@ -0,0 +1,36 @@
+//! Records what happens in the page for the headless browser test, see [`crate::headless`].
+
+/// Injected into the head of served html pages, so that console methods and `WebAssembly` are wrapped before any other script runs.
+///
+/// The recorded state is read back and the output cleared by [`crate::headless`] polling the page.
+const TEST_SCRIPT: &str = r#"
+  <script>
+    (() => {
+      const test = window.__runWasmTest = { loaded: false, errors: [], output: [] };
+      const format = (arg) => {
+        if (typeof arg === "string") return arg;
+        if (arg instanceof Error) return arg.stack || String(arg);
+        try { return JSON.stringify(arg); } catch (e) { return String(arg); }
+      };
+      for (const level of ["debug", "log", "info", "warn", "error"]) {
+        const original = console[level];
+        console[level] = (...args) => {
+          test.output.push({ level, text: args.map(format).join(" ") });
+          original.apply(console, args);
+        };
+      }
+      window.addEventListener("error", (event) => test.errors.push(event.error ? format(event.error) : event.message));
+      window.addEventListener("unhandledrejection", (event) => test.errors.push(`(in promise) ${format(event.reason)}`));
+      for (const name of ["instantiate", "instantiateStreaming"]) {
+        const original = WebAssembly[name];
+        WebAssembly[name] = (...args) => original.apply(WebAssembly, args).then((result) => {
+          test.loaded = true;
+          return result;
+        });
+      }
+    })();
+  </script>"#;
+
+pub(crate) fn inject_script(html: &mut Vec<u8>) {
+    super::insert_after_head(html, TEST_SCRIPT.as_bytes());
+}
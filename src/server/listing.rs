@@ -0,0 +1,63 @@
+//! Renders an index of directories without an index.html, so that testers can browse what files are actually served.
+
+use super::escape_html;
+use super::Response;
+use std::path::Path;
+
+pub(super) fn respond(url_path: &str, dir: &Path) -> Response {
+    let mut entries: Vec<(String, Option<u64>)> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            Some(if metadata.is_dir() {
+                (format!("{}/", name), None)
+            } else {
+                (name, Some(metadata.len()))
+            })
+        })
+        .collect();
+    // Directories first, then files, each sorted by name.
+    entries.sort_by(|a, b| a.1.is_some().cmp(&b.1.is_some()).then(a.0.cmp(&b.0)));
+
+    let title = format!("Index of {}", escape_html(url_path));
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n  <meta charset=\"UTF-8\" />\n  <title>{}</title>\n</head>\n<body>\n  <h1>{}</h1>\n  <ul>\n",
+        title, title
+    );
+    if url_path != "/" {
+        html.push_str("    <li><a href=\"../\">../</a></li>\n");
+    }
+    for (name, size) in &entries {
+        let href = escape_html(&percent_encode(name));
+        let name = escape_html(name);
+        match size {
+            Some(size) => html.push_str(&format!(
+                "    <li><a href=\"{}\">{}</a> ({} bytes)</li>\n",
+                href, name, size
+            )),
+            None => html.push_str(&format!("    <li><a href=\"{}\">{}</a></li>\n", href, name)),
+        }
+    }
+    html.push_str("  </ul>\n</body>\n</html>\n");
+
+    let mut response = Response::new(200);
+    response.header("Content-Type", "text/html; charset=utf-8");
+    response.body = html.into_bytes();
+    response
+}
+
+/// Encodes `name` for use as a relative url, so that names containing e.g. `#`, `?` or spaces link to the right file.
+fn percent_encode(name: &str) -> String {
+    let mut encoded = String::new();
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
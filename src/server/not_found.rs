@@ -0,0 +1,77 @@
+//! Builds 404 responses that help with debugging missing assets.
+//!
+//! A `404.html` in the served directory, e.g. from an assets directory, is used when present.
+//! Otherwise browsers are shown a page listing the files that are available.
+
+use super::{Request, Response};
+use std::path::{Path, PathBuf};
+
+/// Stop listing files after this many, the page is for humans.
+const MAX_LISTED: usize = 200;
+
+pub(super) fn respond(request: &Request, root: &Path) -> Response {
+    let mut response = Response::new(404);
+    if let Ok(custom) = std::fs::read(root.join("404.html")) {
+        response.header("Content-Type", "text/html; charset=utf-8");
+        response.body = custom;
+    } else if matches!(request.header("Accept"), Some(accept) if accept.contains("text/html")) {
+        response.header("Content-Type", "text/html; charset=utf-8");
+        response.body = listing_page(&request.path, root).into_bytes();
+    } else {
+        response.header("Content-Type", "text/plain; charset=utf-8");
+        response.body = format!("Could not find {}\n", request.path).into_bytes();
+    }
+    response
+}
+
+fn listing_page(path: &str, root: &Path) -> String {
+    let mut files = vec![];
+    list_files(root, &mut files);
+    let mut files: Vec<String> = files
+        .iter()
+        .filter_map(|file| file.strip_prefix(root).ok())
+        .map(|file| {
+            let url: Vec<String> = file
+                .components()
+                .map(|x| x.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            format!("/{}", url.join("/"))
+        })
+        .collect();
+    files.sort();
+
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n  <meta charset=\"UTF-8\" />\n  <title>404 Not Found</title>\n</head>\n<body>\n  <h1>404 Not Found</h1>\n  <p>Could not find <code>{}</code>, the available files are:</p>\n  <ul>\n",
+        escape_html(path)
+    );
+    for file in files.iter().take(MAX_LISTED) {
+        let file = escape_html(file);
+        html.push_str(&format!("    <li><a href=\"{}\">{}</a></li>\n", file, file));
+    }
+    if files.len() > MAX_LISTED {
+        html.push_str(&format!(
+            "    <li>and {} more</li>\n",
+            files.len() - MAX_LISTED
+        ));
+    }
+    html.push_str("  </ul>\n</body>\n</html>\n");
+    html
+}
+
+fn list_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            list_files(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,147 @@
+//! Measures how long the page takes to start, for `--profile-startup`.
+//!
+//! The injected script marks when the wasm is fetched, instantiated and the first frame after that is drawn with `performance.mark`,
+//! so they also show up in the performance panel of the browser devtools, then posts them back to be summarized in the terminal.
+
+use super::{Request, Response, ServerConfig};
+use crate::color;
+use serde_json::{json, Value};
+
+pub(crate) const PROFILE_PATH: &str = "/__run_wasm/profile";
+
+/// Injected into the head of served html pages, so that `fetch` and `WebAssembly` are wrapped before any other script runs.
+///
+/// Only the first of each mark counts, e.g. wasm-bindgen falls back to `WebAssembly.instantiate` when streaming fails.
+/// Pages that never request an animation frame are reported a few seconds after the wasm is instantiated instead.
+const PROFILE_SCRIPT: &str = r#"
+  <script>
+    (() => {
+      const prefix = "run-wasm:";
+      const isWasm = (url) => url.split(/[?#]/)[0].endsWith(".wasm");
+      const has = (name) => performance.getEntriesByName(prefix + name, "mark").length > 0;
+      const mark = (name) => { if (!has(name)) performance.mark(prefix + name); };
+      let reported = false;
+      const report = () => {
+        if (reported) return;
+        reported = true;
+        const marks = {};
+        for (const entry of performance.getEntriesByType("mark")) {
+          if (entry.name.startsWith(prefix)) marks[entry.name.slice(prefix.length)] = entry.startTime;
+        }
+        for (const [name, start, end] of [["fetch", "fetch-start", "fetch-response"], ["instantiate", "instantiate-start", "instantiate-end"], ["first-frame", "instantiate-end", "first-frame"]]) {
+          if (has(start) && has(end)) performance.measure(prefix + name, prefix + start, prefix + end);
+        }
+        const wasm = performance.getEntriesByType("resource").find((entry) => isWasm(entry.name));
+        const body = JSON.stringify({
+          page: location.pathname,
+          marks,
+          download_end: wasm ? wasm.responseEnd : null,
+          transfer_size: wasm ? wasm.transferSize : null,
+        });
+        originalFetch("/__run_wasm/profile", { method: "POST", body, keepalive: true }).catch(() => {});
+      };
+      const originalFetch = window.fetch;
+      window.fetch = (input, init) => {
+        if (!isWasm(input instanceof Request ? input.url : String(input))) return originalFetch(input, init);
+        mark("fetch-start");
+        return originalFetch(input, init).then((response) => {
+          mark("fetch-response");
+          return response;
+        });
+      };
+      for (const name of ["instantiate", "instantiateStreaming"]) {
+        const original = WebAssembly[name];
+        WebAssembly[name] = (...args) => {
+          mark("instantiate-start");
+          return original.apply(WebAssembly, args).then((result) => {
+            mark("instantiate-end");
+            setTimeout(report, 5000);
+            return result;
+          });
+        };
+      }
+      const originalRequestAnimationFrame = window.requestAnimationFrame;
+      window.requestAnimationFrame = (callback) => originalRequestAnimationFrame.call(window, (time) => {
+        try {
+          return callback(time);
+        } finally {
+          if (!reported && has("instantiate-end")) {
+            mark("first-frame");
+            report();
+          }
+        }
+      });
+    })();
+  </script>"#;
+
+/// The phases of the summary, each measured from the first mark to the second, with `start` standing for the start of the page load.
+const PHASES: &[(&str, &str, &str)] = &[
+    ("fetch", "fetch-start", "fetch-response"),
+    ("download", "fetch-response", "download-end"),
+    ("instantiate", "instantiate-start", "instantiate-end"),
+    ("first frame", "instantiate-end", "first-frame"),
+    ("total", "start", "end"),
+];
+
+pub(crate) fn inject_script(html: &mut Vec<u8>) {
+    super::insert_after_head(html, PROFILE_SCRIPT.as_bytes());
+}
+
+/// Prints the summary of the marks posted by the page, and emits it as a `startup-profile` event.
+pub(super) fn receive(request: &Request, config: &ServerConfig) -> Response {
+    let profile: Value = match serde_json::from_slice(&request.body) {
+        Ok(profile) => profile,
+        Err(_) => return Response::new(400),
+    };
+    let mark = |name: &str| match name {
+        "start" => Some(0.0),
+        "download-end" => profile["download_end"].as_f64(),
+        "end" => profile["marks"]["first-frame"]
+            .as_f64()
+            .or_else(|| profile["marks"]["instantiate-end"].as_f64()),
+        name => profile["marks"][name].as_f64(),
+    };
+    let phases: Vec<Value> = PHASES
+        .iter()
+        .filter_map(|(name, start, end)| {
+            let (start, end) = (mark(start)?, mark(end)?);
+            Some(json!({ "name": name, "start_ms": start, "duration_ms": end - start }))
+        })
+        .collect();
+    let page = profile["page"].as_str().unwrap_or("/");
+    let transfer_size = profile["transfer_size"].as_u64();
+
+    if !config.events.is_printing() {
+        config.log(format!(
+            "{} of `{}` {}",
+            color::paint(color::GREEN, "Startup profile"),
+            config.build.name,
+            color::paint(color::DIM, &format!("({})", page))
+        ));
+        if phases.is_empty() {
+            config.log("  No wasm was loaded".to_string());
+        }
+        for phase in &phases {
+            config.log(format!(
+                "  {:<12} {:>8.1}ms {}",
+                phase["name"].as_str().unwrap_or_default(),
+                phase["duration_ms"].as_f64().unwrap_or_default(),
+                color::paint(
+                    color::DIM,
+                    &format!("at {:.1}ms", phase["start_ms"].as_f64().unwrap_or_default())
+                )
+            ));
+        }
+    }
+    config.events.emit(
+        "startup-profile",
+        json!({
+            "name": config.build.name,
+            "page": page,
+            "marks": profile["marks"],
+            "phases": phases,
+            "wasm_transfer_size": transfer_size,
+        }),
+    );
+    Response::new(204)
+}
@@ -0,0 +1,196 @@
+//! Forwards requests for configured path prefixes to another http server.
+
+use super::{Connection, Request, Response};
+use crate::Verbosity;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// How long to wait for the proxied server to accept the connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait for the proxied server to send more of its response, so that one that stalls doesnt hold the connection forever.
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug)]
+pub(crate) struct Proxy {
+    /// Requests for this path or paths below it are forwarded.
+    prefix: String,
+    /// The `host:port` of the server to forward to.
+    authority: String,
+    /// Prepended to the request path when forwarding.
+    base_path: String,
+}
+
+impl Proxy {
+    /// Parses a proxy in the `/prefix=http://host:port` form used on the command line.
+    pub fn parse(proxy: &str) -> Result<Self, String> {
+        match proxy.split_once('=') {
+            Some((prefix, target)) => Proxy::new(prefix, target),
+            None => Err(format!(
+                "Invalid proxy {:?}, expected the form \"/prefix=http://host:port\"",
+                proxy
+            )),
+        }
+    }
+
+    pub fn new(prefix: &str, target: &str) -> Result<Self, String> {
+        if !prefix.starts_with('/') {
+            return Err(format!(
+                "Invalid proxy prefix {:?}, it must start with a `/`",
+                prefix
+            ));
+        }
+        let target_without_scheme = match target.strip_prefix("http://") {
+            Some(x) => x,
+            None => {
+                return Err(format!(
+                    "Invalid proxy target {:?}, only http:// urls are supported",
+                    target
+                ))
+            }
+        };
+        let (authority, base_path) = match target_without_scheme.find('/') {
+            Some(i) => target_without_scheme.split_at(i),
+            None => (target_without_scheme, ""),
+        };
+        if authority.is_empty() {
+            return Err(format!("Invalid proxy target {:?}, missing host", target));
+        }
+        let authority = if authority.rsplit_once(':').is_some() && !authority.ends_with(']') {
+            authority.to_string()
+        } else {
+            format!("{}:80", authority)
+        };
+        Ok(Proxy {
+            prefix: prefix.trim_end_matches('/').to_string(),
+            authority,
+            base_path: base_path.trim_end_matches('/').to_string(),
+        })
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        match path.strip_prefix(&self.prefix) {
+            Some(rest) => self.prefix.is_empty() || rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        }
+    }
+
+    /// The url requests are forwarded to.
+    pub fn target(&self) -> String {
+        format!("http://{}{}", self.authority, self.base_path)
+    }
+
+    /// The response to send instead of forwarding `request`, when it is one the proxy cant forward yet:
+    /// a websocket or other protocol upgrade, or a body without a `Content-Length` such as a chunked one.
+    pub(super) fn unsupported(request: &Request) -> Option<Response> {
+        let reason = if request.header("Upgrade").is_some() {
+            "Upgrading the connection, e.g. to a websocket,"
+        } else if request.header("Transfer-Encoding").is_some() {
+            "A request body without a Content-Length"
+        } else {
+            return None;
+        };
+        let mut response = Response::new(502);
+        response.body = format!("{} is not supported by the dev server proxy", reason).into_bytes();
+        Some(response)
+    }
+
+    /// Forward the request to the proxied server and stream its response back to the client unmodified.
+    ///
+    /// `strip_credentials` leaves out the `Authorization` and `Proxy-Authorization` headers,
+    /// for when they hold the `--auth` credentials of the dev server rather than any meant for the proxied server.
+    pub(super) fn forward(
+        &self,
+        request: &Request,
+        client: &mut Connection,
+        verbosity: Verbosity,
+        strip_credentials: bool,
+    ) -> io::Result<()> {
+        let connect = || -> io::Result<TcpStream> {
+            let mut last_err = io::Error::new(io::ErrorKind::NotFound, "no addresses found");
+            for addr in self.authority.to_socket_addrs()? {
+                match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+                    Ok(server) => {
+                        server.set_read_timeout(Some(READ_TIMEOUT))?;
+                        server.set_write_timeout(Some(READ_TIMEOUT))?;
+                        return Ok(server);
+                    }
+                    Err(err) => last_err = err,
+                }
+            }
+            Err(last_err)
+        };
+        let mut server = match connect() {
+            Ok(server) => server,
+            Err(err) => {
+                if verbosity >= Verbosity::Normal {
+                    println!(
+                        "Failed to proxy {} to http://{}: {}",
+                        request.path, self.authority, err
+                    );
+                }
+                let mut response = Response::new(502);
+                response.body =
+                    format!("Failed to connect to http://{}: {}", self.authority, err).into_bytes();
+                return response.write(client, request.method == "HEAD");
+            }
+        };
+
+        let mut head = format!(
+            "{} {}{} HTTP/1.1\r\n",
+            request.method, self.base_path, request.target
+        );
+        for (name, value) in &request.headers {
+            // We only ever send a single request per connection so we can read the response until EOF.
+            let skipped = ["host", "connection"]
+                .iter()
+                .any(|x| name.eq_ignore_ascii_case(x))
+                || strip_credentials
+                    && ["authorization", "proxy-authorization"]
+                        .iter()
+                        .any(|x| name.eq_ignore_ascii_case(x));
+            if !skipped {
+                head.push_str(&format!("{}: {}\r\n", name, value));
+            }
+        }
+        head.push_str(&format!(
+            "Host: {}\r\nConnection: close\r\n\r\n",
+            self.authority
+        ));
+        server.write_all(head.as_bytes())?;
+        server.write_all(&request.body)?;
+        server.flush()?;
+
+        let mut buffer = [0; 8192];
+        let mut forwarded = false;
+        loop {
+            let read = match server.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                // Nothing was sent yet, so the client can still be told why.
+                Err(err)
+                    if !forwarded
+                        && matches!(
+                            err.kind(),
+                            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                        ) =>
+                {
+                    let mut response = Response::new(504);
+                    response.body = format!(
+                        "http://{} did not respond within {}s",
+                        self.authority,
+                        READ_TIMEOUT.as_secs()
+                    )
+                    .into_bytes();
+                    return response.write(client, request.method == "HEAD");
+                }
+                Err(err) => return Err(err),
+            };
+            client.write_all(&buffer[..read])?;
+            forwarded = true;
+        }
+        client.flush()
+    }
+}
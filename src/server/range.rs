@@ -0,0 +1,100 @@
+//! Serves `Range` requests so that the browser can seek within large files without downloading all of them.
+
+use super::Response;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Returns None if the range is not one we can handle, in which case the entire file should be served instead.
+///
+/// Only single ranges are supported, requests for multiple ranges are rare and would need a multipart response.
+pub(super) fn respond(range: &str, file_path: &Path, content_type: &str) -> Option<Response> {
+    let mut file = File::open(file_path).ok()?;
+    let len = file.metadata().ok()?.len();
+    let (start, end) = match parse_range(range, len)? {
+        Some(range) => range,
+        None => {
+            let mut response = Response::new(416);
+            response.header("Content-Range", &format!("bytes */{}", len));
+            return Some(response);
+        }
+    };
+
+    let mut body = vec![0; (end - start + 1) as usize];
+    file.seek(SeekFrom::Start(start)).ok()?;
+    file.read_exact(&mut body).ok()?;
+
+    let mut response = Response::new(206);
+    response.header("Content-Type", content_type);
+    response.header("Accept-Ranges", "bytes");
+    response.header("Content-Range", &format!("bytes {}-{}/{}", start, end, len));
+    response.body = body;
+    Some(response)
+}
+
+/// Parses a `Range` header into an inclusive start and end.
+///
+/// Returns None if the header is not a single byte range and Some(None) if the range is outside of the file.
+fn parse_range(range: &str, len: u64) -> Option<Option<(u64, u64)>> {
+    let range = range.trim().strip_prefix("bytes=")?;
+    if range.contains(',') {
+        return None;
+    }
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            if suffix == 0 {
+                return Some(None);
+            }
+            (len.saturating_sub(suffix), len.checked_sub(1)?)
+        }
+        (start, "") => (start.parse().ok()?, len.checked_sub(1)?),
+        (start, end) => {
+            let end: u64 = end.parse().ok()?;
+            (start.parse().ok()?, end.min(len.checked_sub(1)?))
+        }
+    };
+    if start > end || start >= len {
+        Some(None)
+    } else {
+        Some(Some((start, end)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_range;
+
+    #[test]
+    fn single_ranges() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some(Some((0, 99))));
+        assert_eq!(parse_range("bytes=500-", 1000), Some(Some((500, 999))));
+        assert_eq!(parse_range(" bytes=10 - 20 ", 1000), Some(Some((10, 20))));
+    }
+
+    #[test]
+    fn suffix_ranges() {
+        assert_eq!(parse_range("bytes=-100", 1000), Some(Some((900, 999))));
+        // A suffix longer than the file is the whole file.
+        assert_eq!(parse_range("bytes=-2000", 1000), Some(Some((0, 999))));
+        assert_eq!(parse_range("bytes=-0", 1000), Some(None));
+    }
+
+    #[test]
+    fn out_of_bounds_ranges() {
+        // The end is clamped to the file, but the start must be within it.
+        assert_eq!(parse_range("bytes=900-5000", 1000), Some(Some((900, 999))));
+        assert_eq!(parse_range("bytes=1000-", 1000), Some(None));
+        assert_eq!(parse_range("bytes=1000-1100", 1000), Some(None));
+        assert_eq!(parse_range("bytes=20-10", 1000), Some(None));
+    }
+
+    #[test]
+    fn unsupported_ranges() {
+        assert_eq!(parse_range("bytes=0-1,5-6", 1000), None);
+        assert_eq!(parse_range("items=0-1", 1000), None);
+        assert_eq!(parse_range("bytes=a-b", 1000), None);
+        assert_eq!(parse_range("bytes=0-", 0), None);
+    }
+}
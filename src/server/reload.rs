@@ -0,0 +1,75 @@
+//! Tells connected browsers to reload the page via server-sent events.
+
+use super::{Connection, Request};
+use std::io::{self, Write};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+pub(crate) const RELOAD_PATH: &str = "/__run_wasm/reload";
+
+/// Injected into served html pages so that they reload when told to by the server.
+const RELOAD_SCRIPT: &str = r#"<script>
+  new EventSource("/__run_wasm/reload").addEventListener("reload", () => location.reload());
+</script>
+"#;
+
+#[derive(Default)]
+pub(crate) struct Reloader {
+    generation: Mutex<u64>,
+    changed: Condvar,
+}
+
+impl Reloader {
+    /// Reload every page that is currently open.
+    pub fn reload(&self) {
+        *self.generation.lock().unwrap() += 1;
+        self.changed.notify_all();
+    }
+
+    /// Holds the connection open, sending a reload event every time [`Reloader::reload`] is called.
+    /// Returns once the client disconnects.
+    pub(super) fn stream_events(
+        &self,
+        _request: &Request,
+        stream: &mut Connection,
+    ) -> io::Result<()> {
+        stream.write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\r\n",
+        )?;
+        stream.flush()?;
+
+        let mut generation = self.generation.lock().unwrap();
+        let mut last_seen = *generation;
+        loop {
+            // Wake up every so often to send a keepalive, otherwise we would never notice the client going away.
+            generation = self
+                .changed
+                .wait_timeout(generation, Duration::from_secs(15))
+                .unwrap()
+                .0;
+            let message: &[u8] = if *generation != last_seen {
+                last_seen = *generation;
+                b"event: reload\ndata: \n\n"
+            } else {
+                b": keepalive\n\n"
+            };
+            // Dont hold the lock while blocked on the network.
+            drop(generation);
+            stream.write_all(message)?;
+            stream.flush()?;
+            generation = self.generation.lock().unwrap();
+        }
+    }
+}
+
+/// Insert the reload script at the end of the body of an html page.
+pub(crate) fn inject_script(html: &mut Vec<u8>) {
+    let position = find_last(html, b"</body>").unwrap_or(html.len());
+    html.splice(position..position, RELOAD_SCRIPT.bytes());
+}
+
+fn find_last(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .rposition(|window| window.eq_ignore_ascii_case(needle))
+}
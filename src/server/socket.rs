@@ -0,0 +1,170 @@
+//! Lets the dev server listen on either a TCP socket or a unix domain socket.
+
+#[cfg(feature = "server")]
+use socket2::{Domain, Protocol, Socket, Type};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Like [`TcpListener::bind`], but `reuse_address` can override the platforms default for `SO_REUSEADDR`.
+#[cfg(feature = "server")]
+pub(super) fn bind_tcp(
+    host: &str,
+    port: u16,
+    reuse_address: Option<bool>,
+) -> io::Result<TcpListener> {
+    let reuse_address = match reuse_address {
+        Some(reuse_address) => reuse_address,
+        None => return TcpListener::bind((host, port)),
+    };
+    let mut last_err = None;
+    for addr in (host, port).to_socket_addrs()? {
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+        socket.set_reuse_address(reuse_address)?;
+        match socket.bind(&addr.into()).and_then(|()| socket.listen(128)) {
+            Ok(()) => return Ok(socket.into()),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "could not resolve to any addresses",
+        )
+    }))
+}
+
+pub(crate) enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+impl Listener {
+    pub(super) fn accept(&self) -> io::Result<Connection> {
+        match self {
+            Listener::Tcp(listener) => listener.accept().map(|(x, _)| Connection::Tcp(x)),
+            #[cfg(unix)]
+            Listener::Unix(listener, _) => listener.accept().map(|(x, _)| Connection::Unix(x)),
+        }
+    }
+
+    /// Returns an address that can be connected to in order to reach this listener.
+    pub(crate) fn connect_address(&self) -> ConnectAddress {
+        match self {
+            Listener::Tcp(listener) => {
+                let mut addr = listener.local_addr().unwrap();
+                // Cant connect to the unspecified address on every platform, but it also listens on loopback.
+                match addr.ip() {
+                    IpAddr::V4(ip) if ip.is_unspecified() => {
+                        addr.set_ip(Ipv4Addr::LOCALHOST.into())
+                    }
+                    IpAddr::V6(ip) if ip.is_unspecified() => {
+                        addr.set_ip(Ipv6Addr::LOCALHOST.into())
+                    }
+                    _ => {}
+                }
+                ConnectAddress::Tcp(addr)
+            }
+            #[cfg(unix)]
+            Listener::Unix(_, path) => ConnectAddress::Unix(path.clone()),
+        }
+    }
+}
+
+pub(crate) enum ConnectAddress {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl fmt::Display for ConnectAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConnectAddress::Tcp(addr) => write!(f, "{}", addr),
+            #[cfg(unix)]
+            ConnectAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+impl ConnectAddress {
+    pub fn connect(&self) -> io::Result<()> {
+        match self {
+            ConnectAddress::Tcp(addr) => TcpStream::connect(addr).map(|_| ()),
+            #[cfg(unix)]
+            ConnectAddress::Unix(path) => UnixStream::connect(path).map(|_| ()),
+        }
+    }
+}
+
+/// A connection from a client, accepted by a [`Listener`].
+pub(crate) enum Connection {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Connection {
+    pub fn try_clone(&self) -> io::Result<Connection> {
+        match self {
+            Connection::Tcp(stream) => stream.try_clone().map(Connection::Tcp),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.try_clone().map(Connection::Unix),
+        }
+    }
+
+    /// The address of the client, for logging.
+    pub fn peer_addr(&self) -> String {
+        match self {
+            Connection::Tcp(stream) => stream
+                .peer_addr()
+                .map(|x| x.ip().to_string())
+                .unwrap_or_else(|_| "-".to_string()),
+            // Clients of unix sockets are usually unnamed.
+            #[cfg(unix)]
+            Connection::Unix(_) => "-".to_string(),
+        }
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.set_read_timeout(timeout),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.set_read_timeout(timeout),
+        }
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.flush(),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Reports the state of the build and dev server as JSON, so that tooling can poll the dev server to see when a build finished.
+
+use super::Response;
+use serde_json::json;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub(crate) const STATUS_PATH: &str = "/__run_wasm/status";
+
+/// Describes the build whose output is being served.
+pub(crate) struct BuildStatus {
+    pub name: String,
+    pub profile: &'static str,
+    pub started_at: SystemTime,
+    pub duration: Duration,
+    /// The generated wasm, js and index.html in that order, their sizes are read when the status is requested.
+    pub artifacts: Vec<PathBuf>,
+}
+
+pub(super) fn respond(build: &BuildStatus, server_started: Instant) -> Response {
+    let artifacts: Vec<_> = build
+        .artifacts
+        .iter()
+        .map(|path| {
+            json!({
+                "file": path.file_name().map(|x| x.to_string_lossy()),
+                "size": std::fs::metadata(path).map(|x| x.len()).ok(),
+            })
+        })
+        .collect();
+    let status = json!({
+        "build": {
+            "result": "success",
+            "name": build.name,
+            "profile": build.profile,
+            "started_at": unix_secs(build.started_at),
+            "finished_at": unix_secs(build.started_at + build.duration),
+            "duration_secs": build.duration.as_secs_f64(),
+            "artifacts": artifacts,
+        },
+        "server": {
+            "uptime_secs": server_started.elapsed().as_secs_f64(),
+        },
+    });
+
+    let mut response = Response::new(200);
+    response.header("Content-Type", "application/json");
+    response.body = serde_json::to_vec_pretty(&status).unwrap();
+    response
+}
+
+fn unix_secs(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs_f64())
+        .unwrap_or(0.0)
+}
@@ -0,0 +1,118 @@
+//! Simulates a slow network by delaying and rate limiting responses,
+//! so that the loading experience of a large wasm file can be evaluated without external tools.
+
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The presets, named after and roughly matching those of the chrome devtools: (name, kbit/s, latency in ms)
+const PRESETS: &[(&str, u64, u64)] =
+    &[("slow-3g", 400, 2000), ("3g", 1600, 563), ("4g", 9000, 170)];
+
+pub(crate) struct Throttle {
+    bytes_per_sec: u64,
+    /// Added before every response is sent.
+    pub latency: Duration,
+    /// The bandwidth is shared by every connection, like a real network link.
+    /// This is when the link is next free to send data.
+    next_free: Mutex<Instant>,
+}
+
+impl Throttle {
+    /// Parses either a preset name or a custom `KBITS,MS` value, e.g. `1000,200` for 1000 kbit/s with 200ms latency.
+    pub fn parse(throttle: &str) -> Result<Self, String> {
+        let (kbits, latency_ms) = match PRESETS.iter().find(|(name, _, _)| *name == throttle) {
+            Some((_, kbits, latency_ms)) => (*kbits, *latency_ms),
+            None => match throttle.split_once(',').map(|(kbits, latency)| {
+                (kbits.trim().parse::<u64>(), latency.trim().parse::<u64>())
+            }) {
+                Some((Ok(kbits), Ok(latency_ms))) if kbits > 0 => (kbits, latency_ms),
+                _ => {
+                    let names: Vec<&str> = PRESETS.iter().map(|(name, _, _)| *name).collect();
+                    return Err(format!(
+                        "Invalid throttle {:?}, expected one of {} or KBITS,MS e.g. \"1000,200\" for 1000 kbit/s with 200ms latency",
+                        throttle,
+                        names.join(", ")
+                    ));
+                }
+            },
+        };
+        Ok(Throttle {
+            bytes_per_sec: kbits * 1000 / 8,
+            latency: Duration::from_millis(latency_ms),
+            next_free: Mutex::new(Instant::now()),
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} kbit/s with {}ms latency",
+            self.bytes_per_sec * 8 / 1000,
+            self.latency.as_millis()
+        )
+    }
+
+    /// Wrap `writer` so that writes to it are rate limited.
+    pub(super) fn writer<'a, W: Write>(&'a self, writer: &'a mut W) -> ThrottledWriter<'a, W> {
+        ThrottledWriter {
+            throttle: self,
+            inner: writer,
+        }
+    }
+}
+
+pub(super) struct ThrottledWriter<'a, W> {
+    throttle: &'a Throttle,
+    inner: &'a mut W,
+}
+
+impl<'a, W: Write> Write for ThrottledWriter<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Send in small chunks so that concurrent responses interleave rather than waiting for each other.
+        let chunk = buf
+            .len()
+            .min((self.throttle.bytes_per_sec / 20).max(1) as usize);
+        let send_time = Duration::from_secs_f64(chunk as f64 / self.throttle.bytes_per_sec as f64);
+        let done = {
+            let mut next_free = self.throttle.next_free.lock().unwrap();
+            let start = (*next_free).max(Instant::now());
+            *next_free = start + send_time;
+            *next_free
+        };
+        let written = self.inner.write(&buf[..chunk])?;
+        if let Some(wait) = done.checked_duration_since(Instant::now()) {
+            thread::sleep(wait);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Throttle;
+
+    #[test]
+    fn presets() {
+        let describe = |name| Throttle::parse(name).unwrap().describe();
+        assert_eq!(describe("slow-3g"), "400 kbit/s with 2000ms latency");
+        assert_eq!(describe("3g"), "1600 kbit/s with 563ms latency");
+        assert_eq!(describe("4g"), "9000 kbit/s with 170ms latency");
+    }
+
+    #[test]
+    fn custom_values() {
+        assert_eq!(
+            Throttle::parse("1000, 200").unwrap().describe(),
+            "1000 kbit/s with 200ms latency"
+        );
+        for invalid in ["0,100", "1000", "fast", "a,b"] {
+            let err = Throttle::parse(invalid).err().unwrap();
+            assert!(err.contains("slow-3g, 3g, 4g"), "{}", err);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! A minimal websocket implementation, only supporting receiving messages from the client.
+
+use super::{Connection, Request};
+use std::io::{self, Read, Write};
+
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The largest message the client may send, across all of its frames, console messages are far smaller.
+const MAX_MESSAGE: u64 = 1024 * 1024;
+
+/// The close code telling the client its message was too big.
+const MESSAGE_TOO_BIG: u16 = 1009;
+
+/// Complete the websocket handshake, returns false if the request was not a valid websocket upgrade.
+#[cfg(feature = "server")]
+pub(super) fn accept(request: &Request, stream: &mut Connection) -> io::Result<bool> {
+    let key = match request.header("Sec-WebSocket-Key") {
+        Some(key)
+            if request
+                .header("Upgrade")
+                .map(|x| x.eq_ignore_ascii_case("websocket"))
+                == Some(true) =>
+        {
+            key
+        }
+        _ => return Ok(false),
+    };
+    let accept = base64(
+        &sha1_smol::Sha1::from(format!("{}{}", key, HANDSHAKE_GUID))
+            .digest()
+            .bytes(),
+    );
+    stream.write_all(
+        format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            accept
+        )
+        .as_bytes(),
+    )?;
+    stream.flush()?;
+    Ok(true)
+}
+
+/// Read the next text message from the client, returns None once the client closes the connection.
+///
+/// A message larger than [`MAX_MESSAGE`] closes the connection too, rather than being buffered.
+///
+/// `stream` is only written to, in order to respond to control frames.
+pub(super) fn read_message<R: Read>(
+    reader: &mut R,
+    stream: &mut Connection,
+) -> io::Result<Option<String>> {
+    let mut message = vec![];
+    loop {
+        let mut header = [0; 2];
+        reader.read_exact(&mut header)?;
+        let fin = header[0] & 0x80 != 0;
+        let opcode = header[0] & 0x0f;
+        let masked = header[1] & 0x80 != 0;
+        let len = match header[1] & 0x7f {
+            126 => {
+                let mut len = [0; 2];
+                reader.read_exact(&mut len)?;
+                u16::from_be_bytes(len) as u64
+            }
+            127 => {
+                let mut len = [0; 8];
+                reader.read_exact(&mut len)?;
+                u64::from_be_bytes(len)
+            }
+            len => len as u64,
+        };
+        if len > MAX_MESSAGE - message.len() as u64 {
+            write_frame(stream, 0x8, &MESSAGE_TOO_BIG.to_be_bytes())?;
+            return Ok(None);
+        }
+        let mut mask = [0; 4];
+        if masked {
+            reader.read_exact(&mut mask)?;
+        }
+        let mut payload = vec![];
+        reader.by_ref().take(len).read_to_end(&mut payload)?;
+        if payload.len() as u64 != len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "The connection closed in the middle of a websocket frame",
+            ));
+        }
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+
+        match opcode {
+            // continuation and text frames
+            0x0 | 0x1 => {
+                message.extend(payload);
+                if fin {
+                    return Ok(Some(String::from_utf8_lossy(&message).into_owned()));
+                }
+            }
+            // close
+            0x8 => {
+                write_frame(stream, 0x8, &[])?;
+                return Ok(None);
+            }
+            // ping
+            0x9 => write_frame(stream, 0xA, &payload)?,
+            // binary and pong frames are ignored
+            _ => {}
+        }
+    }
+}
+
+/// Write a single unmasked frame, only used for control frames, which carry at most 125 bytes.
+fn write_frame(stream: &mut Connection, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    if payload.len() > 125 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Control frames cannot carry more than 125 bytes",
+        ));
+    }
+    let mut frame = vec![0x80 | opcode, payload.len() as u8];
+    frame.extend(payload);
+    stream.write_all(&frame)?;
+    stream.flush()
+}
+
+pub(super) fn base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::new();
+    for chunk in input.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(ALPHABET[(n >> (18 - i * 6)) as usize & 0x3f] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::base64;
+
+    #[test]
+    fn encodes_base64() {
+        // The test vectors from RFC 4648.
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foob"), "Zm9vYg==");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+}
@@ -0,0 +1,107 @@
+//! Writes and checks a manifest of the hash of every file in the `--dist` output, for `--dist-manifest` and `--check-dist`.
+//!
+//! Committing the manifest alongside a committed build, e.g. on a GitHub Pages branch, lets CI rebuild and catch a build that is out of date.
+//! The manifest is in the format of `sha1sum`, so it can also be checked with `sha1sum -c` from the output directory.
+
+use crate::Error;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Writes the manifest of the files in `dir` to `path`.
+pub(crate) fn write(dir: &Path, path: &Path) -> Result<(), Error> {
+    let mut manifest = String::new();
+    for (file, hash) in hash_files(dir, path)? {
+        manifest.push_str(&format!("{}  {}\n", hash, file));
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(Error::io(format!("Failed to create {}", parent.display())))?;
+    }
+    std::fs::write(path, manifest).map_err(Error::io(format!("Failed to write {}", path.display())))
+}
+
+/// Compares the files in `dir` against the manifest at `path`, failing with [`Error::DistMismatch`] listing every file that differs.
+pub(crate) fn check(dir: &Path, path: &Path, name: &str) -> Result<(), Error> {
+    let manifest = std::fs::read_to_string(path)
+        .map_err(Error::io(format!("Failed to read {}", path.display())))?;
+    let mut expected = BTreeMap::new();
+    for line in manifest.lines().filter(|x| !x.trim().is_empty()) {
+        // sha1sum marks files hashed in binary mode with a `*`, which makes no difference to the hash.
+        match line.split_once(' ') {
+            Some((hash, file)) => {
+                let file = file.trim_start_matches(' ').trim_start_matches('*');
+                expected.insert(file.to_string(), hash.to_string());
+            }
+            None => {
+                return Err(Error::InvalidArgs(format!(
+                    "Invalid line {:?} in {}, expected a hash and a path separated by spaces",
+                    line,
+                    path.display()
+                )))
+            }
+        }
+    }
+
+    let actual = hash_files(dir, path)?;
+    let mut differences = vec![];
+    for (file, hash) in &actual {
+        match expected.get(file) {
+            Some(expected_hash) if expected_hash == hash => {}
+            Some(_) => differences.push(format!("  changed {}", file)),
+            None => differences.push(format!("  added   {}", file)),
+        }
+    }
+    for file in expected.keys().filter(|x| !actual.contains_key(*x)) {
+        differences.push(format!("  removed {}", file));
+    }
+    if differences.is_empty() {
+        return Ok(());
+    }
+    Err(Error::DistMismatch(format!(
+        "The --dist output of `{}` in {} differs from {}:\n{}\nCommit the new build and update the manifest with --dist-manifest {}",
+        name,
+        dir.display(),
+        path.display(),
+        differences.join("\n"),
+        path.display()
+    )))
+}
+
+/// The hash of every file in `dir` by its path relative to `dir`, leaving out the manifest at `manifest` if it is in there.
+fn hash_files(dir: &Path, manifest: &Path) -> Result<BTreeMap<String, String>, Error> {
+    let mut files = vec![];
+    find_files(dir, &mut files)?;
+    let mut hashes = BTreeMap::new();
+    for file in files {
+        if file == manifest {
+            continue;
+        }
+        let contents = std::fs::read(&file)
+            .map_err(Error::io(format!("Failed to read {}", file.display())))?;
+        let name = file
+            .strip_prefix(dir)
+            .unwrap()
+            .components()
+            .map(|x| x.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .join("/");
+        hashes.insert(name, sha1_smol::Sha1::from(contents).digest().to_string());
+    }
+    Ok(hashes)
+}
+
+fn find_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let entries =
+        std::fs::read_dir(dir).map_err(Error::io(format!("Failed to read {}", dir.display())))?;
+    for entry in entries {
+        let path = entry
+            .map_err(Error::io(format!("Failed to read {}", dir.display())))?
+            .path();
+        if path.is_dir() {
+            find_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,358 @@
+//! The steps a build is made of, which runners can reorder, replace or extend via [`crate::RunWasm::steps`].
+//!
+//! The built in steps run cargo, copy the assets, run wasm-bindgen and render the index.html in that order.
+//! Plugins such as wasm-opt or css preprocessors can live outside of this crate as their own [`BuildStep`].
+
+use crate::cancel::CancelToken;
+use crate::server::events::Events;
+use crate::{annotations, assets, render_index, BuildInfo, Error, PkgTarget};
+use serde_json::Value;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+/// A stage of the build, given the [`BuildContext`] shared by every step of the build.
+///
+/// Steps run in order after the [`crate::RunWasm::before_build`] hooks and before the [`crate::RunWasm::after_build`] hooks.
+/// An error returned by a step fails the build, with [`Error::Step`] unless it is already an [`Error`].
+///
+/// ```no_run
+/// use cargo_run_wasm::{BuildContext, BuildStep};
+///
+/// struct WasmOpt;
+///
+/// impl BuildStep for WasmOpt {
+///     fn name(&self) -> &str {
+///         "wasm-opt"
+///     }
+///
+///     fn run(&self, build: &mut BuildContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+///         let wasm = build.info().dir().join(format!("{}_bg.wasm", build.info().name()));
+///         let status = std::process::Command::new("wasm-opt")
+///             .args(["-O", "-o"])
+///             .args([&wasm, &wasm])
+///             .status()?;
+///         if !status.success() {
+///             return Err("wasm-opt failed".into());
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// cargo_run_wasm::RunWasm::new().step(WasmOpt).run();
+/// ```
+pub trait BuildStep {
+    /// Identifies the step in errors.
+    fn name(&self) -> &str;
+
+    fn run(&self, build: &mut BuildContext)
+        -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Lets [`crate::RunWasm::build_async`] await cargo instead of running [`CargoBuild`] on the async runtime.
+    #[doc(hidden)]
+    fn is_cargo_build(&self) -> bool {
+        false
+    }
+}
+
+/// The state of a build, which each [`BuildStep`] reads and can modify for the steps after it.
+pub struct BuildContext {
+    pub(crate) info: BuildInfo,
+    pub(crate) cargo: String,
+    pub(crate) cargo_args: Vec<String>,
+    /// Environment variables cargo is run with, e.g. the `RUSTFLAGS` for [`crate::RunWasm::coverage`].
+    pub(crate) cargo_env: Vec<(String, String)>,
+    pub(crate) wasm_path: PathBuf,
+    pub(crate) assets_dirs: Vec<PathBuf>,
+    pub(crate) output_files: Vec<(PathBuf, OutputFile)>,
+    pub(crate) css: String,
+    pub(crate) html: Option<String>,
+    pub(crate) canvas_id: Option<String>,
+    pub(crate) streaming: bool,
+    pub(crate) pkg: Option<PkgTarget>,
+    /// False for [`crate::RunWasm::bindgen`], when the wasm is served as cargo built it.
+    pub(crate) bindgen: bool,
+    /// Print the diagnostics in the json messages of cargo as annotations, for [`crate::RunWasm::annotations`].
+    pub(crate) annotate: bool,
+    pub(crate) events: Arc<Events>,
+    pub(crate) cancel: CancelToken,
+}
+
+impl BuildContext {
+    /// What is being built and where the files to serve are written to.
+    pub fn info(&self) -> &BuildInfo {
+        &self.info
+    }
+
+    /// The args [`CargoBuild`] passes to cargo, which earlier steps can add to.
+    pub fn cargo_args(&mut self) -> &mut Vec<String> {
+        &mut self.cargo_args
+    }
+
+    /// The wasm file built by cargo, which [`Bindgen`] reads.
+    pub fn wasm_path(&self) -> &Path {
+        &self.wasm_path
+    }
+
+    /// Point [`Bindgen`] at a different wasm file, e.g. one a step has post-processed.
+    pub fn set_wasm_path<P: AsRef<Path>>(&mut self, path: P) {
+        self.wasm_path = path.as_ref().to_path_buf();
+    }
+
+    /// The css included in the index.html by [`RenderIndex`].
+    pub fn css(&self) -> &str {
+        &self.css
+    }
+
+    /// Replace the css included in the index.html by [`RenderIndex`], e.g. with the output of a css preprocessor.
+    pub fn set_css(&mut self, css: &str) {
+        self.css = css.to_string();
+    }
+
+    /// The token given to [`crate::RunWasm::cancel_token`], for steps that run long enough to be worth stopping early.
+    pub fn cancel_token(&self) -> &CancelToken {
+        &self.cancel
+    }
+
+    pub(crate) fn cargo_command(&self) -> Command {
+        let mut command = Command::new(&self.cargo);
+        command
+            .current_dir(&self.info.project_root)
+            .args(&self.cargo_args)
+            .envs(self.cargo_env.iter().cloned());
+        command
+    }
+
+    /// Forwards a line of cargo's json output as an event.
+    ///
+    /// When annotating, cargo leaves rendering the diagnostics to this, which prints them to stderr as cargo would along with their annotation.
+    pub(crate) fn cargo_message(&self, line: &str) {
+        if let Ok(message) = serde_json::from_str::<Value>(line) {
+            if self.annotate && message["reason"] == "compiler-message" {
+                if let Some(rendered) = message["message"]["rendered"].as_str() {
+                    eprint!("{}", rendered);
+                }
+                if let Some(annotation) = annotations::compiler_message(&message["message"]) {
+                    println!("{}", annotation);
+                }
+            }
+            self.events.emit("cargo-output", message);
+        }
+    }
+}
+
+/// Builds the wasm with cargo, failing the build with [`Error::BuildFailed`] if cargo does.
+///
+/// Cargo is killed when the build is cancelled via [`crate::CancelToken::cancel`].
+pub struct CargoBuild;
+
+impl BuildStep for CargoBuild {
+    fn name(&self) -> &str {
+        "cargo"
+    }
+
+    fn run(
+        &self,
+        build: &mut BuildContext,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _span = tracing::info_span!("cargo", args = ?build.cargo_args).entered();
+        let mut child = build
+            .cargo_command()
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(Error::io(format!("Failed to run {}", build.cargo)))?;
+        let stdout = child.stdout.take().unwrap();
+        let child = Arc::new(Mutex::new(child));
+        let cancel_child = child.clone();
+        let _registration = build.cancel.on_cancel(move || {
+            cancel_child.lock().unwrap().kill().ok();
+        });
+        // Killing cargo closes its stdout, which ends this loop.
+        for line in BufReader::new(stdout).lines() {
+            let line = line.map_err(Error::io("Failed to read the output of cargo"))?;
+            build.cargo_message(&line);
+        }
+        let status = child
+            .lock()
+            .unwrap()
+            .wait()
+            .map_err(Error::io("Failed to wait for cargo"))?;
+        if !status.success() {
+            return Err(Error::BuildFailed {
+                exit_code: status.code(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    fn is_cargo_build(&self) -> bool {
+        true
+    }
+}
+
+/// A file added to the output directory via [`crate::RunWasm::output_file`] or [`crate::RunWasm::output_file_from`].
+#[derive(Clone)]
+pub(crate) enum OutputFile {
+    Contents(Vec<u8>),
+    /// Copied from a path, relative to the workspace root.
+    Copy(PathBuf),
+}
+
+/// Copies the assets directories and the files added via [`crate::RunWasm::output_file`] into the output directory.
+///
+/// Runs before [`Bindgen`] and [`RenderIndex`] by default so that assets cant overwrite the generated files.
+pub struct CopyAssets;
+
+impl BuildStep for CopyAssets {
+    fn name(&self) -> &str {
+        "assets"
+    }
+
+    fn run(
+        &self,
+        build: &mut BuildContext,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let dest = &build.info.dir;
+        for assets_dir in &build.assets_dirs {
+            let assets_dir = build.info.project_root.join(assets_dir);
+            assets::copy_dir(&assets_dir, dest).map_err(Error::io(format!(
+                "Failed to copy assets from {}",
+                assets_dir.display()
+            )))?;
+        }
+        for (path, file) in &build.output_files {
+            let dest = dest.join(path);
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(Error::io(format!("Failed to create {}", parent.display())))?;
+            }
+            match file {
+                OutputFile::Contents(contents) => std::fs::write(&dest, contents)
+                    .map_err(Error::io(format!("Failed to write {}", dest.display())))?,
+                OutputFile::Copy(source) => {
+                    let source = build.info.project_root.join(source);
+                    std::fs::copy(&source, &dest).map_err(Error::io(format!(
+                        "Failed to copy {} to {}",
+                        source.display(),
+                        dest.display()
+                    )))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs wasm-bindgen on [`BuildContext::wasm_path`], writing `<name>_bg.wasm` and `<name>.js` to the output directory.
+///
+/// The files are named after [`BuildInfo::name`] even when the wasm file is not, e.g. for a binary named differently from its package.
+/// For [`crate::RunWasm::pkg`] TypeScript declarations are written as well.
+/// When disabled via [`crate::RunWasm::bindgen`] the wasm is copied to `<name>.wasm` as is instead.
+pub struct Bindgen;
+
+impl BuildStep for Bindgen {
+    fn name(&self) -> &str {
+        "bindgen"
+    }
+
+    fn run(
+        &self,
+        build: &mut BuildContext,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _span = tracing::info_span!("bindgen", input = %build.wasm_path.display()).entered();
+        if !build.bindgen {
+            let dest = build.info.dir.join(format!("{}.wasm", build.info.name));
+            std::fs::copy(&build.wasm_path, &dest).map_err(Error::io(format!(
+                "Failed to copy {} to {}",
+                build.wasm_path.display(),
+                dest.display()
+            )))?;
+            return Ok(());
+        }
+        let mut bindgen = wasm_bindgen_cli_support::Bindgen::new();
+        match build.pkg {
+            Some(PkgTarget::Bundler) => bindgen.bundler(true),
+            _ => bindgen.web(true),
+        }
+        .map_err(|err| Error::Bindgen(format!("{:#}", err)))?
+        .typescript(build.pkg.is_some())
+        .omit_default_module_path(false)
+        .input_path(&build.wasm_path)
+        .out_name(&build.info.name)
+        .generate(&build.info.dir)
+        .map_err(|err| Error::Bindgen(format!("{:#}", err)))?;
+        Ok(())
+    }
+}
+
+/// Writes the index.html that loads the js generated by [`Bindgen`], see [`crate::render_index`].
+///
+/// Does nothing for [`crate::RunWasm::pkg`].
+/// Without wasm-bindgen, unless an html template was given, the page instantiates the wasm without any imports and calls its `main` or `_start` export,
+/// and is not written at all when the assets include an index.html, since loading such a wasm usually takes js of its own.
+pub struct RenderIndex;
+
+impl BuildStep for RenderIndex {
+    fn name(&self) -> &str {
+        "index"
+    }
+
+    fn run(
+        &self,
+        build: &mut BuildContext,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // A package is imported by other code rather than opened as a page.
+        if build.pkg.is_some() {
+            return Ok(());
+        }
+        if !build.bindgen && build.html.is_none() {
+            let from_assets = build
+                .assets_dirs
+                .iter()
+                .any(|x| build.info.project_root.join(x).join("index.html").is_file())
+                || build
+                    .output_files
+                    .iter()
+                    .any(|(path, _)| path == Path::new("index.html"));
+            if from_assets {
+                return Ok(());
+            }
+            let wasm_url = format!("./{}.wasm", build.info.name);
+            let instantiate = if build.streaming {
+                format!(
+                    "WebAssembly.instantiateStreaming(fetch(\"{}\"), {{}})",
+                    wasm_url
+                )
+            } else {
+                format!(
+                    "fetch(\"{}\").then((response) => response.arrayBuffer()).then((bytes) => WebAssembly.instantiate(bytes, {{}}))",
+                    wasm_url
+                )
+            };
+            let template =
+                include_str!("index.raw.template.html").replace("{{instantiate}}", &instantiate);
+            let html = render_index(
+                Some(&template),
+                &build.info.name,
+                &build.css,
+                build.canvas_id.as_deref(),
+                build.streaming,
+            );
+            std::fs::write(build.info.dir.join("index.html"), html)
+                .map_err(Error::io("Failed to write index.html"))?;
+            return Ok(());
+        }
+        let html = render_index(
+            build.html.as_deref(),
+            &build.info.name,
+            &build.css,
+            build.canvas_id.as_deref(),
+            build.streaming,
+        );
+        std::fs::write(build.info.dir.join("index.html"), html)
+            .map_err(Error::io("Failed to write index.html"))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+//! Suggestions for misspelled names, so that a typo is answered with what was probably meant.
+
+/// The `candidates` that are within a few edits of `name`, closest first.
+///
+/// Like cargo, a third of the length of `name` is allowed to differ so that short names dont match everything.
+pub(crate) fn similar<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Vec<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    let mut matches: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    matches.sort();
+    matches.dedup();
+    matches
+        .into_iter()
+        .map(|(_, candidate)| candidate)
+        .take(3)
+        .collect()
+}
+
+/// Formats the suggestions as a sentence to append to an error, empty when there are none.
+pub(crate) fn did_you_mean(suggestions: &[&str]) -> String {
+    match suggestions {
+        [] => String::new(),
+        [suggestion] => format!(", did you mean `{}`?", suggestion),
+        suggestions => format!(
+            ", did you mean one of {}?",
+            suggestions
+                .iter()
+                .map(|x| format!("`{}`", x))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// The Levenshtein distance, counting how many characters must be inserted, removed or replaced to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(a != *b);
+            current.push(replace.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
@@ -0,0 +1,110 @@
+//! Lays the `--dist` output out the way Trunk does, for `--trunk-dist`, so that deploy scripts written for `trunk build --release` keep working.
+//!
+//! Trunk writes to `dist` next to its Trunk.toml, names the js and wasm `<name>-<hash>.js` and `<name>-<hash>_bg.wasm`,
+//! and refers to them from the index.html by absolute urls under its `public_url`, with preload links for both in the head.
+//! The `dist` and `public_url` of an existing Trunk.toml, or the `TRUNK_BUILD_*` environment variables, are respected.
+
+use crate::Error;
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+/// The page rendered in place of the usual one, which exposes the bindings and announces that the wasm started like Trunk does.
+pub(crate) const INDEX_TEMPLATE: &str = include_str!("index.trunk.template.html");
+
+/// The settings of the `[build]` table of Trunk.toml that decide where the output goes and how it is linked.
+pub(crate) struct TrunkConfig {
+    pub dist: PathBuf,
+    /// Always ends in a `/`.
+    pub public_url: String,
+}
+
+/// Reads the Trunk.toml of the package in `package_dir`, or else of the workspace in `project_root`,
+/// falling back to Trunk's defaults of `dist` next to the package and a `public_url` of `/`.
+pub(crate) fn read_config(package_dir: &Path, project_root: &Path) -> Result<TrunkConfig, Error> {
+    let (dir, build) = match [package_dir, project_root]
+        .iter()
+        .map(|dir| dir.join("Trunk.toml"))
+        .find(|path| path.is_file())
+    {
+        Some(path) => {
+            let contents = std::fs::read_to_string(&path)
+                .map_err(Error::io(format!("Failed to read {}", path.display())))?;
+            let toml: Value = contents.parse().map_err(|err| {
+                Error::InvalidArgs(format!("Failed to parse {}: {}", path.display(), err))
+            })?;
+            let build = toml.get("build").cloned();
+            (path.parent().unwrap().to_path_buf(), build)
+        }
+        None => (package_dir.to_path_buf(), None),
+    };
+    let setting = |key: &str, env: &str| {
+        std::env::var(env).ok().or_else(|| {
+            build
+                .as_ref()
+                .and_then(|x| x.get(key))
+                .and_then(|x| x.as_str())
+                .map(|x| x.to_string())
+        })
+    };
+    let dist = dir.join(setting("dist", "TRUNK_BUILD_DIST").unwrap_or_else(|| "dist".to_string()));
+    let mut public_url =
+        setting("public_url", "TRUNK_BUILD_PUBLIC_URL").unwrap_or_else(|| "/".to_string());
+    if !public_url.ends_with('/') {
+        public_url.push('/');
+    }
+    Ok(TrunkConfig { dist, public_url })
+}
+
+/// Renames the js and wasm of `name` in `dir` like Trunk does, with the hash of the wasm in both names,
+/// and points the index.html at them under `public_url`, adding preload links for them to its head.
+///
+/// Returns the new paths of the wasm and js.
+pub(crate) fn rename(
+    dir: &Path,
+    name: &str,
+    public_url: &str,
+) -> Result<(PathBuf, PathBuf), Error> {
+    let wasm_name = format!("{}_bg.wasm", name);
+    let js_name = format!("{}.js", name);
+    let read = |path: &Path| {
+        std::fs::read(path).map_err(Error::io(format!("Failed to read {}", path.display())))
+    };
+    let write = |path: &Path, contents: &[u8]| {
+        std::fs::write(path, contents)
+            .map_err(Error::io(format!("Failed to write {}", path.display())))
+    };
+    let remove = |path: &Path| {
+        std::fs::remove_file(path)
+            .map_err(Error::io(format!("Failed to delete {}", path.display())))
+    };
+
+    let wasm = read(&dir.join(&wasm_name))?;
+    let hash = crate::dist::hash(&wasm);
+    let hashed_wasm_name = format!("{}-{}_bg.wasm", name, hash);
+    let hashed_js_name = format!("{}-{}.js", name, hash);
+    let js =
+        String::from_utf8_lossy(&read(&dir.join(&js_name))?).replace(&wasm_name, &hashed_wasm_name);
+    let js_url = format!("{}{}", public_url, hashed_js_name);
+    let wasm_url = format!("{}{}", public_url, hashed_wasm_name);
+
+    let index_path = dir.join("index.html");
+    let mut index = String::from_utf8_lossy(&read(&index_path)?)
+        .replace(&format!("./{}", js_name), &js_url)
+        .replace(&format!("./{}", wasm_name), &wasm_url);
+    let preload = format!(
+        "  <link rel=\"modulepreload\" href=\"{}\" crossorigin=\"anonymous\">\n  \
+         <link rel=\"preload\" href=\"{}\" crossorigin=\"anonymous\" as=\"fetch\" type=\"application/wasm\">\n",
+        js_url, wasm_url
+    );
+    match index.to_ascii_lowercase().find("</head>") {
+        Some(i) => index.insert_str(i, &preload),
+        None => index.insert_str(0, &preload),
+    }
+
+    write(&dir.join(&hashed_wasm_name), &wasm)?;
+    remove(&dir.join(&wasm_name))?;
+    write(&dir.join(&hashed_js_name), js.as_bytes())?;
+    remove(&dir.join(&js_name))?;
+    write(&index_path, index.as_bytes())?;
+    Ok((dir.join(hashed_wasm_name), dir.join(hashed_js_name)))
+}
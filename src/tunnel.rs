@@ -0,0 +1,117 @@
+//! Shares the dev server on a public https url by running a tunneling tool and picking the url out of its output.
+//!
+//! The tool has to be installed separately, which keeps us from depending on any one tunneling service.
+
+use crate::Verbosity;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// The commands run for the built in backends, `{port}` and `{url}` are replaced with the local port and url.
+/// The last item is text that the public url contains, to tell it apart from any other urls the tool prints.
+const BACKENDS: &[(&str, &str, &str)] = &[
+    (
+        "cloudflared",
+        "cloudflared tunnel --url {url}",
+        ".trycloudflare.com",
+    ),
+    ("ngrok", "ngrok http {port} --log stdout", "ngrok"),
+    (
+        "localhost.run",
+        "ssh -o StrictHostKeyChecking=accept-new -R 80:localhost:{port} nokey@localhost.run",
+        ".lhr.life",
+    ),
+];
+
+pub(crate) struct Tunnel {
+    child: Child,
+}
+
+impl Tunnel {
+    /// Starts `backend`, which is either the name of a built in backend or a command containing `{port}` or `{url}`.
+    ///
+    /// The public url, followed by `url_path`, is printed once the tool reports it.
+    pub fn start(
+        backend: &str,
+        port: u16,
+        url: &str,
+        url_path: &str,
+        verbosity: Verbosity,
+    ) -> Result<Self, String> {
+        let (command, url_hint) = match BACKENDS.iter().find(|(name, _, _)| *name == backend) {
+            Some((_, command, url_hint)) => (*command, *url_hint),
+            None if backend.contains("{port}") || backend.contains("{url}") => (backend, ""),
+            None => {
+                let names: Vec<&str> = BACKENDS.iter().map(|(name, _, _)| *name).collect();
+                return Err(format!(
+                    "Unknown tunnel {:?}, expected one of {} or a command containing {{port}} or {{url}}",
+                    backend,
+                    names.join(", ")
+                ));
+            }
+        };
+        let command = command
+            .replace("{port}", &port.to_string())
+            .replace("{url}", url);
+        let mut parts = command.split_whitespace();
+        let program = parts.next().unwrap_or_default();
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| format!("Failed to run the tunnel command {:?}: {}", program, err))?;
+
+        let found = Arc::new(Mutex::new(false));
+        let stdout = child.stdout.take().unwrap();
+        let stderr = child.stderr.take().unwrap();
+        watch_output(stdout, url_hint, url_path, found.clone(), verbosity);
+        watch_output(stderr, url_hint, url_path, found, verbosity);
+        Ok(Tunnel { child })
+    }
+
+    pub fn stop(mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+fn watch_output<R: Read + Send + 'static>(
+    output: R,
+    url_hint: &'static str,
+    url_path: &str,
+    found: Arc<Mutex<bool>>,
+    verbosity: Verbosity,
+) {
+    let url_path = url_path.trim_start_matches('/').to_string();
+    thread::spawn(move || {
+        for line in BufReader::new(output).lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => return,
+            };
+            if verbosity >= Verbosity::Verbose {
+                println!("[tunnel] {}", line);
+            }
+            let mut found = found.lock().unwrap();
+            if !*found {
+                if let Some(url) = find_url(&line, url_hint) {
+                    *found = true;
+                    println!(
+                        "Sharing publicly on {}/{}",
+                        url.trim_end_matches('/'),
+                        url_path
+                    );
+                }
+            }
+        }
+    });
+}
+
+fn find_url<'a>(line: &'a str, url_hint: &str) -> Option<&'a str> {
+    line.split(|c: char| c.is_whitespace() || c == '|' || c == '"' || c == '=')
+        .map(|word| word.trim_end_matches(&[',', '.', ')'][..]))
+        .find(|word| word.starts_with("https://") && word.contains(url_hint))
+}